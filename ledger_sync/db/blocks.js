@@ -8,6 +8,8 @@ const db = require('./')
 
 const stateTables = [
   'participants',
+  'organizations',
+  'multisigPolicies',
   'records',
   'tables',
   'properties',
@@ -63,6 +63,15 @@ const addParticipant = (participant, blockNum) => {
   return addBlockState('participants', 'publicKey', participant.publicKey, participant, blockNum)
 }
 
+const addOrganization = (organization, blockNum) => {
+  return addBlockState('organizations', 'organizationId',
+    organization.organizationId, organization, blockNum)
+}
+
+const addMultisigPolicy = (policy, blockNum) => {
+  return addBlockState('multisigPolicies', 'policyId', policy.policyId, policy, blockNum)
+}
+
 const addRecord = (record, blockNum) => {
   return addBlockState('records', 'recordId', record.recordId, record, blockNum)
 }
@@ -139,6 +148,8 @@ const addExchange = (exchange, blockNum) => {
 
 module.exports = {
   addParticipant,
+  addOrganization,
+  addMultisigPolicy,
   addRecord,
   addTable,
   addProperty,
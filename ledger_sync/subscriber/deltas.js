@@ -33,6 +33,11 @@ const deltaQueue = {
   }
 }
 
+// The email index, key aliases, role assignments and pending multisig
+// approvals only serve the transaction processor; emails and signing keys
+// are synced with the Participants
+const INDEX_PREFIXES = ['ac', 'ca', 'cc', 'fc']
+
 const getProtoName = address => {
   const typePrefix = address.slice(6, 8)
   if (typePrefix === 'ea') {
@@ -43,6 +48,8 @@ const getProtoName = address => {
   const names = {
     ae: 'Participant',
     aa: 'Proposal',
+    af: 'Organization',
+    fa: 'MultisigPolicy',
     ce: 'Exchange',
     ec: 'Record',
     ee: 'Table'
@@ -90,7 +97,10 @@ const entryAdder = block => change => {
 
 const handle = (block, changes) => {
   deltaQueue.add(() => {
-    const [ pageChanges, otherChanges ] = _.partition(changes, change => {
+    const entityChanges = changes.filter(change => {
+      return !INDEX_PREFIXES.includes(change.address.slice(6, 8))
+    })
+    const [ pageChanges, otherChanges ] = _.partition(entityChanges, change => {
       return getProtoName(change.address) === 'PropertyPage'
     })
 
@@ -18,7 +18,7 @@ const {
 const deltas = require('./deltas')
 const config = require('../system/config')
 
-const PREFIX = '3bfc81'
+const PREFIX = config.NAMESPACE
 const NULL_BLOCK_ID = '0000000000000000'
 const VALIDATOR_URL = config.VALIDATOR_URL
 const stream = new Stream(VALIDATOR_URL)
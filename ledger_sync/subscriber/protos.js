@@ -25,6 +25,14 @@ const compile = () => {
       'Participant',
       'ParticipantContainer'
     ]),
+    loadProtos('multisig.proto', [
+      'MultisigPolicy',
+      'MultisigPolicyContainer'
+    ]),
+    loadProtos('organization.proto', [
+      'Organization',
+      'OrganizationContainer'
+    ]),
     loadProtos('property.proto', [
       'Property',
       'PropertyContainer',
@@ -3,6 +3,8 @@
 
 'use strict'
 
+const { createHash } = require('crypto')
+
 const loadConfig = (defaultValue = {}) => {
   try {
     return require('../config.json')
@@ -26,5 +28,10 @@ initConfigValue('VALIDATOR_URL', 'tcp://localhost:4004')
 initConfigValue('DB_HOST', 'localhost')
 initConfigValue('DB_PORT', 28015)
 initConfigValue('DB_NAME', 'dgc_REST_api')
+initConfigValue('FAMILY_NAME', 'dgc_REST_api')
+initConfigValue('NAMESPACE', createHash('sha512')
+  .update(config.FAMILY_NAME)
+  .digest('hex')
+  .slice(0, 6))
 
 module.exports = config
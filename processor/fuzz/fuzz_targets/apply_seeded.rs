@@ -0,0 +1,16 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_main]
+extern crate dgc_rest_api_tp_fuzz;
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use dgc_rest_api_tp_fuzz::{check_deterministic, seeded_context, signer};
+
+// The first byte picks the signer, the rest is the payload.
+fuzz_target!(|data: &[u8]| {
+    if let Some((selector, payload)) = data.split_first() {
+        check_deterministic(&seeded_context(), signer(*selector), payload);
+    }
+});
@@ -0,0 +1,17 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_main]
+extern crate dgc_rest_api_tp;
+extern crate dgc_rest_api_tp_fuzz;
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use dgc_rest_api_tp::context::MemoryContext;
+use dgc_rest_api_tp_fuzz::{check_deterministic, OWNER};
+
+// Payload decoding runs before any state is read, so an empty context
+// exercises every decode and validation path of PayloadDGC.
+fuzz_target!(|data: &[u8]| {
+    check_deterministic(&MemoryContext::new(), OWNER, data);
+});
@@ -0,0 +1,25 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+#![no_main]
+extern crate dgc_rest_api_tp;
+extern crate dgc_rest_api_tp_fuzz;
+#[macro_use]
+extern crate libfuzzer_sys;
+
+use dgc_rest_api_tp::handler::validate_struct_values;
+use dgc_rest_api_tp_fuzz::decode_struct_input;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((schema, value)) = decode_struct_input(data) {
+        let first = format!(
+            "{:?}",
+            validate_struct_values(&value.struct_values, &schema.struct_properties)
+        );
+        let second = format!(
+            "{:?}",
+            validate_struct_values(&value.struct_values, &schema.struct_properties)
+        );
+        assert_eq!(first, second);
+    }
+});
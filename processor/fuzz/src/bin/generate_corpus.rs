@@ -0,0 +1,54 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Writes the starting corpus of every fuzz target from valid payloads.
+//! Run from the fuzz directory with `cargo run --bin generate_corpus`.
+
+extern crate dgc_rest_api_tp_fuzz;
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+use dgc_rest_api_tp_fuzz::*;
+
+fn main() {
+    let mut seeds = seed_payloads();
+    let samples = sample_payloads();
+
+    let payloads: Vec<Vec<u8>> = seeds
+        .iter()
+        .chain(samples.iter())
+        .map(|&(_, ref payload)| payload.clone())
+        .collect();
+
+    seeds.extend(samples);
+    let signed: Vec<Vec<u8>> = seeds
+        .into_iter()
+        .map(|(signer, payload)| {
+            let mut data = vec![if signer == OWNER { 0 } else { 1 }];
+            data.extend(payload);
+            data
+        })
+        .collect();
+
+    let structs = vec![encode_struct_input(&struct_schema(), &struct_value())];
+
+    let result = write_corpus("decode_payload", &payloads)
+        .and_then(|_| write_corpus("apply_seeded", &signed))
+        .and_then(|_| write_corpus("validate_struct", &structs));
+    if let Err(err) = result {
+        eprintln!("Unable to write corpus: {}", err);
+        process::exit(1);
+    }
+}
+
+fn write_corpus(target: &str, inputs: &[Vec<u8>]) -> Result<(), std::io::Error> {
+    let directory = Path::new("corpus").join(target);
+    fs::create_dir_all(&directory)?;
+    for (index, input) in inputs.iter().enumerate() {
+        File::create(directory.join(format!("valid-{:02}", index)))?.write_all(input)?;
+    }
+    Ok(())
+}
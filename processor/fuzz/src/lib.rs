@@ -0,0 +1,262 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Shared state and checks for the dgc-REST-api fuzz targets.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+
+use protobuf::Message;
+use protobuf::RepeatedField;
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::{make_payload, PayloadAction, FAMILY_VERSION};
+
+/// The participant who owns the seeded record.
+pub const OWNER: &str = "021111111111111111111111111111111111111111111111111111111111111111";
+/// A second participant, the receiver of the seeded proposal.
+pub const OTHER: &str = "032222222222222222222222222222222222222222222222222222222222222222";
+
+pub const TABLE: &str = "fuzz";
+pub const RECORD_ID: &str = "fuzz-001";
+pub const PROPOSAL_ID: &str = "fuzz-proposal-001";
+
+const SIGNATURE: &str = "fuzz";
+const TIMESTAMP: u64 = 1_500_000_000;
+
+thread_local! {
+    static SEEDED: MemoryContext = build_seeded_context();
+}
+
+/// Picks the signer of a fuzzed transaction from its first input byte.
+pub fn signer(selector: u8) -> &'static str {
+    if selector % 2 == 0 {
+        OWNER
+    } else {
+        OTHER
+    }
+}
+
+/// A copy of the state built from `seed_payloads`.
+pub fn seeded_context() -> MemoryContext {
+    SEEDED.with(|context| context.clone())
+}
+
+fn build_seeded_context() -> MemoryContext {
+    let handler = TransactionHandlerDGC::new();
+    let mut context = MemoryContext::new();
+    for (signer, payload) in seed_payloads() {
+        let request = make_process_request(&Family::default(), FAMILY_VERSION, signer, &payload, SIGNATURE);
+        if let Err(err) = context.apply(&handler, &request) {
+            panic!("Seed transaction was rejected: {:?}", err);
+        }
+    }
+    context
+}
+
+/// Applies the payload to two copies of the context. Both runs must agree
+/// on the result and on the state written.
+pub fn check_deterministic(context: &MemoryContext, signer: &str, payload: &[u8]) {
+    let handler = TransactionHandlerDGC::new();
+    let request = make_process_request(&Family::default(), FAMILY_VERSION, signer, payload, SIGNATURE);
+
+    let mut first = context.clone();
+    let mut second = context.clone();
+    let first_result = format!("{:?}", first.apply(&handler, &request));
+    let second_result = format!("{:?}", second.apply(&handler, &request));
+
+    assert_eq!(first_result, second_result);
+    assert_eq!(first.state(), second.state());
+}
+
+/// Builds the payloads that seed the fuzzed state: two participants, a
+/// table with a property of every data type, a record, and an open proposal.
+pub fn seed_payloads() -> Vec<(&'static str, Vec<u8>)> {
+    let mut owner = payload::CreateParticipantAction::new();
+    owner.set_name(String::from("owner"));
+    let mut other = payload::CreateParticipantAction::new();
+    other.set_name(String::from("other"));
+
+    let mut create_table = payload::CreateTableAction::new();
+    create_table.set_name(TABLE.to_string());
+    create_table.set_properties(RepeatedField::from_vec(vec![
+        schema("weight", property::PropertySchema_DataType::NUMBER),
+        schema("species", property::PropertySchema_DataType::STRING),
+        schema("photo", property::PropertySchema_DataType::BYTES),
+        schema("alive", property::PropertySchema_DataType::BOOLEAN),
+        schema("grade", property::PropertySchema_DataType::ENUM),
+        schema("position", property::PropertySchema_DataType::LOCATION),
+        struct_schema(),
+    ]));
+
+    let mut create_record = payload::CreateRecordAction::new();
+    create_record.set_record_id(RECORD_ID.to_string());
+    create_record.set_table(TABLE.to_string());
+    create_record.set_properties(RepeatedField::from_vec(vec![
+        number_value("weight", 1500),
+        struct_value(),
+    ]));
+
+    let mut create_proposal = payload::CreateProposalAction::new();
+    create_proposal.set_proposal_id(PROPOSAL_ID.to_string());
+    create_proposal.set_role(proposal::Proposal_Role::transferOwnership);
+    create_proposal.set_receiving_participant(OTHER.to_string());
+    create_proposal.set_record_id(RECORD_ID.to_string());
+
+    vec![
+        (OWNER, encode(PayloadAction::CreateParticipant(owner))),
+        (OTHER, encode(PayloadAction::CreateParticipant(other))),
+        (OWNER, encode(PayloadAction::CreateTable(create_table))),
+        (OWNER, encode(PayloadAction::CreateRecord(create_record))),
+        (OWNER, encode(PayloadAction::CreateProposal(create_proposal))),
+    ]
+}
+
+/// Payloads which are valid against the seeded state, used as the starting
+/// corpus for the apply target.
+pub fn sample_payloads() -> Vec<(&'static str, Vec<u8>)> {
+    let mut update = payload::UpdatePropertiesAction::new();
+    update.set_record_id(RECORD_ID.to_string());
+    update.set_properties(RepeatedField::from_vec(vec![
+        number_value("weight", 1750),
+        struct_value(),
+    ]));
+
+    let mut accept = payload::AnswerProposalAction::new();
+    accept.set_proposal_id(PROPOSAL_ID.to_string());
+    accept.set_role(proposal::Proposal_Role::transferOwnership);
+    accept.set_response(payload::AnswerProposalAction_Response::ACCEPT);
+    accept.set_receiving_participant(OTHER.to_string());
+    accept.set_record_id(RECORD_ID.to_string());
+
+    let mut cancel = accept.clone();
+    cancel.set_response(payload::AnswerProposalAction_Response::CANCEL);
+
+    let mut custodian = payload::CreateProposalAction::new();
+    custodian.set_proposal_id(String::from("fuzz-proposal-002"));
+    custodian.set_role(proposal::Proposal_Role::transferCustodianship);
+    custodian.set_receiving_participant(OTHER.to_string());
+    custodian.set_record_id(RECORD_ID.to_string());
+
+    let mut reporter = payload::CreateProposalAction::new();
+    reporter.set_proposal_id(String::from("fuzz-proposal-003"));
+    reporter.set_role(proposal::Proposal_Role::authorizeReporter);
+    reporter.set_receiving_participant(OTHER.to_string());
+    reporter.set_record_id(RECORD_ID.to_string());
+    reporter.set_properties(RepeatedField::from_vec(vec![String::from("weight")]));
+
+    let mut finalize = payload::FinalizeRecordAction::new();
+    finalize.set_record_id(RECORD_ID.to_string());
+
+    let mut revoke = payload::RevokeReporterAction::new();
+    revoke.set_record_id(RECORD_ID.to_string());
+    revoke.set_reporter_id(OWNER.to_string());
+    revoke.set_properties(RepeatedField::from_vec(vec![String::from("weight")]));
+
+    vec![
+        (OWNER, encode(PayloadAction::UpdateProperties(update))),
+        (OTHER, encode(PayloadAction::AnswerProposal(accept))),
+        (OWNER, encode(PayloadAction::AnswerProposal(cancel))),
+        (OWNER, encode(PayloadAction::CreateProposal(custodian))),
+        (OWNER, encode(PayloadAction::CreateProposal(reporter))),
+        (OWNER, encode(PayloadAction::FinalizeRecord(finalize))),
+        (OWNER, encode(PayloadAction::RevokeReporter(revoke))),
+    ]
+}
+
+/// The struct schema of the seeded table: a struct holding a string and a
+/// nested struct.
+pub fn struct_schema() -> property::PropertySchema {
+    let mut inner = schema("box", property::PropertySchema_DataType::STRUCT);
+    inner.set_struct_properties(RepeatedField::from_vec(vec![schema(
+        "height",
+        property::PropertySchema_DataType::NUMBER,
+    )]));
+
+    let mut outer = schema("shipment", property::PropertySchema_DataType::STRUCT);
+    outer.set_struct_properties(RepeatedField::from_vec(vec![
+        schema("carrier", property::PropertySchema_DataType::STRING),
+        inner,
+    ]));
+    outer
+}
+
+/// A value matching `struct_schema`.
+pub fn struct_value() -> property::PropertyValue {
+    let mut carrier = property::PropertyValue::new();
+    carrier.set_name(String::from("carrier"));
+    carrier.set_data_type(property::PropertySchema_DataType::STRING);
+    carrier.set_string_value(String::from("dgc"));
+
+    let mut inner = property::PropertyValue::new();
+    inner.set_name(String::from("box"));
+    inner.set_data_type(property::PropertySchema_DataType::STRUCT);
+    inner.set_struct_values(RepeatedField::from_vec(vec![number_value("height", 20)]));
+
+    let mut outer = property::PropertyValue::new();
+    outer.set_name(String::from("shipment"));
+    outer.set_data_type(property::PropertySchema_DataType::STRUCT);
+    outer.set_struct_values(RepeatedField::from_vec(vec![carrier, inner]));
+    outer
+}
+
+/// Splits the input of the struct validation target into a schema and a
+/// value. The first two bytes give the length of the encoded schema.
+pub fn decode_struct_input(
+    data: &[u8],
+) -> Option<(property::PropertySchema, property::PropertyValue)> {
+    if data.len() < 2 {
+        return None;
+    }
+    let length = ((data[0] as usize) << 8 | data[1] as usize).min(data.len() - 2);
+    let (schema_bytes, value_bytes) = data[2..].split_at(length);
+    let schema = protobuf::parse_from_bytes(schema_bytes).ok()?;
+    let value = protobuf::parse_from_bytes(value_bytes).ok()?;
+    Some((schema, value))
+}
+
+/// The inverse of `decode_struct_input`.
+pub fn encode_struct_input(
+    schema: &property::PropertySchema,
+    value: &property::PropertyValue,
+) -> Vec<u8> {
+    let schema_bytes = schema.write_to_bytes().unwrap_or_default();
+    let value_bytes = value.write_to_bytes().unwrap_or_default();
+
+    let mut data = vec![(schema_bytes.len() >> 8) as u8, schema_bytes.len() as u8];
+    data.extend(schema_bytes);
+    data.extend(value_bytes);
+    data
+}
+
+fn schema(name: &str, data_type: property::PropertySchema_DataType) -> property::PropertySchema {
+    let mut schema = property::PropertySchema::new();
+    schema.set_name(name.to_string());
+    schema.set_data_type(data_type);
+    match data_type {
+        property::PropertySchema_DataType::NUMBER => schema.set_number_exponent(-3),
+        property::PropertySchema_DataType::ENUM => schema.set_enum_options(
+            RepeatedField::from_vec(vec![String::from("A"), String::from("B")]),
+        ),
+        _ => (),
+    }
+    schema
+}
+
+fn number_value(name: &str, number: i64) -> property::PropertyValue {
+    let mut value = property::PropertyValue::new();
+    value.set_name(name.to_string());
+    value.set_data_type(property::PropertySchema_DataType::NUMBER);
+    value.set_number_value(number);
+    value
+}
+
+fn encode(action: PayloadAction) -> Vec<u8> {
+    make_payload(action, TIMESTAMP)
+        .ok()
+        .and_then(|payload| payload.write_to_bytes().ok())
+        .unwrap_or_default()
+}
@@ -2,22 +2,106 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crypto::digest::Digest;
-use crypto::sha2::Sha512;
+use crypto::sha2::{Sha256, Sha512};
 
-const FAMILY_NAME: &str = "dgc_REST_api";
-const PARTICIPANT: &str = "ae";
-const PROPERTY: &str = "ea";
-const PROPOSAL: &str = "aa";
-const RECORD: &str = "ec";
-const TABLE: &str = "ee";
-const EXCHANGE: &str = "ce";
+pub const FAMILY_NAME: &str = "dgc_REST_api";
+pub const PARTICIPANT: &str = "ae";
+pub const PROPERTY: &str = "ea";
+pub const PROPOSAL: &str = "aa";
+pub const RECORD: &str = "ec";
+pub const TABLE: &str = "ee";
+pub const EXCHANGE: &str = "ce";
+pub const EMAIL_INDEX: &str = "ac";
+pub const KEY_ALIAS: &str = "ca";
+pub const ROLE: &str = "cc";
+pub const ORGANIZATION: &str = "af";
+pub const MULTISIG_POLICY: &str = "fa";
+pub const PENDING_APPROVAL: &str = "fc";
 
-pub fn get_dgc_rest_api_prefix() -> String {
+pub const SETTINGS_NAMESPACE: &str = "000000";
+const SETTINGS_MAX_KEY_PARTS: usize = 4;
+const SETTINGS_ADDRESS_PART_SIZE: usize = 16;
+
+pub const BLOCK_INFO_NAMESPACE: &str = "00b10c";
+
+/// The namespace derived from a family name: the first 6 characters of its
+/// sha512 hash.
+pub fn get_dgc_rest_api_prefix(family_name: &str) -> String {
     let mut sha = Sha512::new();
-    sha.input_str(&FAMILY_NAME);
+    sha.input_str(family_name);
     sha.result_str()[..6].to_string()
 }
 
+/// The transaction family a processor handles, and the namespace its state
+/// is stored under. Deployments which share a validator network each use
+/// their own family.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Family {
+    name: String,
+    namespace: String,
+}
+
+impl Default for Family {
+    fn default() -> Family {
+        Family::new(FAMILY_NAME)
+    }
+}
+
+impl Family {
+    /// The family `name`, with the namespace derived from it.
+    pub fn new(name: &str) -> Family {
+        Family {
+            name: name.to_string(),
+            namespace: get_dgc_rest_api_prefix(name),
+        }
+    }
+
+    /// The family `name` stored under `namespace`, which must be 6 lowercase
+    /// hex characters and not one of the sawtooth namespaces this family
+    /// reads.
+    pub fn with_namespace(name: &str, namespace: &str) -> Result<Family, String> {
+        if name.is_empty() {
+            return Err(String::from("Family name cannot be empty"));
+        }
+        if namespace.len() != 6
+            || !namespace
+                .chars()
+                .all(|c| c.is_digit(16) && !c.is_uppercase())
+        {
+            return Err(format!(
+                "Namespace must be 6 lowercase hex characters: {}",
+                namespace
+            ));
+        }
+        if namespace == SETTINGS_NAMESPACE || namespace == BLOCK_INFO_NAMESPACE {
+            return Err(format!("Namespace {} is reserved", namespace));
+        }
+        Ok(Family {
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+        })
+    }
+
+    /// The family from optional command line or configuration values. The
+    /// name defaults to `FAMILY_NAME` and the namespace to the one derived
+    /// from the name.
+    pub fn from_options(name: Option<&str>, namespace: Option<&str>) -> Result<Family, String> {
+        let name = name.unwrap_or(FAMILY_NAME);
+        match namespace {
+            Some(namespace) => Family::with_namespace(name, namespace),
+            None => Family::with_namespace(name, &get_dgc_rest_api_prefix(name)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+}
+
 pub fn hash(to_hash: &str, num: usize) -> String {
     let mut sha = Sha512::new();
     sha.input_str(to_hash);
@@ -29,34 +113,95 @@ pub fn hash(to_hash: &str, num: usize) -> String {
     hash.to_string()
 }
 
-pub fn make_participant_address(identifier: &str) -> String {
-    get_dgc_rest_api_prefix() + &PARTICIPANT + &hash(identifier, 62)
+pub fn make_participant_address(namespace: &str, identifier: &str) -> String {
+    namespace.to_string() + &PARTICIPANT + &hash(identifier, 62)
 }
 
-pub fn make_record_address(record_id: &str) -> String {
-    get_dgc_rest_api_prefix() + &RECORD + &hash(record_id, 62)
+pub fn make_record_address(namespace: &str, record_id: &str) -> String {
+    namespace.to_string() + &RECORD + &hash(record_id, 62)
 }
 
-pub fn make_table_address(name: &str) -> String {
-    get_dgc_rest_api_prefix() + &TABLE + &hash(name, 62)
+pub fn make_table_address(namespace: &str, name: &str) -> String {
+    namespace.to_string() + &TABLE + &hash(name, 62)
 }
 
-pub fn make_property_address(record_id: &str, property_name: &str, page: u32) -> String {
-    make_property_address_range(record_id) + &hash(property_name, 22) + &num_to_page_number(page)
+pub fn make_property_address(namespace: &str, record_id: &str, property_name: &str, page: u32) -> String {
+    make_property_address_range(namespace, record_id) + &hash(property_name, 22) + &num_to_page_number(page)
 }
 
-pub fn make_property_address_range(record_id: &str) -> String {
-    get_dgc_rest_api_prefix() + &PROPERTY + &hash(record_id, 36)
+pub fn make_property_address_range(namespace: &str, record_id: &str) -> String {
+    namespace.to_string() + &PROPERTY + &hash(record_id, 36)
 }
 
 pub fn num_to_page_number(page: u32) -> String {
     format!("{:01$x}", page, 4)
 }
 
-pub fn make_proposal_address(proposal_id: &str) -> String {
-    get_dgc_rest_api_prefix() + &PROPOSAL + &hash(proposal_id, 62)
+pub fn make_proposal_address(namespace: &str, proposal_id: &str) -> String {
+    namespace.to_string() + &PROPOSAL + &hash(proposal_id, 62)
+}
+
+pub fn make_exchange_address(namespace: &str, buy_proposal_id: &str, sell_proposal_id: &str) -> String {
+    namespace.to_string() + &EXCHANGE + &hash(buy_proposal_id, 31) + &hash(sell_proposal_id, 31)
+}
+
+/// The form emails are compared and indexed in: trimmed and lowercased.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// The address of the index entry recording which participant uses `email`.
+pub fn make_email_index_address(namespace: &str, email: &str) -> String {
+    namespace.to_string() + &EMAIL_INDEX + &hash(&normalize_email(email), 62)
+}
+
+/// The address of the alias recording which participant `public_key` signs
+/// for after a key rotation.
+pub fn make_key_alias_address(namespace: &str, public_key: &str) -> String {
+    namespace.to_string() + &KEY_ALIAS + &hash(public_key, 62)
+}
+
+/// The address of the roles assigned to the participant `public_key`.
+pub fn make_role_address(namespace: &str, public_key: &str) -> String {
+    namespace.to_string() + &ROLE + &hash(public_key, 62)
+}
+
+pub fn make_organization_address(namespace: &str, organization_id: &str) -> String {
+    namespace.to_string() + &ORGANIZATION + &hash(organization_id, 62)
+}
+
+pub fn make_multisig_policy_address(namespace: &str, policy_id: &str) -> String {
+    namespace.to_string() + &MULTISIG_POLICY + &hash(policy_id, 62)
+}
+
+/// The address of the approvals collected for the action `approval_id`.
+pub fn make_pending_approval_address(namespace: &str, approval_id: &str) -> String {
+    namespace.to_string() + &PENDING_APPROVAL + &hash(approval_id, 62)
+}
+
+/// The address of an on-chain setting, as computed by the sawtooth settings
+/// family: the key is split on "." into at most four parts, each hashed.
+pub fn make_setting_address(key: &str) -> String {
+    let mut parts: Vec<&str> = key.splitn(SETTINGS_MAX_KEY_PARTS, '.').collect();
+    while parts.len() < SETTINGS_MAX_KEY_PARTS {
+        parts.push("");
+    }
+
+    let mut address = SETTINGS_NAMESPACE.to_string();
+    for part in parts {
+        let mut sha = Sha256::new();
+        sha.input_str(part);
+        address.push_str(&sha.result_str()[..SETTINGS_ADDRESS_PART_SIZE]);
+    }
+    address
+}
+
+/// The address of the BlockInfo family's config, which holds the number of
+/// the latest block.
+pub fn make_block_info_config_address() -> String {
+    format!("{}01{}", BLOCK_INFO_NAMESPACE, "0".repeat(62))
 }
 
-pub fn make_exchange_address(buy_proposal_id: &str, sell_proposal_id: &str) -> String {
-    get_dgc_rest_api_prefix() + &EXCHANGE + &hash(buy_proposal_id, 31) + &hash(sell_proposal_id, 31)
+pub fn make_block_info_address(block_num: u64) -> String {
+    format!("{}00{:062x}", BLOCK_INFO_NAMESPACE, block_num)
 }
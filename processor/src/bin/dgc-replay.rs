@@ -0,0 +1,265 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+#[macro_use]
+extern crate clap;
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate rustc_serialize;
+extern crate sawtooth_sdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
+
+use clap::{App, Arg};
+use rustc_serialize::base64::FromBase64;
+use serde_json::Value;
+
+use sawtooth_sdk::messages::batch::BatchList;
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::decode::decode_state;
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::transaction::{parse_payload, FAMILY_VERSION};
+
+/// One transaction of a replay file. The payload is a base64 encoded
+/// payload of `family_version`; the timestamp is when the transaction was
+/// committed.
+#[derive(Debug, Deserialize)]
+struct ReplayTransaction {
+    signer: String,
+    payload: String,
+    #[serde(default)]
+    timestamp: u64,
+    #[serde(default = "default_family_version")]
+    family_version: String,
+}
+
+fn default_family_version() -> String {
+    FAMILY_VERSION.to_string()
+}
+
+struct Transaction {
+    family_version: String,
+    signer: String,
+    payload: Vec<u8>,
+    signature: String,
+    timestamp: u64,
+}
+
+fn main() {
+    let matches = App::new("dgc-replay")
+        .version(crate_version!())
+        .about("Replays dgc-REST-api transactions against in-memory state")
+        .arg(
+            Arg::with_name("transactions")
+                .required(true)
+                .help("JSON file of transactions, or a batch list with --batches"),
+        )
+        .arg(
+            Arg::with_name("batches")
+                .long("batches")
+                .help("read the transactions from a sawtooth BatchList file"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("file to write the results and final state to (default: stdout)"),
+        )
+        .arg(
+            Arg::with_name("expected")
+                .long("expected")
+                .takes_value(true)
+                .help("golden file to compare the output with"),
+        )
+        .arg(
+            Arg::with_name("family-name")
+                .long("family-name")
+                .takes_value(true)
+                .help("transaction family name (default: dgc_REST_api)"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .takes_value(true)
+                .help("state namespace (default: derived from the family name)"),
+        )
+        .get_matches();
+
+    let family = match Family::from_options(
+        matches.value_of("family-name"),
+        matches.value_of("namespace"),
+    ) {
+        Ok(family) => family,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let path = matches.value_of("transactions").unwrap_or("");
+    let transactions = if matches.is_present("batches") {
+        read_batches(path)
+    } else {
+        read_transactions(path)
+    };
+    let transactions = match transactions {
+        Ok(transactions) => transactions,
+        Err(err) => {
+            eprintln!("Unable to read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let report = match replay(&family, transactions) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("Unable to decode final state: {}", err);
+            process::exit(1);
+        }
+    };
+    let output = serde_json::to_string_pretty(&report).unwrap_or_default();
+
+    match matches.value_of("output") {
+        Some(output_path) => {
+            if let Err(err) = File::create(output_path).and_then(|mut f| f.write_all(output.as_bytes())) {
+                eprintln!("Unable to write {}: {}", output_path, err);
+                process::exit(1);
+            }
+        }
+        None => println!("{}", output),
+    }
+
+    if let Some(expected_path) = matches.value_of("expected") {
+        let expected: Value = match read_file(expected_path)
+            .and_then(|contents| serde_json::from_str(&contents).map_err(|err| format!("{}", err)))
+        {
+            Ok(expected) => expected,
+            Err(err) => {
+                eprintln!("Unable to read {}: {}", expected_path, err);
+                process::exit(1);
+            }
+        };
+        if expected != report {
+            eprintln!("Output differs from {}", expected_path);
+            process::exit(2);
+        }
+    }
+}
+
+/// Runs every transaction in order and returns the outcome of each along
+/// with the decoded final state.
+fn replay(family: &Family, transactions: Vec<Transaction>) -> Result<Value, String> {
+    let mut handler = TransactionHandlerDGC::new();
+    handler.set_family(family.clone());
+    let mut context = MemoryContext::new();
+
+    let mut results = Vec::new();
+    for (index, transaction) in transactions.iter().enumerate() {
+        let request = make_process_request(
+            family,
+            &transaction.family_version,
+            &transaction.signer,
+            &transaction.payload,
+            &transaction.signature,
+        );
+        let action = match parse_payload(&transaction.family_version, &transaction.payload) {
+            Some(Ok((action, _))) => action.name(),
+            _ => "UNKNOWN",
+        };
+
+        let mut result = json!({
+            "index": index,
+            "signer": transaction.signer,
+            "action": action,
+            "timestamp": transaction.timestamp,
+        });
+        match context.apply(&handler, &request) {
+            Ok(()) => result["status"] = json!("accepted"),
+            Err(ApplyError::InvalidTransaction(msg)) => {
+                result["status"] = json!("rejected");
+                result["error"] = json!(msg);
+            }
+            Err(ApplyError::InternalError(msg)) => {
+                result["status"] = json!("internal_error");
+                result["error"] = json!(msg);
+            }
+        }
+        results.push(result);
+    }
+
+    let state = decode_state(family.namespace(), context.state()).map_err(|err| format!("{}", err))?;
+    Ok(json!({
+        "results": results,
+        "state": state,
+    }))
+}
+
+fn read_transactions(path: &str) -> Result<Vec<Transaction>, String> {
+    let replay_transactions: Vec<ReplayTransaction> =
+        serde_json::from_str(&read_file(path)?).map_err(|err| format!("{}", err))?;
+
+    let mut transactions = Vec::new();
+    for (index, transaction) in replay_transactions.into_iter().enumerate() {
+        let payload = transaction
+            .payload
+            .from_base64()
+            .map_err(|err| format!("transaction {}: invalid payload: {}", index, err))?;
+        transactions.push(Transaction {
+            family_version: transaction.family_version,
+            signer: transaction.signer,
+            payload: payload,
+            signature: format!("{:0128x}", index),
+            timestamp: transaction.timestamp,
+        });
+    }
+    Ok(transactions)
+}
+
+fn read_batches(path: &str) -> Result<Vec<Transaction>, String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .map_err(|err| format!("{}", err))?;
+    let batch_list: BatchList =
+        protobuf::parse_from_bytes(&bytes).map_err(|err| format!("{}", err))?;
+
+    let mut transactions = Vec::new();
+    for batch in batch_list.get_batches() {
+        for transaction in batch.get_transactions() {
+            let header: TransactionHeader = protobuf::parse_from_bytes(transaction.get_header())
+                .map_err(|err| format!("{}", err))?;
+            let timestamp = match parse_payload(header.get_family_version(), transaction.get_payload()) {
+                Some(Ok((_, timestamp))) => timestamp,
+                _ => 0,
+            };
+            transactions.push(Transaction {
+                family_version: header.get_family_version().to_string(),
+                signer: header.get_signer_public_key().to_string(),
+                payload: transaction.get_payload().to_vec(),
+                signature: transaction.get_header_signature().to_string(),
+                timestamp: timestamp,
+            });
+        }
+    }
+    Ok(transactions)
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|err| format!("{}", err))?;
+    Ok(contents)
+}
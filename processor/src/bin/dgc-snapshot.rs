@@ -0,0 +1,111 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+#[macro_use]
+extern crate clap;
+extern crate csv;
+extern crate dgc_rest_api_tp;
+extern crate serde_json;
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+
+use clap::{App, Arg};
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::decode::decode_state;
+use dgc_rest_api_tp::snapshot::{export_tables, parse_snapshot, TableExport, HISTORY_HEADER};
+
+fn main() {
+    let matches = App::new("dgc-snapshot")
+        .version(crate_version!())
+        .about("Exports a snapshot of dgc-REST-api state to JSON and CSV")
+        .arg(
+            Arg::with_name("snapshot")
+                .required(true)
+                .help("JSON file of state entries, such as a saved /state response"),
+        )
+        .arg(
+            Arg::with_name("output_dir")
+                .short("o")
+                .long("output-dir")
+                .takes_value(true)
+                .default_value(".")
+                .help("directory to write state.json and the table CSVs to"),
+        )
+        .arg(
+            Arg::with_name("family-name")
+                .long("family-name")
+                .takes_value(true)
+                .help("transaction family name (default: dgc_REST_api)"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .takes_value(true)
+                .help("state namespace (default: derived from the family name)"),
+        )
+        .get_matches();
+
+    let path = matches.value_of("snapshot").unwrap_or("");
+    let output_dir = Path::new(matches.value_of("output_dir").unwrap_or("."));
+
+    let family = match Family::from_options(
+        matches.value_of("family-name"),
+        matches.value_of("namespace"),
+    ) {
+        Ok(family) => family,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    if let Err(err) = export(family.namespace(), path, output_dir) {
+        eprintln!("Unable to export {}: {}", path, err);
+        process::exit(1);
+    }
+}
+
+fn export(namespace: &str, path: &str, output_dir: &Path) -> Result<(), String> {
+    let mut contents = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|err| format!("{}", err))?;
+    let state = parse_snapshot(&contents).map_err(|err| format!("{}", err))?;
+
+    fs::create_dir_all(output_dir).map_err(|err| format!("{}", err))?;
+
+    let decoded = decode_state(namespace, &state).map_err(|err| format!("{}", err))?;
+    let json = serde_json::to_string_pretty(&decoded).map_err(|err| format!("{}", err))?;
+    File::create(output_dir.join("state.json"))
+        .and_then(|mut f| f.write_all(json.as_bytes()))
+        .map_err(|err| format!("{}", err))?;
+
+    for table in export_tables(namespace, &state).map_err(|err| format!("{}", err))? {
+        write_table(&table, output_dir).map_err(|err| format!("{}: {}", table.name, err))?;
+    }
+    Ok(())
+}
+
+/// Writes `<table>.csv`, with one row per record, and `<table>_history.csv`
+/// with one row per reported value.
+fn write_table(table: &TableExport, output_dir: &Path) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(output_dir.join(format!("{}.csv", table.name)))?;
+    writer.write_record(&table.header)?;
+    for row in &table.records {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+
+    let mut writer =
+        csv::Writer::from_path(output_dir.join(format!("{}_history.csv", table.name)))?;
+    writer.write_record(&HISTORY_HEADER)?;
+    for row in &table.history {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,938 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+#[macro_use]
+extern crate clap;
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate rustc_serialize;
+extern crate sawtooth_sdk;
+
+use std::env;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use protobuf::Message;
+use protobuf::RepeatedField;
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::FromHex;
+
+use sawtooth_sdk::messages::batch::BatchList;
+use sawtooth_sdk::signing;
+use sawtooth_sdk::signing::secp256k1::Secp256k1PrivateKey;
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::roles;
+use dgc_rest_api_tp::schema::{parse_data_type, PropertyValueDefinition, SchemaError,
+                              SchemaFormat, TableDefinition};
+use dgc_rest_api_tp::transaction::*;
+
+#[derive(Debug)]
+enum CliError {
+    UserError(String),
+    IoError(io::Error),
+    SigningError(signing::Error),
+    ProtobufError(protobuf::ProtobufError),
+    SchemaError(SchemaError),
+    TransactionError(TransactionError),
+}
+
+impl StdError for CliError {
+    fn description(&self) -> &str {
+        match *self {
+            CliError::UserError(ref msg) => msg,
+            CliError::IoError(ref err) => err.description(),
+            CliError::SigningError(ref err) => err.description(),
+            CliError::ProtobufError(ref err) => err.description(),
+            CliError::SchemaError(ref err) => err.description(),
+            CliError::TransactionError(ref err) => err.description(),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::UserError(ref s) => write!(f, "Error: {}", s),
+            CliError::IoError(ref err) => write!(f, "IoError: {}", err),
+            CliError::SigningError(ref err) => write!(f, "SigningError: {}", err),
+            CliError::ProtobufError(ref err) => write!(f, "ProtobufError: {}", err),
+            CliError::SchemaError(ref err) => write!(f, "{}", err),
+            CliError::TransactionError(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        CliError::IoError(e)
+    }
+}
+
+impl From<signing::Error> for CliError {
+    fn from(e: signing::Error) -> Self {
+        CliError::SigningError(e)
+    }
+}
+
+impl From<protobuf::ProtobufError> for CliError {
+    fn from(e: protobuf::ProtobufError) -> Self {
+        CliError::ProtobufError(e)
+    }
+}
+
+impl From<SchemaError> for CliError {
+    fn from(e: SchemaError) -> Self {
+        CliError::SchemaError(e)
+    }
+}
+
+impl From<TransactionError> for CliError {
+    fn from(e: TransactionError) -> Self {
+        CliError::TransactionError(e)
+    }
+}
+
+fn main() {
+    let matches = App::new("dgc")
+        .version(crate_version!())
+        .about("Builds and signs dgc-REST-api batches offline")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("key")
+                .short("k")
+                .long("key")
+                .takes_value(true)
+                .global(true)
+                .help("signing key name or path (default: ~/.sawtooth/keys/$USER.priv)"),
+        )
+        .arg(
+            Arg::with_name("participant")
+                .long("participant")
+                .takes_value(true)
+                .global(true)
+                .help("public key of the participant the key signs for, after a key rotation"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .global(true)
+                .help("batch list file to write (default: dgc.batch)"),
+        )
+        .arg(
+            Arg::with_name("append")
+                .long("append")
+                .global(true)
+                .help("add the batch to an existing batch list file"),
+        )
+        .arg(
+            Arg::with_name("timestamp")
+                .long("timestamp")
+                .takes_value(true)
+                .global(true)
+                .help("payload timestamp in seconds since the epoch (default: now)"),
+        )
+        .arg(
+            Arg::with_name("family-version")
+                .long("family-version")
+                .takes_value(true)
+                .global(true)
+                .possible_values(&FAMILY_VERSIONS)
+                .default_value(FAMILY_VERSION)
+                .help("payload format to write"),
+        )
+        .arg(
+            Arg::with_name("family-name")
+                .long("family-name")
+                .takes_value(true)
+                .global(true)
+                .help("transaction family name (default: dgc_REST_api)"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .takes_value(true)
+                .global(true)
+                .help("state namespace (default: derived from the family name)"),
+        )
+        .subcommand(
+            SubCommand::with_name("create-participant")
+                .about("register the signing key as a participant")
+                .arg(positional("name", "human readable name of the participant"))
+                .arg(option("email", "email address, unique among participants")),
+        )
+        .subcommand(
+            SubCommand::with_name("update-participant")
+                .about("replace the signing participant's name and email (family version 2)")
+                .arg(positional("name", "new name of the participant"))
+                .arg(option("email", "new email address; omit to remove the email"))
+                .arg(option(
+                    "recovery-key",
+                    "public key which can rotate the signing key if it is lost; omit to keep it",
+                ))
+                .arg(
+                    Arg::with_name("remove-recovery-key")
+                        .long("remove-recovery-key")
+                        .conflicts_with("recovery-key")
+                        .help("remove the recovery key"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rotate-key")
+                .about("move the signing of a participant to a new key (family version 2)")
+                .arg(positional("participant_id", "public key the participant registered with"))
+                .arg(
+                    option("new-key", "name or path of the new private key, which signs its consent")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set-participant-status")
+                .about("suspend, deactivate or reactivate a participant (family version 2)")
+                .arg(positional("participant_id", "public key the participant registered with"))
+                .arg(
+                    positional("status", "new status")
+                        .possible_values(&["ACTIVE", "SUSPENDED", "DEACTIVATED"]),
+                )
+                .arg(option("reason", "why the status changed")),
+        )
+        .subcommand(
+            SubCommand::with_name("set-roles")
+                .about("replace the roles of a public key, none to remove them (family version 2)")
+                .arg(positional("public_key", "public key to give the roles to"))
+                .arg(
+                    Arg::with_name("role")
+                        .help("role to hold")
+                        .multiple(true)
+                        .possible_values(&[
+                            "ADMIN",
+                            "TABLE_ADMIN",
+                            "CREDIT_ISSUER",
+                            "EXCHANGE_OPERATOR",
+                            "AUDITOR",
+                        ]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("create-organization")
+                .about("create an organization with the signer as its first member (family version 2)")
+                .arg(positional("organization_id", "unique identifier of the organization"))
+                .arg(positional("name", "name of the organization")),
+        )
+        .subcommand(
+            SubCommand::with_name("set-organization-member")
+                .about("replace the permissions of a member, none to remove it (family version 2)")
+                .arg(positional("organization_id", "identifier of the organization"))
+                .arg(positional("participant_id", "public key the member registered with"))
+                .arg(
+                    Arg::with_name("permission")
+                        .help("permission to hold")
+                        .multiple(true)
+                        .possible_values(&[
+                            "PROPOSE",
+                            "ANSWER",
+                            "FINALIZE",
+                            "REVOKE_REPORTER",
+                            "MANAGE_MEMBERS",
+                        ]),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("create-multisig-policy")
+                .about("create a policy needing a threshold of its signers (family version 2)")
+                .arg(positional("policy_id", "unique identifier of the policy"))
+                .arg(
+                    positional("signer", "public key a signing participant registered with")
+                        .multiple(true),
+                )
+                .arg(
+                    option("threshold", "how many signers must approve an action")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("withdraw-approval")
+                .about("withdraw an approval sent for a multisig policy (family version 2)")
+                .arg(positional("approval_id", "id of the pending approval")),
+        )
+        .subcommand(
+            SubCommand::with_name("create-table")
+                .about("create a table from a JSON or YAML schema file")
+                .arg(positional("schema", "path to the table schema file"))
+                .arg(option("format", "json or yaml (default: from the file extension)")),
+        )
+        .subcommand(
+            SubCommand::with_name("export-table")
+                .about("print a table from state as a schema file")
+                .arg(positional("state", "path to a table container state entry"))
+                .arg(option("name", "only print the table with this name"))
+                .arg(option("format", "json or yaml (default: json)"))
+                .arg(
+                    Arg::with_name("base64")
+                        .long("base64")
+                        .help("the state entry is base64 encoded"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("create-record")
+                .about("create a record of a table")
+                .arg(positional("record_id", "natural key of the record"))
+                .arg(positional("table", "name of the record's table"))
+                .arg(property_value_arg())
+                .arg(values_file_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("finalize-record")
+                .about("finalize a record")
+                .arg(positional("record_id", "natural key of the record")),
+        )
+        .subcommand(
+            SubCommand::with_name("update-properties")
+                .about("report new property values for a record")
+                .arg(positional("record_id", "natural key of the record"))
+                .arg(property_value_arg())
+                .arg(values_file_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("create-proposal")
+                .about("create a proposal")
+                .arg(positional("proposal_id", "id of the proposal"))
+                .arg(positional(
+                    "role",
+                    "transferOwnership, transferCustodianship, authorizeReporter, \
+                     buyDGC, sellDGC, creditDGC or transferDGC",
+                ))
+                .arg(option("receiver", "public key of the receiving participant"))
+                .arg(option("record", "natural key of the record"))
+                .arg(
+                    option("property", "property name, for authorizeReporter")
+                        .short("p")
+                        .multiple(true),
+                )
+                .arg(option("amount", "dg coin amount, or the price of a transferOwnership"))
+                .arg(option("currency", "ISO 4217 currency code"))
+                .arg(option("quote", "currency quote amount"))
+                .arg(option("expires-at", "Unix time the proposal expires at (default: never)"))
+                .arg(option("terms", "human-readable terms of the proposal")),
+        )
+        .subcommand(
+            SubCommand::with_name("answer-proposal")
+                .about("answer a proposal")
+                .arg(positional("proposal_id", "id of the proposal"))
+                .arg(positional("role", "role of the proposal"))
+                .arg(positional(
+                    "response",
+                    "ACCEPT, REJECT, CANCEL, autoCLOSE or autoOPEN",
+                ))
+                .arg(option("receiver", "public key of the receiving participant"))
+                .arg(option("record", "natural key of the record"))
+                .arg(option("amount", "dg coin amount")),
+        )
+        .subcommand(
+            SubCommand::with_name("counter-proposal")
+                .about("answer a proposal with a counter-offer (family version 2)")
+                .arg(positional("proposal_id", "id of the proposal"))
+                .arg(positional("counter_proposal_id", "id of the counter-offer"))
+                .arg(option("terms", "human-readable terms of the counter-offer"))
+                .arg(option("amount", "dg coin amount (default: the proposal's)"))
+                .arg(
+                    option("property", "property name, for authorizeReporter (default: the proposal's)")
+                        .short("p")
+                        .multiple(true),
+                )
+                .arg(option("expires-at", "Unix time the counter-offer expires at (default: never)")),
+        )
+        .subcommand(
+            SubCommand::with_name("expire-proposals")
+                .about("expire open proposals whose expiry has passed (family version 2)")
+                .arg(positional("proposal_id", "id of the proposal").multiple(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("set-credit-limit")
+                .about("set, lower or revoke a participant's credit limit (family version 2)")
+                .arg(positional("participant_id", "public key of the participant"))
+                .arg(positional("amount", "new credit limit in dg coin, 0 to revoke")),
+        )
+        .subcommand(
+            SubCommand::with_name("repay-credit")
+                .about("repay drawn credit from the signer's balance (family version 2)")
+                .arg(positional("amount", "dg coin amount to repay")),
+        )
+        .subcommand(
+            SubCommand::with_name("revoke-reporter")
+                .about("revoke a reporter's authorization")
+                .arg(positional("record_id", "natural key of the record"))
+                .arg(positional("reporter", "public key of the reporter"))
+                .arg(
+                    option("property", "property name")
+                        .short("p")
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("rewrite state entries in the current layout (family version 2)")
+                .arg(positional("address", "participant, proposal or record address").multiple(true))
+                .arg(option("cursor", "index of the first address to migrate (default: 0)")),
+        )
+        .get_matches();
+
+    if let Err(err) = run(&matches) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let (name, args) = matches.subcommand();
+    let args = match args {
+        Some(args) => args,
+        None => return Err(CliError::UserError(String::from("Subcommand required"))),
+    };
+
+    if name == "export-table" {
+        return export_table(args);
+    }
+
+    let action = match name {
+        "create-participant" => create_participant(args)?,
+        "update-participant" => update_participant(args)?,
+        "rotate-key" => rotate_key(args)?,
+        "set-participant-status" => set_participant_status(args)?,
+        "set-roles" => set_roles(args)?,
+        "create-organization" => create_organization(args)?,
+        "set-organization-member" => set_organization_member(args)?,
+        "create-multisig-policy" => create_multisig_policy(args)?,
+        "withdraw-approval" => withdraw_approval(args)?,
+        "create-table" => create_table(args)?,
+        "create-record" => create_record(args)?,
+        "finalize-record" => finalize_record(args)?,
+        "update-properties" => update_properties(args)?,
+        "create-proposal" => create_proposal(args)?,
+        "answer-proposal" => answer_proposal(args)?,
+        "counter-proposal" => counter_proposal(args)?,
+        "expire-proposals" => expire_proposals(args)?,
+        "set-credit-limit" => set_credit_limit(args)?,
+        "repay-credit" => repay_credit(args)?,
+        "revoke-reporter" => revoke_reporter(args)?,
+        "migrate" => migrate(args)?,
+        _ => {
+            return Err(CliError::UserError(format!(
+                "Unknown subcommand: {}",
+                name
+            )))
+        }
+    };
+
+    let timestamp = match args.value_of("timestamp") {
+        Some(timestamp) => parse_number(timestamp, "timestamp")?,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| CliError::UserError(format!("{}", err)))?
+            .as_secs(),
+    };
+
+    let private_key = load_signing_key(args.value_of("key"))?;
+    let context = signing::create_context("secp256k1")?;
+    let factory = signing::CryptoFactory::new(&*context);
+    let signer = factory.new_signer(&private_key);
+
+    let family = Family::from_options(args.value_of("family-name"), args.value_of("namespace"))
+        .map_err(CliError::UserError)?;
+    let participant = args.value_of("participant");
+    let transaction = match args.value_of("family-version") {
+        Some(FAMILY_VERSION_2) => create_transaction_v2(
+            &family,
+            &make_payload_v2(action, timestamp),
+            &signer,
+            participant,
+            None,
+        )?,
+        _ => create_transaction(
+            &family,
+            &make_payload(action, timestamp)?,
+            &signer,
+            participant,
+            None,
+        )?,
+    };
+    let batch = create_batch(vec![transaction], &signer)?;
+
+    let output = args.value_of("output").unwrap_or("dgc.batch");
+    let mut batches = if args.is_present("append") && Path::new(output).exists() {
+        let mut bytes = Vec::new();
+        File::open(output)?.read_to_end(&mut bytes)?;
+        let batch_list: BatchList = protobuf::parse_from_bytes(&bytes)?;
+        batch_list.get_batches().to_vec()
+    } else {
+        Vec::new()
+    };
+    batches.push(batch);
+
+    let batch_list = create_batch_list(batches);
+    File::create(output)?.write_all(&batch_list.write_to_bytes()?)?;
+    println!("Wrote {} batch(es) to {}", batch_list.get_batches().len(), output);
+
+    Ok(())
+}
+
+fn create_participant(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name(required(args, "name")?.to_string());
+    action.set_email(args.value_of("email").unwrap_or("").to_string());
+    Ok(PayloadAction::CreateParticipant(action))
+}
+
+fn update_participant(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::UpdateParticipantAction::new();
+    action.set_name(required(args, "name")?.to_string());
+    action.set_email(args.value_of("email").unwrap_or("").to_string());
+    action.set_recovery_key(args.value_of("recovery-key").unwrap_or("").to_string());
+    action.set_remove_recovery_key(args.is_present("remove-recovery-key"));
+    Ok(PayloadAction::UpdateParticipant(action))
+}
+
+fn rotate_key(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let participant_id = required(args, "participant_id")?;
+    let new_key = load_signing_key(args.value_of("new-key"))?;
+    let context = signing::create_context("secp256k1")?;
+    let factory = signing::CryptoFactory::new(&*context);
+    let new_signer = factory.new_signer(&new_key);
+
+    let mut action = payload_v2::RotateKeyAction::new();
+    action.set_participant_id(participant_id.to_string());
+    action.set_new_public_key(new_signer.get_public_key()?.as_hex());
+    action.set_new_key_signature(new_signer.sign(participant_id.as_bytes())?);
+    Ok(PayloadAction::RotateKey(action))
+}
+
+fn set_participant_status(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::SetParticipantStatusAction::new();
+    action.set_participant_id(required(args, "participant_id")?.to_string());
+    action.set_status(parse_status(required(args, "status")?)?);
+    action.set_reason(args.value_of("reason").unwrap_or("").to_string());
+    Ok(PayloadAction::SetParticipantStatus(action))
+}
+
+fn set_roles(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::SetRolesAction::new();
+    action.set_public_key(required(args, "public_key")?.to_string());
+    for role in values(args, "role") {
+        match roles::parse_role(&role) {
+            Some(role) => action.mut_roles().push(role),
+            None => return Err(CliError::UserError(format!("Unknown role: {}", role))),
+        }
+    }
+    Ok(PayloadAction::SetRoles(action))
+}
+
+fn create_organization(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::CreateOrganizationAction::new();
+    action.set_organization_id(required(args, "organization_id")?.to_string());
+    action.set_name(required(args, "name")?.to_string());
+    Ok(PayloadAction::CreateOrganization(action))
+}
+
+fn set_organization_member(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::SetOrganizationMemberAction::new();
+    action.set_organization_id(required(args, "organization_id")?.to_string());
+    action.set_participant_id(required(args, "participant_id")?.to_string());
+    for permission in values(args, "permission") {
+        action.mut_permissions().push(parse_member_permission(&permission)?);
+    }
+    Ok(PayloadAction::SetOrganizationMember(action))
+}
+
+fn create_multisig_policy(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::CreateMultisigPolicyAction::new();
+    action.set_policy_id(required(args, "policy_id")?.to_string());
+    action.set_signers(RepeatedField::from_vec(values(args, "signer")));
+    action.set_threshold(parse_number(required(args, "threshold")?, "threshold")?);
+    Ok(PayloadAction::CreateMultisigPolicy(action))
+}
+
+fn withdraw_approval(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::WithdrawApprovalAction::new();
+    action.set_approval_id(required(args, "approval_id")?.to_string());
+    Ok(PayloadAction::WithdrawApproval(action))
+}
+
+fn create_table(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let path = required(args, "schema")?;
+    let format = schema_format(args, path)?;
+    let definition = TableDefinition::parse(&read_file(path)?, format)?;
+    Ok(PayloadAction::CreateTable(definition.to_create_table_action()?))
+}
+
+/// Prints the tables stored in a table container state entry, as fetched
+/// from the REST API's /state endpoint.
+fn export_table(args: &ArgMatches) -> Result<(), CliError> {
+    let path = required(args, "state")?;
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    if args.is_present("base64") {
+        let encoded = String::from_utf8(bytes)
+            .map_err(|_| CliError::UserError(String::from("State file is not base64 text")))?;
+        bytes = encoded
+            .trim()
+            .from_base64()
+            .map_err(|err| CliError::UserError(format!("Invalid base64: {}", err)))?;
+    }
+    let container: record::TableContainer = protobuf::parse_from_bytes(&bytes)?;
+
+    let format = match args.value_of("format") {
+        Some(format) => SchemaFormat::from_name(format)?,
+        None => SchemaFormat::Json,
+    };
+    let mut found = false;
+    for table in container.get_entries() {
+        if let Some(name) = args.value_of("name") {
+            if table.get_name() != name {
+                continue;
+            }
+        }
+        found = true;
+        println!("{}", TableDefinition::from_table(table).to_string(format)?);
+    }
+    if !found {
+        return Err(CliError::UserError(String::from("No matching table in state")));
+    }
+    Ok(())
+}
+
+fn create_record(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload::CreateRecordAction::new();
+    action.set_record_id(required(args, "record_id")?.to_string());
+    action.set_table(required(args, "table")?.to_string());
+    action.set_properties(RepeatedField::from_vec(property_values(args)?));
+    Ok(PayloadAction::CreateRecord(action))
+}
+
+fn finalize_record(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload::FinalizeRecordAction::new();
+    action.set_record_id(required(args, "record_id")?.to_string());
+    Ok(PayloadAction::FinalizeRecord(action))
+}
+
+fn update_properties(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let properties = property_values(args)?;
+    if properties.is_empty() {
+        return Err(CliError::UserError(String::from(
+            "At least one property value is required",
+        )));
+    }
+
+    let mut action = payload::UpdatePropertiesAction::new();
+    action.set_record_id(required(args, "record_id")?.to_string());
+    action.set_properties(RepeatedField::from_vec(properties));
+    Ok(PayloadAction::UpdateProperties(action))
+}
+
+fn create_proposal(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload::CreateProposalAction::new();
+    action.set_proposal_id(required(args, "proposal_id")?.to_string());
+    action.set_role(parse_role(required(args, "role")?)?);
+    action.set_receiving_participant(args.value_of("receiver").unwrap_or("").to_string());
+    action.set_record_id(args.value_of("record").unwrap_or("").to_string());
+    action.set_properties(RepeatedField::from_vec(values(args, "property")));
+    if let Some(amount) = args.value_of("amount") {
+        action.set_dg_coin_amount(parse_number(amount, "amount")?);
+    }
+    action.set_currency_iso_codes(args.value_of("currency").unwrap_or("").to_string());
+    if let Some(quote) = args.value_of("quote") {
+        action.set_currency_quote_amount(parse_number(quote, "quote")?);
+    }
+    if let Some(expires_at) = args.value_of("expires-at") {
+        action.set_expires_at(parse_number(expires_at, "expiry")?);
+    }
+    action.set_terms(args.value_of("terms").unwrap_or("").to_string());
+    Ok(PayloadAction::CreateProposal(action))
+}
+
+fn answer_proposal(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload::AnswerProposalAction::new();
+    action.set_proposal_id(required(args, "proposal_id")?.to_string());
+    action.set_role(parse_role(required(args, "role")?)?);
+    action.set_response(parse_response(required(args, "response")?)?);
+    action.set_receiving_participant(args.value_of("receiver").unwrap_or("").to_string());
+    action.set_record_id(args.value_of("record").unwrap_or("").to_string());
+    if let Some(amount) = args.value_of("amount") {
+        action.set_dg_coin_amount(parse_number(amount, "amount")?);
+    }
+    Ok(PayloadAction::AnswerProposal(action))
+}
+
+fn counter_proposal(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut counter = payload_v2::CounterOffer::new();
+    counter.set_proposal_id(required(args, "counter_proposal_id")?.to_string());
+    counter.set_terms(args.value_of("terms").unwrap_or("").to_string());
+    if let Some(amount) = args.value_of("amount") {
+        counter.set_dg_coin_amount(parse_number(amount, "amount")?);
+        counter.set_has_dg_coin_amount(true);
+    }
+    counter.set_properties(RepeatedField::from_vec(values(args, "property")));
+    if let Some(expires_at) = args.value_of("expires-at") {
+        counter.set_expires_at(parse_number(expires_at, "expiry")?);
+    }
+
+    let mut action = payload_v2::AnswerProposalV2Action::new();
+    action.set_proposal_id(required(args, "proposal_id")?.to_string());
+    action.set_response(payload_v2::AnswerProposalV2Action_Response::COUNTER);
+    action.set_counter(counter);
+    Ok(PayloadAction::CounterProposal(action))
+}
+
+fn expire_proposals(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::ExpireProposalsAction::new();
+    action.set_proposal_ids(RepeatedField::from_vec(values(args, "proposal_id")));
+    Ok(PayloadAction::ExpireProposals(action))
+}
+
+fn set_credit_limit(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::SetCreditLimitAction::new();
+    action.set_participant_id(required(args, "participant_id")?.to_string());
+    action.set_dg_coin_amount(parse_number(required(args, "amount")?, "amount")?);
+    Ok(PayloadAction::SetCreditLimit(action))
+}
+
+fn repay_credit(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::RepayCreditAction::new();
+    action.set_dg_coin_amount(parse_number(required(args, "amount")?, "amount")?);
+    Ok(PayloadAction::RepayCredit(action))
+}
+
+fn revoke_reporter(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload::RevokeReporterAction::new();
+    action.set_record_id(required(args, "record_id")?.to_string());
+    action.set_reporter_id(required(args, "reporter")?.to_string());
+    action.set_properties(RepeatedField::from_vec(values(args, "property")));
+    Ok(PayloadAction::RevokeReporter(action))
+}
+
+fn migrate(args: &ArgMatches) -> Result<PayloadAction, CliError> {
+    let mut action = payload_v2::MigrateAction::new();
+    action.set_addresses(RepeatedField::from_vec(values(args, "address")));
+    if let Some(cursor) = args.value_of("cursor") {
+        action.set_cursor(parse_number(cursor, "cursor")?);
+    }
+    Ok(PayloadAction::Migrate(action))
+}
+
+fn positional<'a, 'b>(name: &'a str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name).required(true).help(help)
+}
+
+fn option<'a, 'b>(name: &'a str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name).long(name).takes_value(true).help(help)
+}
+
+fn property_value_arg<'a, 'b>() -> Arg<'a, 'b> {
+    option("property", "property value as NAME:TYPE=VALUE")
+        .short("p")
+        .multiple(true)
+        .number_of_values(1)
+}
+
+fn values_file_arg<'a, 'b>() -> Arg<'a, 'b> {
+    option("values", "path to a JSON file of property values")
+}
+
+fn required<'a>(args: &'a ArgMatches, name: &str) -> Result<&'a str, CliError> {
+    args.value_of(name)
+        .ok_or_else(|| CliError::UserError(format!("Argument required: {}", name)))
+}
+
+fn values(args: &ArgMatches, name: &str) -> Vec<String> {
+    match args.values_of(name) {
+        Some(values) => values.map(|v| v.to_string()).collect(),
+        None => Vec::new(),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, CliError> {
+    value
+        .parse::<T>()
+        .map_err(|_| CliError::UserError(format!("Invalid {}: {}", name, value)))
+}
+
+fn parse_role(role: &str) -> Result<proposal::Proposal_Role, CliError> {
+    match role {
+        "transferOwnership" => Ok(proposal::Proposal_Role::transferOwnership),
+        "transferCustodianship" => Ok(proposal::Proposal_Role::transferCustodianship),
+        "authorizeReporter" => Ok(proposal::Proposal_Role::authorizeReporter),
+        "buyDGC" => Ok(proposal::Proposal_Role::buyDGC),
+        "sellDGC" => Ok(proposal::Proposal_Role::sellDGC),
+        "creditDGC" => Ok(proposal::Proposal_Role::creditDGC),
+        "transferDGC" => Ok(proposal::Proposal_Role::transferDGC),
+        _ => Err(CliError::UserError(format!("Unknown role: {}", role))),
+    }
+}
+
+fn parse_status(status: &str) -> Result<participant::Participant_Status, CliError> {
+    match status {
+        "ACTIVE" => Ok(participant::Participant_Status::ACTIVE),
+        "SUSPENDED" => Ok(participant::Participant_Status::SUSPENDED),
+        "DEACTIVATED" => Ok(participant::Participant_Status::DEACTIVATED),
+        _ => Err(CliError::UserError(format!("Unknown status: {}", status))),
+    }
+}
+
+fn parse_member_permission(
+    permission: &str,
+) -> Result<organization::Organization_Member_Permission, CliError> {
+    match permission {
+        "PROPOSE" => Ok(organization::Organization_Member_Permission::PROPOSE),
+        "ANSWER" => Ok(organization::Organization_Member_Permission::ANSWER),
+        "FINALIZE" => Ok(organization::Organization_Member_Permission::FINALIZE),
+        "REVOKE_REPORTER" => Ok(organization::Organization_Member_Permission::REVOKE_REPORTER),
+        "MANAGE_MEMBERS" => Ok(organization::Organization_Member_Permission::MANAGE_MEMBERS),
+        _ => Err(CliError::UserError(format!("Unknown permission: {}", permission))),
+    }
+}
+
+fn parse_response(response: &str) -> Result<payload::AnswerProposalAction_Response, CliError> {
+    match response {
+        "ACCEPT" => Ok(payload::AnswerProposalAction_Response::ACCEPT),
+        "REJECT" => Ok(payload::AnswerProposalAction_Response::REJECT),
+        "CANCEL" => Ok(payload::AnswerProposalAction_Response::CANCEL),
+        "autoCLOSE" => Ok(payload::AnswerProposalAction_Response::autoCLOSE),
+        "autoOPEN" => Ok(payload::AnswerProposalAction_Response::autoOPEN),
+        _ => Err(CliError::UserError(format!("Unknown response: {}", response))),
+    }
+}
+
+/// Collects the values given with --values and --property. STRUCT values can
+/// only be given in a values file.
+fn property_values(args: &ArgMatches) -> Result<Vec<property::PropertyValue>, CliError> {
+    let mut properties = Vec::new();
+    if let Some(path) = args.value_of("values") {
+        let format = SchemaFormat::from_path(path);
+        for definition in PropertyValueDefinition::parse_list(&read_file(path)?, format)? {
+            properties.push(definition.to_property_value()?);
+        }
+    }
+    for arg in values(args, "property") {
+        properties.push(parse_property_arg(&arg)?);
+    }
+    Ok(properties)
+}
+
+fn parse_property_arg(arg: &str) -> Result<property::PropertyValue, CliError> {
+    let (name_and_type, value) = match arg.find('=') {
+        Some(i) => (&arg[..i], &arg[i + 1..]),
+        None => {
+            return Err(CliError::UserError(format!(
+                "Property must be NAME:TYPE=VALUE: {}",
+                arg
+            )))
+        }
+    };
+    let (name, data_type) = match name_and_type.find(':') {
+        Some(i) => (&name_and_type[..i], &name_and_type[i + 1..]),
+        None => {
+            return Err(CliError::UserError(format!(
+                "Property must be NAME:TYPE=VALUE: {}",
+                arg
+            )))
+        }
+    };
+    let data_type = parse_data_type(data_type)?;
+
+    let mut property_value = property::PropertyValue::new();
+    property_value.set_name(name.to_string());
+    property_value.set_data_type(data_type);
+    match data_type {
+        property::PropertySchema_DataType::BYTES => {
+            let bytes = value
+                .from_hex()
+                .map_err(|err| CliError::UserError(format!("Invalid hex {}: {}", value, err)))?;
+            property_value.set_bytes_value(bytes)
+        }
+        property::PropertySchema_DataType::BOOLEAN => {
+            property_value.set_boolean_value(parse_number(value, name)?)
+        }
+        property::PropertySchema_DataType::NUMBER => {
+            property_value.set_number_value(parse_number(value, name)?)
+        }
+        property::PropertySchema_DataType::STRING => {
+            property_value.set_string_value(value.to_string())
+        }
+        property::PropertySchema_DataType::ENUM => property_value.set_enum_value(value.to_string()),
+        property::PropertySchema_DataType::LOCATION => {
+            let coordinates: Vec<&str> = value.split(',').collect();
+            if coordinates.len() != 2 {
+                return Err(CliError::UserError(format!(
+                    "Location must be LATITUDE,LONGITUDE: {}",
+                    value
+                )));
+            }
+            let mut location = property::Location::new();
+            location.set_latitude(parse_number(coordinates[0], "latitude")?);
+            location.set_longitude(parse_number(coordinates[1], "longitude")?);
+            property_value.set_location_value(location)
+        }
+        property::PropertySchema_DataType::STRUCT
+        | property::PropertySchema_DataType::TYPE_UNSET => {
+            return Err(CliError::UserError(format!(
+                "Property {} must be given in a values file",
+                name
+            )))
+        }
+    };
+    Ok(property_value)
+}
+
+fn schema_format(args: &ArgMatches, path: &str) -> Result<SchemaFormat, CliError> {
+    match args.value_of("format") {
+        Some(format) => Ok(SchemaFormat::from_name(format)?),
+        None => Ok(SchemaFormat::from_path(path)),
+    }
+}
+
+fn read_file(path: &str) -> Result<String, CliError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Reads a hex encoded secp256k1 private key. A bare name is looked up in
+/// ~/.sawtooth/keys, as the sawtooth CLIs do.
+fn load_signing_key(name: Option<&str>) -> Result<Secp256k1PrivateKey, CliError> {
+    let path = match name {
+        Some(name) if name.contains('/') || name.ends_with(".priv") => PathBuf::from(name),
+        Some(name) => key_dir()?.join(format!("{}.priv", name)),
+        None => {
+            let username = env::var("USER")
+                .map_err(|_| CliError::UserError(String::from("Could not determine $USER")))?;
+            key_dir()?.join(format!("{}.priv", username))
+        }
+    };
+
+    let mut key = String::new();
+    File::open(&path)
+        .map_err(|err| {
+            CliError::UserError(format!("Unable to open key {}: {}", path.display(), err))
+        })?
+        .read_to_string(&mut key)?;
+    Ok(Secp256k1PrivateKey::from_hex(key.trim())?)
+}
+
+fn key_dir() -> Result<PathBuf, CliError> {
+    match env::home_dir() {
+        Some(home) => Ok(home.join(".sawtooth").join("keys")),
+        None => Err(CliError::UserError(String::from(
+            "Could not determine home directory",
+        ))),
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use protobuf;
+
+use sawtooth_sdk::processor::handler::ApplyError;
+use sawtooth_sdk::processor::handler::TransactionContext;
+
+use addressing::*;
+use messages::block_info::{BlockInfo, BlockInfoConfig};
+use settings::get_setting;
+
+pub const MAX_SKEW_KEY: &str = "dgc.timestamp.max_skew_seconds";
+pub const DEFAULT_MAX_SKEW: u64 = 300;
+
+/// Set to "true" on networks which do not run BlockInfo, to apply
+/// transactions on the client's timestamps alone.
+pub const WITHOUT_BLOCK_INFO_KEY: &str = "dgc.timestamp.allow_without_block_info";
+
+/// The last block in which version 1.1 proposals and answers are applied on
+/// the rules chains written before version 2 were, so that those chains
+/// replay unchanged. Later blocks, and networks without the setting, apply
+/// the rules of version 2 to both versions.
+pub const LEGACY_RULES_UNTIL_KEY: &str = "dgc.proposals.legacy_rules_until_block";
+
+/// The addresses transactions must list as inputs so `check_timestamp` can
+/// read them.
+pub fn block_time_addresses() -> Vec<String> {
+    vec![
+        BLOCK_INFO_NAMESPACE.to_string(),
+        make_setting_address(MAX_SKEW_KEY),
+        make_setting_address(WITHOUT_BLOCK_INFO_KEY),
+        make_setting_address(LEGACY_RULES_UNTIL_KEY),
+    ]
+}
+
+/// Reads the block being built from the BlockInfo family, or None if the
+/// network does not run BlockInfo.
+fn get_block_info(context: &mut TransactionContext) -> Result<Option<BlockInfo>, ApplyError> {
+    let config: BlockInfoConfig = match context.get_state(vec![make_block_info_config_address()])? {
+        Some(data) => match protobuf::parse_from_bytes(&data) {
+            Ok(config) => config,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot deserialize block info config",
+                )))
+            }
+        },
+        None => return Ok(None),
+    };
+
+    let address = make_block_info_address(config.get_latest_block());
+    let block_info: BlockInfo = match context.get_state(vec![address])? {
+        Some(data) => match protobuf::parse_from_bytes(&data) {
+            Ok(block_info) => block_info,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot deserialize block info",
+                )))
+            }
+        },
+        None => return Ok(None),
+    };
+    Ok(Some(block_info))
+}
+
+/// Reads the timestamp of the block being built from the BlockInfo family,
+/// or None if the network does not run BlockInfo.
+pub fn get_block_timestamp(context: &mut TransactionContext) -> Result<Option<u64>, ApplyError> {
+    Ok(get_block_info(context)?.map(|block_info| block_info.get_timestamp()))
+}
+
+/// Whether the block being built is one the
+/// `dgc.proposals.legacy_rules_until_block` setting keeps on the rules of
+/// version 1.1. Without BlockInfo the height is unknown, so it is not.
+pub fn within_legacy_rules(context: &mut TransactionContext) -> Result<bool, ApplyError> {
+    let until = match get_setting(context, LEGACY_RULES_UNTIL_KEY)? {
+        Some(value) => match value.parse::<u64>() {
+            Ok(until) => until,
+            Err(_) => {
+                warn!("Ignoring invalid setting {}: {}", LEGACY_RULES_UNTIL_KEY, value);
+                return Ok(false);
+            }
+        },
+        None => return Ok(false),
+    };
+    Ok(match get_block_info(context)? {
+        Some(block_info) => block_info.get_block_num() <= until,
+        None => false,
+    })
+}
+
+/// Rejects a payload timestamp further from the block time than the
+/// `dgc.timestamp.max_skew_seconds` setting allows, and returns the block
+/// time. Without BlockInfo the timestamp cannot be checked, so the payload
+/// is rejected unless `dgc.timestamp.allow_without_block_info` is set, in
+/// which case the block time is given as 0.
+pub fn check_timestamp(context: &mut TransactionContext, timestamp: u64) -> Result<u64, ApplyError> {
+    let max_skew = match get_setting(context, MAX_SKEW_KEY)? {
+        Some(value) => match value.parse::<u64>() {
+            Ok(max_skew) => max_skew,
+            Err(_) => {
+                warn!("Ignoring invalid setting {}: {}", MAX_SKEW_KEY, value);
+                DEFAULT_MAX_SKEW
+            }
+        },
+        None => DEFAULT_MAX_SKEW,
+    };
+
+    let block_timestamp = match get_block_timestamp(context)? {
+        Some(block_timestamp) => block_timestamp,
+        None => {
+            if get_setting(context, WITHOUT_BLOCK_INFO_KEY)? != Some(String::from("true")) {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "BlockInfo is not available to check the timestamp against",
+                )));
+            }
+            warn!("BlockInfo is not available, timestamp {} is not checked", timestamp);
+            return Ok(0);
+        }
+    };
+
+    let skew = if timestamp > block_timestamp {
+        timestamp - block_timestamp
+    } else {
+        block_timestamp - timestamp
+    };
+    if skew > max_skew {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Timestamp {} is {} seconds from the block time {}, more than the {} allowed",
+            timestamp, skew, block_timestamp, max_skew
+        )));
+    }
+    Ok(block_timestamp)
+}
+
+/// The time actions are judged against: the block time, or the payload
+/// timestamp on a network allowed to run without BlockInfo.
+pub fn current_time(timestamp: u64, block_timestamp: u64) -> u64 {
+    if block_timestamp == 0 {
+        timestamp
+    } else {
+        block_timestamp
+    }
+}
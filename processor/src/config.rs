@@ -0,0 +1,289 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::env;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use log::LogLevelFilter;
+use toml;
+
+use addressing::{Family, FAMILY_NAME};
+use limits::Limits;
+
+/// Names the configuration file when `--config` is not given.
+pub const CONFIG_PATH_ENV: &str = "DGC_TP_CONFIG";
+/// Read when neither `--config` nor `DGC_TP_CONFIG` is given, if it exists.
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/dgc/tp.toml";
+
+pub const CONNECT_ENV: &str = "DGC_TP_CONNECT";
+pub const METRICS_ADDRESS_ENV: &str = "DGC_TP_METRICS_ADDRESS";
+pub const HEALTH_ADDRESS_ENV: &str = "DGC_TP_HEALTH_ADDRESS";
+pub const READY_FILE_ENV: &str = "DGC_TP_READY_FILE";
+pub const LOG_LEVEL_ENV: &str = "DGC_TP_LOG_LEVEL";
+pub const LOG_FORMAT_ENV: &str = "DGC_TP_LOG_FORMAT";
+pub const LOG_FILE_ENV: &str = "DGC_TP_LOG_FILE";
+pub const FAMILY_NAME_ENV: &str = "DGC_TP_FAMILY_NAME";
+pub const NAMESPACE_ENV: &str = "DGC_TP_NAMESPACE";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IoError(String),
+    ParseError(String),
+    InvalidValue(String),
+}
+
+impl StdError for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::IoError(ref msg) => msg,
+            ConfigError::ParseError(ref msg) => msg,
+            ConfigError::InvalidValue(ref msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::IoError(ref s) => write!(f, "IoError: {}", s),
+            ConfigError::ParseError(ref s) => write!(f, "ParseError: {}", s),
+            ConfigError::InvalidValue(ref s) => write!(f, "InvalidValue: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Result<LogFormat, ConfigError> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(ConfigError::InvalidValue(format!(
+                "Log format must be text or json, not {}",
+                value
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// One of off, error, warn, info, debug or trace.
+    pub level: String,
+    pub format: LogFormat,
+    /// Also log to this file, rolling it over once it reaches `max_file_size`.
+    pub file: Option<String>,
+    pub max_file_size: u64,
+    /// How many rolled over files to keep, as `<file>.0` to `<file>.<n-1>`.
+    pub max_files: u32,
+}
+
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig {
+            level: String::from("warn"),
+            format: LogFormat::Text,
+            file: None,
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn level_filter(&self) -> Result<LogLevelFilter, ConfigError> {
+        self.level
+            .parse::<LogLevelFilter>()
+            .map_err(|_| ConfigError::InvalidValue(format!("Unknown log level {}", self.level)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FamilyConfig {
+    pub name: String,
+    /// The 6 hex character address prefix. Unset means the prefix derived
+    /// from the family name.
+    pub namespace: Option<String>,
+}
+
+impl Default for FamilyConfig {
+    fn default() -> FamilyConfig {
+        FamilyConfig {
+            name: FAMILY_NAME.to_string(),
+            namespace: None,
+        }
+    }
+}
+
+/// How long to wait before trying the validator again. The delay doubles
+/// after each failure, up to `max_delay_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> ReconnectConfig {
+        ReconnectConfig {
+            initial_delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+/// Settings of the transaction processor, read from a TOML file and then
+/// overridden by environment variables and command line options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProcessorConfig {
+    /// The validator endpoint.
+    pub connect: String,
+    /// Serve Prometheus metrics at `http://<metrics_address>/metrics`. Unset
+    /// means no metrics are collected.
+    pub metrics_address: Option<String>,
+    /// Serve `/healthz` and `/readyz` probes on this address.
+    pub health_address: Option<String>,
+    /// Kept in existence while the processor is connected and accepting
+    /// transactions.
+    pub ready_file: Option<String>,
+    /// How long to wait on shutdown for transactions being applied.
+    pub shutdown_timeout_seconds: u64,
+    pub log: LogConfig,
+    pub family: FamilyConfig,
+    /// Limits used where the matching on-chain setting is unset. Every
+    /// processor on a network must use the same values, or they will
+    /// disagree about which transactions are valid.
+    pub limits: Limits,
+    pub reconnect: ReconnectConfig,
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> ProcessorConfig {
+        ProcessorConfig {
+            connect: String::from("tcp://localhost:4004"),
+            metrics_address: None,
+            health_address: None,
+            ready_file: None,
+            shutdown_timeout_seconds: 30,
+            log: LogConfig::default(),
+            family: FamilyConfig::default(),
+            limits: Limits::default(),
+            reconnect: ReconnectConfig::default(),
+        }
+    }
+}
+
+impl ProcessorConfig {
+    /// Reads the file at `path`, or else the one named by `DGC_TP_CONFIG`,
+    /// or else `DEFAULT_CONFIG_PATH` if it exists. With no file at all the
+    /// defaults are used.
+    pub fn load(path: Option<&str>) -> Result<ProcessorConfig, ConfigError> {
+        let path = path
+            .map(String::from)
+            .or_else(|| env::var(CONFIG_PATH_ENV).ok());
+        match path {
+            Some(path) => ProcessorConfig::from_file(&path),
+            None => {
+                if Path::new(DEFAULT_CONFIG_PATH).exists() {
+                    ProcessorConfig::from_file(DEFAULT_CONFIG_PATH)
+                } else {
+                    Ok(ProcessorConfig::default())
+                }
+            }
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<ProcessorConfig, ConfigError> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|err| ConfigError::IoError(format!("{}: {}", path, err)))?;
+        ProcessorConfig::parse(&contents)
+            .map_err(|err| ConfigError::ParseError(format!("{}: {}", path, err)))
+    }
+
+    pub fn parse(contents: &str) -> Result<ProcessorConfig, ConfigError> {
+        toml::from_str(contents).map_err(|err| ConfigError::ParseError(format!("{}", err)))
+    }
+
+    /// Replaces values with those of the `DGC_TP_*` environment variables
+    /// that are set.
+    pub fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Ok(connect) = env::var(CONNECT_ENV) {
+            self.connect = connect;
+        }
+        if let Ok(address) = env::var(METRICS_ADDRESS_ENV) {
+            self.metrics_address = Some(address);
+        }
+        if let Ok(address) = env::var(HEALTH_ADDRESS_ENV) {
+            self.health_address = Some(address);
+        }
+        if let Ok(path) = env::var(READY_FILE_ENV) {
+            self.ready_file = Some(path);
+        }
+        if let Ok(level) = env::var(LOG_LEVEL_ENV) {
+            self.log.level = level;
+        }
+        if let Ok(format) = env::var(LOG_FORMAT_ENV) {
+            self.log.format = LogFormat::parse(&format)?;
+        }
+        if let Ok(file) = env::var(LOG_FILE_ENV) {
+            self.log.file = Some(file);
+        }
+        if let Ok(name) = env::var(FAMILY_NAME_ENV) {
+            self.family.name = name;
+        }
+        if let Ok(namespace) = env::var(NAMESPACE_ENV) {
+            self.family.namespace = Some(namespace);
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.log.level_filter()?;
+        if self.log.file.is_some() && (self.log.max_file_size == 0 || self.log.max_files == 0) {
+            return Err(ConfigError::InvalidValue(String::from(
+                "log.max_file_size and log.max_files must be greater than 0",
+            )));
+        }
+
+        if self.reconnect.initial_delay_ms == 0
+            || self.reconnect.max_delay_ms < self.reconnect.initial_delay_ms
+        {
+            return Err(ConfigError::InvalidValue(String::from(
+                "reconnect.initial_delay_ms must be greater than 0 and at most max_delay_ms",
+            )));
+        }
+
+        self.family()?;
+        Ok(())
+    }
+
+    /// The family the processor registers for and the namespace it stores
+    /// state under.
+    pub fn family(&self) -> Result<Family, ConfigError> {
+        Family::from_options(
+            Some(&self.family.name),
+            self.family.namespace.as_ref().map(|namespace| namespace.as_str()),
+        ).map_err(ConfigError::InvalidValue)
+    }
+
+    /// The effective configuration as TOML, as shown by `--print-config`.
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string(self).map_err(|err| ConfigError::ParseError(format!("{}", err)))
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use protobuf::RepeatedField;
+
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::transaction::TransactionHeader;
+use sawtooth_sdk::processor::handler::ApplyError;
+use sawtooth_sdk::processor::handler::ContextError;
+use sawtooth_sdk::processor::handler::TransactionContext;
+use sawtooth_sdk::processor::handler::TransactionHandler;
+
+use addressing::*;
+use block_time::block_time_addresses;
+use limits::Limits;
+use transaction::{make_addresses, make_inputs, parse_payload};
+
+/// A TransactionContext backed by an in-memory map of address to state
+/// entry, for running the handler outside of a validator.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryContext {
+    state: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryContext {
+    pub fn new() -> MemoryContext {
+        MemoryContext {
+            state: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_state(state: BTreeMap<String, Vec<u8>>) -> MemoryContext {
+        MemoryContext { state: state }
+    }
+
+    pub fn state(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.state
+    }
+
+    /// Applies a transaction the way a validator would: the writes of a
+    /// rejected transaction are discarded.
+    pub fn apply(
+        &mut self,
+        handler: &TransactionHandler,
+        request: &TpProcessRequest,
+    ) -> Result<(), ApplyError> {
+        let snapshot = self.state.clone();
+        let result = handler.apply(request, self);
+        if result.is_err() {
+            self.state = snapshot;
+        }
+        result
+    }
+}
+
+impl TransactionContext for MemoryContext {
+    fn get_state(&mut self, addresses: Vec<String>) -> Result<Option<Vec<u8>>, ContextError> {
+        Ok(match addresses.first() {
+            Some(address) => self.state.get(address).cloned(),
+            None => None,
+        })
+    }
+
+    fn set_state(&mut self, entries: HashMap<String, Vec<u8>>) -> Result<(), ContextError> {
+        for (address, data) in entries {
+            self.state.insert(address, data);
+        }
+        Ok(())
+    }
+
+    fn delete_state(&mut self, addresses: Vec<String>) -> Result<Option<Vec<String>>, ContextError> {
+        let mut deleted = Vec::new();
+        for address in addresses {
+            if self.state.remove(&address).is_some() {
+                deleted.push(address);
+            }
+        }
+        Ok(Some(deleted))
+    }
+
+    fn add_receipt_data(&mut self, _data: &[u8]) -> Result<(), ContextError> {
+        Ok(())
+    }
+
+    fn add_event(
+        &mut self,
+        _event_type: String,
+        _attributes: Vec<(String, String)>,
+        _data: &[u8],
+    ) -> Result<(), ContextError> {
+        Ok(())
+    }
+}
+
+/// Builds the request the validator would send for a payload of `family`,
+/// in `family_version`, signed by `signer`. Payloads which do not decode are
+/// given the whole namespace as inputs and outputs, so that the handler sees
+/// them and rejects them.
+pub fn make_process_request(
+    family: &Family,
+    family_version: &str,
+    signer: &str,
+    payload: &[u8],
+    signature: &str,
+) -> TpProcessRequest {
+    let (inputs, outputs) = match parse_payload(family_version, payload) {
+        Some(Ok((action, _))) => (
+            make_inputs(family, &action, signer, signer),
+            make_addresses(family, &action, signer),
+        ),
+        _ => {
+            let mut inputs = Limits::setting_addresses();
+            inputs.extend(block_time_addresses());
+            inputs.push(family.namespace().to_string());
+            (inputs, vec![family.namespace().to_string()])
+        }
+    };
+
+    let mut header = TransactionHeader::new();
+    header.set_family_name(family.name().to_string());
+    header.set_family_version(family_version.to_string());
+    header.set_signer_public_key(signer.to_string());
+    header.set_batcher_public_key(signer.to_string());
+    header.set_inputs(RepeatedField::from_vec(inputs));
+    header.set_outputs(RepeatedField::from_vec(outputs));
+
+    let mut request = TpProcessRequest::new();
+    request.set_header(header);
+    request.set_payload(payload.to_vec());
+    request.set_signature(signature.to_string());
+    request
+}
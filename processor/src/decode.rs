@@ -0,0 +1,525 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+use protobuf;
+use rustc_serialize::hex::ToHex;
+use serde_json::{Map, Value};
+
+use addressing::*;
+use messages::*;
+use schema::{data_type_name, PropertyDefinition, TableDefinition};
+
+#[derive(Debug)]
+pub enum DecodeError {
+    UnknownAddress(String),
+    ProtobufError(String, protobuf::ProtobufError),
+}
+
+impl StdError for DecodeError {
+    fn description(&self) -> &str {
+        match *self {
+            DecodeError::UnknownAddress(ref address) => address,
+            DecodeError::ProtobufError(_, ref err) => err.description(),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::UnknownAddress(ref address) => {
+                write!(f, "UnknownAddress: {}", address)
+            }
+            DecodeError::ProtobufError(ref address, ref err) => {
+                write!(f, "Cannot deserialize {}: {}", address, err)
+            }
+        }
+    }
+}
+
+/// The kinds of entity stored under the dgc-REST-api namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntryType {
+    Participant,
+    Record,
+    Table,
+    Property,
+    PropertyPage,
+    Proposal,
+    Exchange,
+    EmailIndex,
+    KeyAlias,
+    Role,
+    Organization,
+    MultisigPolicy,
+    PendingApproval,
+}
+
+impl EntryType {
+    /// The type of entry stored at `address`, if it is in `namespace`.
+    pub fn from_address(namespace: &str, address: &str) -> Option<EntryType> {
+        if !address.starts_with(namespace) || address.len() != 70 {
+            return None;
+        }
+        match &address[6..8] {
+            PARTICIPANT => Some(EntryType::Participant),
+            RECORD => Some(EntryType::Record),
+            TABLE => Some(EntryType::Table),
+            PROPERTY if address.ends_with(&num_to_page_number(0)) => Some(EntryType::Property),
+            PROPERTY => Some(EntryType::PropertyPage),
+            PROPOSAL => Some(EntryType::Proposal),
+            EXCHANGE => Some(EntryType::Exchange),
+            EMAIL_INDEX => Some(EntryType::EmailIndex),
+            KEY_ALIAS => Some(EntryType::KeyAlias),
+            ROLE => Some(EntryType::Role),
+            ORGANIZATION => Some(EntryType::Organization),
+            MULTISIG_POLICY => Some(EntryType::MultisigPolicy),
+            PENDING_APPROVAL => Some(EntryType::PendingApproval),
+            _ => None,
+        }
+    }
+
+    /// The plural name used to group entities of this type in documents.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EntryType::Participant => "participants",
+            EntryType::Record => "records",
+            EntryType::Table => "tables",
+            EntryType::Property => "properties",
+            EntryType::PropertyPage => "property_pages",
+            EntryType::Proposal => "proposals",
+            EntryType::Exchange => "exchanges",
+            EntryType::EmailIndex => "email_index",
+            EntryType::KeyAlias => "key_aliases",
+            EntryType::Role => "roles",
+            EntryType::Organization => "organizations",
+            EntryType::MultisigPolicy => "multisig_policies",
+            EntryType::PendingApproval => "pending_approvals",
+        }
+    }
+}
+
+/// Decodes a state entry into one JSON document per container entry.
+pub fn decode_entry(
+    namespace: &str,
+    address: &str,
+    data: &[u8],
+) -> Result<(EntryType, Vec<Value>), DecodeError> {
+    let entry_type = match EntryType::from_address(namespace, address) {
+        Some(entry_type) => entry_type,
+        None => return Err(DecodeError::UnknownAddress(address.to_string())),
+    };
+    let to_err = |err| DecodeError::ProtobufError(address.to_string(), err);
+
+    let documents = match entry_type {
+        EntryType::Participant => {
+            let container: participant::ParticipantContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(participant_to_json).collect()
+        }
+        EntryType::Record => {
+            let container: record::RecordContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(record_to_json).collect()
+        }
+        EntryType::Table => {
+            let container: record::TableContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(table_to_json).collect()
+        }
+        EntryType::Property => {
+            let container: property::PropertyContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(property_to_json).collect()
+        }
+        EntryType::PropertyPage => {
+            let container: property::PropertyPageContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            let page = u32::from_str_radix(&address[66..], 16).unwrap_or(0);
+            container
+                .get_entries()
+                .iter()
+                .map(|entry| property_page_to_json(entry, page))
+                .collect()
+        }
+        EntryType::Proposal => {
+            let container: proposal::ProposalContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(proposal_to_json).collect()
+        }
+        EntryType::Exchange => {
+            let container: proposal::ExchangeContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(exchange_to_json).collect()
+        }
+        EntryType::EmailIndex => {
+            let container: participant::EmailIndexContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container
+                .get_entries()
+                .iter()
+                .map(|entry| json!({
+                    "email": entry.get_email(),
+                    "public_key": entry.get_public_key(),
+                }))
+                .collect()
+        }
+        EntryType::KeyAlias => {
+            let container: participant::KeyAliasContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container
+                .get_entries()
+                .iter()
+                .map(|entry| json!({
+                    "public_key": entry.get_public_key(),
+                    "participant_id": entry.get_participant_id(),
+                }))
+                .collect()
+        }
+        EntryType::Role => {
+            let container: role::RoleAssignmentContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container
+                .get_entries()
+                .iter()
+                .map(|entry| json!({
+                    "public_key": entry.get_public_key(),
+                    "roles": entry
+                        .get_roles()
+                        .iter()
+                        .map(|role| format!("{:?}", role))
+                        .collect::<Vec<String>>(),
+                    "assigned_by": entry.get_assigned_by(),
+                    "timestamp": entry.get_timestamp(),
+                }))
+                .collect()
+        }
+        EntryType::Organization => {
+            let container: organization::OrganizationContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container.get_entries().iter().map(organization_to_json).collect()
+        }
+        EntryType::MultisigPolicy => {
+            let container: multisig::MultisigPolicyContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container
+                .get_entries()
+                .iter()
+                .map(|entry| json!({
+                    "policy_id": entry.get_policy_id(),
+                    "signers": entry.get_signers().to_vec(),
+                    "threshold": entry.get_threshold(),
+                    "created_by": entry.get_created_by(),
+                    "timestamp": entry.get_timestamp(),
+                }))
+                .collect()
+        }
+        EntryType::PendingApproval => {
+            let container: multisig::PendingApprovalContainer =
+                protobuf::parse_from_bytes(data).map_err(to_err)?;
+            container
+                .get_entries()
+                .iter()
+                .map(|entry| json!({
+                    "approval_id": entry.get_approval_id(),
+                    "action": entry.get_action(),
+                    "record_id": entry.get_record_id(),
+                    "approvers": entry.get_approvers().to_vec(),
+                    "timestamp": entry.get_timestamp(),
+                }))
+                .collect()
+        }
+    };
+    Ok((entry_type, documents))
+}
+
+pub fn participant_to_json(participant: &participant::Participant) -> Value {
+    json!({
+        "public_key": participant.get_public_key(),
+        "name": participant.get_name(),
+        "email": participant.get_email(),
+        "timestamp": participant.get_timestamp(),
+        "format_version": participant.get_format_version(),
+        "profile_history": participant
+            .get_profile_history()
+            .iter()
+            .map(|change| json!({
+                "name": change.get_name(),
+                "email": change.get_email(),
+                "timestamp": change.get_timestamp(),
+                "block_timestamp": change.get_block_timestamp(),
+            }))
+            .collect::<Vec<Value>>(),
+        "signing_key": participant.get_signing_key(),
+        "recovery_key": participant.get_recovery_key(),
+        "status": format!("{:?}", participant.get_status()),
+        "status_history": participant
+            .get_status_history()
+            .iter()
+            .map(|change| json!({
+                "status": format!("{:?}", change.get_status()),
+                "changed_by": change.get_changed_by(),
+                "reason": change.get_reason(),
+                "timestamp": change.get_timestamp(),
+                "block_timestamp": change.get_block_timestamp(),
+            }))
+            .collect::<Vec<Value>>(),
+        "key_rotations": participant
+            .get_key_rotations()
+            .iter()
+            .map(|rotation| json!({
+                "previous_key": rotation.get_previous_key(),
+                "new_key": rotation.get_new_key(),
+                "rotated_by": rotation.get_rotated_by(),
+                "timestamp": rotation.get_timestamp(),
+                "block_timestamp": rotation.get_block_timestamp(),
+            }))
+            .collect::<Vec<Value>>(),
+        "dg_coin_balances": participant
+            .get_dg_coin_balances()
+            .iter()
+            .map(credit_balance_to_json)
+            .collect::<Vec<Value>>(),
+        "dg_coin_credits": participant
+            .get_dg_coin_credits()
+            .iter()
+            .map(credit_balance_to_json)
+            .collect::<Vec<Value>>(),
+        "dg_coin_drawn_credits": participant
+            .get_dg_coin_drawn_credits()
+            .iter()
+            .map(credit_balance_to_json)
+            .collect::<Vec<Value>>(),
+    })
+}
+
+fn credit_balance_to_json(balance: &participant::Participant_CreditBalance) -> Value {
+    json!({
+        "timestamp": balance.get_timestamp(),
+        "block_timestamp": balance.get_block_timestamp(),
+        "dg_coin_amount": balance.get_dg_coin_amount(),
+        "proposal_id": balance.get_proposal_id(),
+        "changed_by": balance.get_changed_by(),
+    })
+}
+
+pub fn record_to_json(record: &record::Record) -> Value {
+    json!({
+        "record_id": record.get_record_id(),
+        "table": record.get_table(),
+        "owners": record
+            .get_owners()
+            .iter()
+            .map(associated_participant_to_json)
+            .collect::<Vec<Value>>(),
+        "custodians": record
+            .get_custodians()
+            .iter()
+            .map(associated_participant_to_json)
+            .collect::<Vec<Value>>(),
+        "final": record.get_field_final(),
+        "format_version": record.get_format_version(),
+    })
+}
+
+fn associated_participant_to_json(associated: &record::Record_AssociatedParticipant) -> Value {
+    json!({
+        "participant_id": associated.get_participant_id(),
+        "timestamp": associated.get_timestamp(),
+        "block_timestamp": associated.get_block_timestamp(),
+    })
+}
+
+pub fn table_to_json(table: &record::Table) -> Value {
+    json!(TableDefinition::from_table(table))
+}
+
+pub fn property_to_json(property: &property::Property) -> Value {
+    json!({
+        "name": property.get_name(),
+        "record_id": property.get_record_id(),
+        "data_type": data_type_name(property.get_data_type()),
+        "reporters": property
+            .get_reporters()
+            .iter()
+            .map(|reporter| json!({
+                "public_key": reporter.get_public_key(),
+                "authorized": reporter.get_authorized(),
+                "index": reporter.get_index(),
+            }))
+            .collect::<Vec<Value>>(),
+        "current_page": property.get_current_page(),
+        "wrapped": property.get_wrapped(),
+        "fixed": property.get_fixed(),
+        "number_exponent": property.get_number_exponent(),
+        "enum_options": property.get_enum_options().to_vec(),
+        "struct_properties": property
+            .get_struct_properties()
+            .iter()
+            .map(|schema| json!(PropertyDefinition::from_property_schema(schema)))
+            .collect::<Vec<Value>>(),
+        "unit": property.get_unit(),
+    })
+}
+
+pub fn property_page_to_json(page: &property::PropertyPage, page_number: u32) -> Value {
+    json!({
+        "name": page.get_name(),
+        "record_id": page.get_record_id(),
+        "page": page_number,
+        "reported_values": page
+            .get_reported_values()
+            .iter()
+            .map(reported_value_to_json)
+            .collect::<Vec<Value>>(),
+    })
+}
+
+pub fn reported_value_to_json(value: &property::PropertyPage_ReportedValue) -> Value {
+    json!({
+        "reporter_index": value.get_reporter_index(),
+        "timestamp": value.get_timestamp(),
+        "block_timestamp": value.get_block_timestamp(),
+        "bytes_value": value.get_bytes_value().to_hex(),
+        "boolean_value": value.get_boolean_value(),
+        "number_value": value.get_number_value(),
+        "string_value": value.get_string_value(),
+        "enum_value": value.get_enum_value(),
+        "struct_values": value
+            .get_struct_values()
+            .iter()
+            .map(property_value_to_json)
+            .collect::<Vec<Value>>(),
+        "location_value": location_to_json(value.get_location_value()),
+    })
+}
+
+pub fn property_value_to_json(value: &property::PropertyValue) -> Value {
+    let mut document = json!({
+        "name": value.get_name(),
+        "data_type": data_type_name(value.get_data_type()),
+    });
+    let typed = match value.get_data_type() {
+        property::PropertySchema_DataType::TYPE_UNSET => None,
+        property::PropertySchema_DataType::BYTES => {
+            Some(("bytes_value", json!(value.get_bytes_value().to_hex())))
+        }
+        property::PropertySchema_DataType::BOOLEAN => {
+            Some(("boolean_value", json!(value.get_boolean_value())))
+        }
+        property::PropertySchema_DataType::NUMBER => {
+            Some(("number_value", json!(value.get_number_value())))
+        }
+        property::PropertySchema_DataType::STRING => {
+            Some(("string_value", json!(value.get_string_value())))
+        }
+        property::PropertySchema_DataType::ENUM => {
+            Some(("enum_value", json!(value.get_enum_value())))
+        }
+        property::PropertySchema_DataType::STRUCT => Some((
+            "struct_values",
+            json!(value
+                .get_struct_values()
+                .iter()
+                .map(property_value_to_json)
+                .collect::<Vec<Value>>()),
+        )),
+        property::PropertySchema_DataType::LOCATION => Some((
+            "location_value",
+            location_to_json(value.get_location_value()),
+        )),
+    };
+    if let Some((key, typed_value)) = typed {
+        document[key] = typed_value;
+    }
+    document
+}
+
+fn location_to_json(location: &property::Location) -> Value {
+    json!({
+        "latitude": location.get_latitude(),
+        "longitude": location.get_longitude(),
+    })
+}
+
+pub fn proposal_to_json(proposal: &proposal::Proposal) -> Value {
+    json!({
+        "proposal_id": proposal.get_proposal_id(),
+        "role": format!("{:?}", proposal.get_role()),
+        "status": format!("{:?}", proposal.get_status()),
+        "timestamp": proposal.get_timestamp(),
+        "issuing_participant": proposal.get_issuing_participant(),
+        "receiving_participant": proposal.get_receiving_participant(),
+        "record_id": proposal.get_record_id(),
+        "properties": proposal.get_properties().to_vec(),
+        "terms": proposal.get_terms(),
+        "dg_coin_amount": proposal.get_dg_coin_amount(),
+        "currency_iso_codes": proposal.get_currency_iso_codes(),
+        "currency_quote_amount": proposal.get_currency_quote_amount(),
+        "dg_coin_exchanged": proposal.get_dg_coin_exchanged(),
+        "format_version": proposal.get_format_version(),
+        "expires_at": proposal.get_expires_at(),
+        "parent_proposal_id": proposal.get_parent_proposal_id(),
+        "sent_to_issuer": proposal.get_sent_to_issuer(),
+    })
+}
+
+pub fn organization_to_json(organization: &organization::Organization) -> Value {
+    json!({
+        "organization_id": organization.get_organization_id(),
+        "name": organization.get_name(),
+        "members": organization
+            .get_members()
+            .iter()
+            .map(|member| json!({
+                "participant_id": member.get_participant_id(),
+                "permissions": member
+                    .get_permissions()
+                    .iter()
+                    .map(|permission| format!("{:?}", permission))
+                    .collect::<Vec<String>>(),
+            }))
+            .collect::<Vec<Value>>(),
+        "created_by": organization.get_created_by(),
+        "timestamp": organization.get_timestamp(),
+    })
+}
+
+pub fn exchange_to_json(exchange: &proposal::Exchange) -> Value {
+    json!({
+        "buy_proposal_id": exchange.get_buy_proposal_id(),
+        "sell_proposal_id": exchange.get_sell_proposal_id(),
+        "timestamp": exchange.get_timestamp(),
+        "last_dgc_price": exchange.get_last_dgc_price(),
+        "currency_iso_codes": exchange.get_currency_iso_codes(),
+        "last_currency_price": exchange.get_last_currency_price(),
+    })
+}
+
+/// Decodes every dgc-REST-api entry of a state snapshot, stored under
+/// `namespace`, into a document grouping entities by type. Entries are
+/// ordered by address so identical state always gives identical output.
+pub fn decode_state(
+    namespace: &str,
+    state: &BTreeMap<String, Vec<u8>>,
+) -> Result<Value, DecodeError> {
+    let mut groups: BTreeMap<EntryType, Vec<Value>> = BTreeMap::new();
+    for (address, data) in state {
+        let (entry_type, documents) = decode_entry(namespace, address, data)?;
+        let group = groups.entry(entry_type).or_insert_with(Vec::new);
+        for mut document in documents {
+            document["address"] = json!(address);
+            group.push(document);
+        }
+    }
+
+    let mut decoded = Map::new();
+    for (entry_type, documents) in groups {
+        decoded.insert(entry_type.name().to_string(), Value::Array(documents));
+    }
+    Ok(Value::Object(decoded))
+}
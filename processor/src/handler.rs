@@ -6,105 +6,82 @@ use protobuf::Message;
 use protobuf::RepeatedField;
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
 
 use sawtooth_sdk::processor::handler::ApplyError;
 use sawtooth_sdk::processor::handler::TransactionContext;
 use sawtooth_sdk::processor::handler::TransactionHandler;
 use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::signing::Context;
+use sawtooth_sdk::signing::secp256k1::{Secp256k1Context, Secp256k1PublicKey};
 
 use messages::*;
 use addressing::*;
+use block_time::{check_timestamp, current_time, within_legacy_rules};
+use lifecycle::Lifecycle;
+use limits::Limits;
+use logging;
+use metrics::{MeteredContext, Metrics};
+use migrate::*;
+use roles::{grants, Permission, ENFORCE_KEY};
+use settings::{approval_lifetime, get_setting, is_admin};
+use transaction::{
+    make_payload_v2, parse_payload, settlement_answer, PayloadAction, FAMILY_VERSION,
+    FAMILY_VERSIONS,
+};
 
 const PROPERTY_PAGE_MAX_LENGTH: usize = 256;
 
-#[derive(Debug, Clone)]
-enum Action {
-    CreateParticipant(payload::CreateParticipantAction),
-    CreateRecord(payload::CreateRecordAction),
-    FinalizeRecord(payload::FinalizeRecordAction),
-    CreateTable(payload::CreateTableAction),
-    UpdateProperties(payload::UpdatePropertiesAction),
-    CreateProposal(payload::CreateProposalAction),
-    AnswerProposal(payload::AnswerProposalAction),
-    RevokeReporter(payload::RevokeReporterAction),
-}
-
 struct PayloadDGC {
-    action: Action,
+    action: PayloadAction,
     timestamp: u64,
 }
 
 impl PayloadDGC {
-    pub fn new(payload: &[u8]) -> Result<Option<PayloadDGC>, ApplyError> {
-        let payload: payload::PayloadDGC = match protobuf::parse_from_bytes(payload) {
-            Ok(payload) => payload,
-            Err(_) => {
+    /// Decodes a payload of `family_version`. Every version decodes to the
+    /// same actions, which are then checked the same way.
+    pub fn new(
+        family_version: &str,
+        payload: &[u8],
+        limits: &Limits,
+    ) -> Result<Option<PayloadDGC>, ApplyError> {
+        limits.check_payload_size(payload.len())?;
+        let (action, timestamp) = match parse_payload(family_version, payload) {
+            Some(Ok(decoded)) => decoded,
+            Some(Err(_)) => {
                 return Err(ApplyError::InvalidTransaction(String::from(
                     "Cannot deserialize payload",
                 )))
             }
-        };
-
-        let dgc_rest_api_action = payload.get_action();
-        let action = match dgc_rest_api_action {
-            payload::PayloadDGC_Action::CREATE_PARTICIPANT => {
-                let create_participant = payload.get_create_participant();
-                if create_participant.get_name() == "" {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Participant name cannot be an empty string",
-                    )));
-                }
-                Action::CreateParticipant(create_participant.clone())
-            }
-            payload::PayloadDGC_Action::CREATE_RECORD => {
-                let create_record = payload.get_create_record();
-                if create_record.get_record_id() == "" {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Record id cannot be empty string",
-                    )));
-                }
-                Action::CreateRecord(create_record.clone())
-            }
-            payload::PayloadDGC_Action::FINALIZE_RECORD => {
-                Action::FinalizeRecord(payload.get_finalize_record().clone())
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Unsupported family version: {}",
+                    family_version
+                )))
             }
-            payload::PayloadDGC_Action::CREATE_TABLE => {
-                let create_table = payload.get_create_table();
-                if create_table.get_name() == "" {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Table name cannot be an empty string",
-                    )));
-                };
-                let properties = create_table.get_properties();
-                if properties.len() == 0 {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Table must have at least one property",
-                    )));
-                }
-                for prop in properties {
-                    if prop.name == "" {
-                        return Err(ApplyError::InvalidTransaction(String::from(
-                            "Property name cannot be an empty string",
-                        )));
-                    }
-                }
+        };
+        limits.check_action(&action)?;
 
-                Action::CreateTable(create_table.clone())
-            }
-            payload::PayloadDGC_Action::UPDATE_PROPERTIES => {
-                Action::UpdateProperties(payload.get_update_properties().clone())
+        match action {
+            PayloadAction::CreateParticipant(ref create_participant) => {
+                validate_profile(create_participant.get_name(), create_participant.get_email())?
             }
-            payload::PayloadDGC_Action::CREATE_PROPOSAL => {
-                Action::CreateProposal(payload.get_create_proposal().clone())
+            PayloadAction::UpdateParticipant(ref update_participant) => {
+                validate_profile(update_participant.get_name(), update_participant.get_email())?
             }
-            payload::PayloadDGC_Action::ANSWER_PROPOSAL => {
-                Action::AnswerProposal(payload.get_answer_proposal().clone())
-            }
-            payload::PayloadDGC_Action::REVOKE_REPORTER => {
-                Action::RevokeReporter(payload.get_revoke_reporter().clone())
+            PayloadAction::CreateRecord(ref create_record) if create_record.get_record_id() == "" => {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Record id cannot be empty string",
+                )));
             }
-        };
-        let timestamp = match payload.get_timestamp() {
+            PayloadAction::CreateTable(ref create_table) => validate_create_table(create_table)?,
+            _ => {}
+        }
+        let timestamp = match timestamp {
             0 => {
                 return Err(ApplyError::InvalidTransaction(String::from(
                     "Timestamp is not set",
@@ -119,7 +96,7 @@ impl PayloadDGC {
         }))
     }
 
-    pub fn get_action(&self) -> Action {
+    pub fn get_action(&self) -> PayloadAction {
         self.action.clone()
     }
 
@@ -128,17 +105,321 @@ impl PayloadDGC {
     }
 }
 
+/// Checks the name and optional email a participant is created or updated
+/// with.
+fn validate_profile(name: &str, email: &str) -> Result<(), ApplyError> {
+    if name == "" {
+        return Err(ApplyError::InvalidTransaction(String::from(
+            "Participant name cannot be an empty string",
+        )));
+    }
+    let email = email.trim();
+    if email == "" {
+        return Ok(());
+    }
+    let valid = match email.find('@') {
+        Some(at) => {
+            let domain = &email[at + 1..];
+            at > 0
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    };
+    if !valid {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Invalid email address: {}",
+            email
+        )));
+    }
+    Ok(())
+}
+
+/// The key which signs for `participant`: its registered key until the first
+/// rotation.
+fn current_signing_key(participant: &participant::Participant) -> &str {
+    match participant.get_signing_key() {
+        "" => participant.get_public_key(),
+        signing_key => signing_key,
+    }
+}
+
+/// Refuses a participant which is suspended or deactivated. `role` names it
+/// in the error.
+fn check_active(participant: &participant::Participant, role: &str) -> Result<(), ApplyError> {
+    let status = match participant.get_status() {
+        participant::Participant_Status::ACTIVE => return Ok(()),
+        participant::Participant_Status::SUSPENDED => "suspended",
+        participant::Participant_Status::DEACTIVATED => "deactivated",
+    };
+    Err(ApplyError::InvalidTransaction(format!(
+        "{} {} is {}",
+        role,
+        participant.get_public_key(),
+        status
+    )))
+}
+
+/// The participant behind a party whose balances a proposal moves, which
+/// organizations and multisig policies, having none, cannot be.
+fn balance_holder(
+    participant: Option<participant::Participant>,
+    role: &str,
+    id: &str,
+) -> Result<participant::Participant, ApplyError> {
+    match participant {
+        Some(participant) => Ok(participant),
+        None => Err(ApplyError::InvalidTransaction(format!(
+            "{} must be a participant: {}",
+            role, id
+        ))),
+    }
+}
+
+/// The outcome of `StateDGC::consent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consent {
+    /// Every party needed has agreed, so the action runs
+    Given,
+    /// The approval was kept until a multisig policy's threshold is reached
+    Pending,
+    /// The signer cannot act for the parties
+    Refused,
+}
+
+/// Identifies an action waiting for approvals by what it does and whose
+/// consent it needs, so approvals only add up for the same action. The
+/// action is hashed as a version 2 payload, whatever version it came in.
+fn approval_id(action: &PayloadAction, parties: &[&str]) -> Result<String, ApplyError> {
+    let action_bytes = make_payload_v2(action.clone(), 0)
+        .write_to_bytes()
+        .map_err(|_| ApplyError::InternalError(String::from("Cannot serialize action")))?;
+    let mut sha = Sha512::new();
+    for party in parties {
+        sha.input_str(party);
+        sha.input(&[0]);
+    }
+    sha.input(&action_bytes);
+    Ok(sha.result_str())
+}
+
+/// Whether `proposal` has an expiry, and it has passed at `now`.
+fn is_expired(proposal: &proposal::Proposal, now: u64) -> bool {
+    proposal.get_expires_at() != 0 && now >= proposal.get_expires_at()
+}
+
+/// Whether the approvals collected in `approval` no longer count at `now`.
+/// Approvals kept before they were given an expiry never expire.
+fn approval_expired(approval: &multisig::PendingApproval, now: u64) -> bool {
+    approval.get_expires_at() != 0 && now >= approval.get_expires_at()
+}
+
+/// The latest amount of a history of balances, credit limits or drawn
+/// credit.
+fn latest_amount(entries: &[participant::Participant_CreditBalance]) -> f64 {
+    match entries.last() {
+        Some(entry) => entry.get_dg_coin_amount(),
+        None => 0.0,
+    }
+}
+
+/// Adds an entry to a history of balances, credit limits or drawn credit,
+/// changed by `amount` from the latest. Entries are changed either by the
+/// proposal `proposal_id` or directly by the participant `changed_by`.
+fn change_amount(
+    entries: &mut RepeatedField<participant::Participant_CreditBalance>,
+    amount: f64,
+    proposal_id: &str,
+    changed_by: &str,
+    timestamp: u64,
+    block_timestamp: u64,
+) {
+    let mut entry = participant::Participant_CreditBalance::new();
+    entry.set_proposal_id(proposal_id.to_string());
+    entry.set_changed_by(changed_by.to_string());
+    entry.set_timestamp(timestamp);
+    entry.set_block_timestamp(block_timestamp);
+    entry.set_dg_coin_amount(latest_amount(entries) + amount);
+    entries.push(entry);
+}
+
+/// The credit `participant` can still draw: its limit less what it has
+/// drawn, or nothing if its limit was lowered below that.
+fn available_credit(participant: &participant::Participant) -> f64 {
+    let available = latest_amount(participant.get_dg_coin_credits())
+        - latest_amount(participant.get_dg_coin_drawn_credits());
+    if available > 0.0 {
+        available
+    } else {
+        0.0
+    }
+}
+
+/// Takes `amount` from the balance of `participant` for the proposal
+/// `proposal_id`, drawing on its credit for what the balance does not cover.
+fn spend_dg_coin(
+    participant: &mut participant::Participant,
+    amount: f64,
+    proposal_id: &str,
+    timestamp: u64,
+    block_timestamp: u64,
+) -> Result<(), ApplyError> {
+    let balance = latest_amount(participant.get_dg_coin_balances());
+    let from_balance = if balance < amount { balance } else { amount };
+    let drawn = amount - from_balance;
+    if drawn > available_credit(participant) {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "The dg coin balance and credit of issuing participant are not enough: {}",
+            participant.get_public_key()
+        )));
+    }
+
+    change_amount(
+        &mut participant.dg_coin_balances,
+        -from_balance,
+        proposal_id,
+        "",
+        timestamp,
+        block_timestamp,
+    );
+    if drawn > 0.0 {
+        change_amount(
+            &mut participant.dg_coin_drawn_credits,
+            drawn,
+            proposal_id,
+            "",
+            timestamp,
+            block_timestamp,
+        );
+    }
+    Ok(())
+}
+
+/// The parties which answer and which can cancel `proposal`: its receiver
+/// and its issuer, the other way round for a counter-offer sent back to the
+/// issuer.
+fn answering_parties(proposal: &proposal::Proposal) -> (String, String) {
+    let issuer = proposal.get_issuing_participant().to_string();
+    let receiver = proposal.get_receiving_participant().to_string();
+    if proposal.get_sent_to_issuer() {
+        (issuer, receiver)
+    } else {
+        (receiver, issuer)
+    }
+}
+
+/// Records `signer` as the user of `email`, unless another participant
+/// already uses it.
+fn claim_email(state: &mut StateDGC, email: &str, signer: &str) -> Result<(), ApplyError> {
+    match state.get_email_owner(email)? {
+        Some(ref owner) if owner != signer => Err(ApplyError::InvalidTransaction(format!(
+            "Email is already used by another participant: {}",
+            email
+        ))),
+        _ => state.set_email_owner(email, Some(signer)),
+    }
+}
+
+/// Whether `signature` is the signature of `message` by `public_key`. A key or
+/// signature which does not parse does not verify.
+fn verify_signature(public_key: &str, signature: &str, message: &[u8]) -> bool {
+    let public_key = match Secp256k1PublicKey::from_hex(public_key) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+    Secp256k1Context::new()
+        .verify(signature, message, &public_key)
+        .unwrap_or(false)
+}
+
+/// Checks applied to every CreateTableAction before it reaches state. Shared
+/// with the schema file importer so both reject the same tables.
+pub fn validate_create_table(create_table: &payload::CreateTableAction) -> Result<(), ApplyError> {
+    if create_table.get_name() == "" {
+        return Err(ApplyError::InvalidTransaction(String::from(
+            "Table name cannot be an empty string",
+        )));
+    };
+    let properties = create_table.get_properties();
+    if properties.len() == 0 {
+        return Err(ApplyError::InvalidTransaction(String::from(
+            "Table must have at least one property",
+        )));
+    }
+    for prop in properties {
+        if prop.name == "" {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Property name cannot be an empty string",
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that struct values match the struct schema, recursing into nested
+/// structs.
+pub fn validate_struct_values(
+    struct_values: &RepeatedField<property::PropertyValue>,
+    schema_values: &RepeatedField<property::PropertySchema>
+) -> Result<(), ApplyError> {
+    if struct_values.len() != schema_values.len() {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "Provided struct does not match schema length: {:?} != {:?}",
+            struct_values.len(),
+            schema_values.len(),
+        )))
+    }
+
+    for schema in schema_values.iter() {
+        let value = match struct_values.iter().find(|val| val.name == schema.name) {
+            Some(val) => val,
+            None => return Err(ApplyError::InvalidTransaction(format!(
+                "Provided struct missing required property from schema: {}",
+                schema.name,
+            )))
+        };
+
+        if value.data_type != schema.data_type {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Struct property \"{}\" must have data type: {:?}",
+                schema.name,
+                schema.data_type,
+            )))
+        }
+
+        if schema.data_type == property::PropertySchema_DataType::STRUCT {
+            match validate_struct_values(
+                &value.struct_values,
+                &schema.struct_properties
+            ) {
+                Ok(_) => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub struct StateDGC<'a> {
     context: &'a mut TransactionContext,
+    namespace: String,
 }
 
 impl<'a> StateDGC<'a> {
-    pub fn new(context: &'a mut TransactionContext) -> StateDGC {
-        StateDGC { context: context }
+    /// State of the family stored under `namespace`.
+    pub fn new(context: &'a mut TransactionContext, namespace: &str) -> StateDGC<'a> {
+        StateDGC {
+            context: context,
+            namespace: namespace.to_string(),
+        }
     }
 
     pub fn get_record(&mut self, record_id: &str) -> Result<Option<record::Record>, ApplyError> {
-        let address = make_record_address(record_id);
+        let address = make_record_address(&self.namespace, record_id);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -154,7 +435,9 @@ impl<'a> StateDGC<'a> {
 
                 for record in records.get_entries() {
                     if record.record_id == record_id {
-                        return Ok(Some(record.clone()));
+                        let mut record = record.clone();
+                        upgrade_record(&mut record)?;
+                        return Ok(Some(record));
                     }
                 }
                 Ok(None)
@@ -166,9 +449,10 @@ impl<'a> StateDGC<'a> {
     pub fn set_record(
         &mut self,
         record_id: &str,
-        record: record::Record,
+        mut record: record::Record,
     ) -> Result<(), ApplyError> {
-        let address = make_record_address(record_id);
+        record.set_format_version(RECORD_FORMAT);
+        let address = make_record_address(&self.namespace, record_id);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut record_container = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -221,7 +505,7 @@ impl<'a> StateDGC<'a> {
         &mut self,
         name: &str,
     ) -> Result<Option<record::Table>, ApplyError> {
-        let address = make_table_address(name);
+        let address = make_table_address(&self.namespace, name);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -251,7 +535,7 @@ impl<'a> StateDGC<'a> {
         name: &str,
         table: record::Table,
     ) -> Result<(), ApplyError> {
-        let address = make_table_address(name);
+        let address = make_table_address(&self.namespace, name);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut tables = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -287,7 +571,7 @@ impl<'a> StateDGC<'a> {
         &mut self, 
         participant_id: &str
     ) -> Result<Option<participant::Participant>, ApplyError> {
-        let address = make_participant_address(participant_id);
+        let address = make_participant_address(&self.namespace, participant_id);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -303,7 +587,9 @@ impl<'a> StateDGC<'a> {
 
                 for participant in participants.get_entries() {
                     if participant.public_key == participant_id {
-                        return Ok(Some(participant.clone()));
+                        let mut participant = participant.clone();
+                        upgrade_participant(&mut participant)?;
+                        return Ok(Some(participant));
                     }
                 }
                 Ok(None)
@@ -315,9 +601,10 @@ impl<'a> StateDGC<'a> {
     pub fn set_participant(
         &mut self, 
         participant_id: &str, 
-        participant: participant::Participant
+        mut participant: participant::Participant
     ) -> Result<(), ApplyError> {
-        let address = make_participant_address(participant_id);
+        participant.set_format_version(PARTICIPANT_FORMAT);
+        let address = make_participant_address(&self.namespace, participant_id);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut participant_container = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -373,7 +660,7 @@ impl<'a> StateDGC<'a> {
         record_id: &str,
         property_name: &str,
     ) -> Result<Option<property::Property>, ApplyError> {
-        let address = make_property_address(record_id, property_name, 0);
+        let address = make_property_address(&self.namespace, record_id, property_name, 0);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -404,7 +691,7 @@ impl<'a> StateDGC<'a> {
         property_name: &str,
         property: property::Property,
     ) -> Result<(), ApplyError> {
-        let address = make_property_address(record_id, property_name, 0);
+        let address = make_property_address(&self.namespace, record_id, property_name, 0);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut property_container = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -459,7 +746,7 @@ impl<'a> StateDGC<'a> {
         property_name: &str,
         page: u32,
     ) -> Result<Option<property::PropertyPage>, ApplyError> {
-        let address = make_property_address(record_id, property_name, page);
+        let address = make_property_address(&self.namespace, record_id, property_name, page);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -491,7 +778,7 @@ impl<'a> StateDGC<'a> {
         page_num: u32,
         property_page: property::PropertyPage,
     ) -> Result<(), ApplyError> {
-        let address = make_property_address(record_id, property_name, page_num);
+        let address = make_property_address(&self.namespace, record_id, property_name, page_num);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut property_pages = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -544,7 +831,7 @@ impl<'a> StateDGC<'a> {
         &mut self,
         proposal_id: &str,
     ) -> Result<Option<proposal::Proposal>, ApplyError> {
-        let address = make_proposal_address(proposal_id);
+        let address = make_proposal_address(&self.namespace, proposal_id);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -560,7 +847,9 @@ impl<'a> StateDGC<'a> {
 
                 for proposal in proposals.get_entries() {
                     if proposal.proposal_id == proposal_id {
-                        return Ok(Some(proposal.clone()));
+                        let mut proposal = proposal.clone();
+                        upgrade_proposal(&mut proposal)?;
+                        return Ok(Some(proposal));
                     }
                 }
                 Ok(None)
@@ -572,9 +861,10 @@ impl<'a> StateDGC<'a> {
     pub fn set_proposal(
         &mut self,
         proposal_id: &str,
-        proposal: proposal::Proposal,
+        mut proposal: proposal::Proposal,
     ) -> Result<(), ApplyError> {
-        let address = make_proposal_address(proposal_id);
+        proposal.set_format_version(PROPOSAL_FORMAT);
+        let address = make_proposal_address(&self.namespace, proposal_id);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut proposal_container = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -630,7 +920,7 @@ impl<'a> StateDGC<'a> {
         buy_proposal_id: &str,
         sell_proposal_id: &str,
     ) -> Result<Option<proposal::Exchange>, ApplyError> {
-        let address = make_exchange_address(buy_proposal_id, sell_proposal_id);
+        let address = make_exchange_address(&self.namespace, buy_proposal_id, sell_proposal_id);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
@@ -662,7 +952,7 @@ impl<'a> StateDGC<'a> {
         sell_proposal_id: &str,
         exchange: proposal::Exchange,
     ) -> Result<(), ApplyError> {
-        let address = make_exchange_address(buy_proposal_id, sell_proposal_id);
+        let address = make_exchange_address(&self.namespace, buy_proposal_id, sell_proposal_id);
         let d = self.context.get_state(vec![address.clone()])?;
         let mut exchange_container = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
@@ -713,28 +1003,26 @@ impl<'a> StateDGC<'a> {
             .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
         Ok(())
     }
-/*
-    pub fn get_exchange(
-        &mut self,
-        timestamp: u64,
-    ) -> Result<Option<proposal::Exchange>, ApplyError> {
-        let address = make_exchange_address(timestamp);
+    /// The public key of the participant using `email`, if any.
+    pub fn get_email_owner(&mut self, email: &str) -> Result<Option<String>, ApplyError> {
+        let address = make_email_index_address(&self.namespace, email);
+        let normalized = normalize_email(email);
         let d = self.context.get_state(vec![address])?;
         match d {
             Some(packed) => {
-                let exchanges: proposal::ExchangeContainer =
+                let index: participant::EmailIndexContainer =
                     match protobuf::parse_from_bytes(packed.as_slice()) {
-                        Ok(exchanges) => exchanges,
+                        Ok(index) => index,
                         Err(_) => {
                             return Err(ApplyError::InternalError(String::from(
-                                "Cannot deserialize exchange container",
+                                "Cannot deserialize email index container",
                             )))
                         }
                     };
 
-                for exchange in exchanges.get_entries() {
-                    if exchange.timestamp == timestamp {
-                        return Ok(Some(exchange.clone()));
+                for entry in index.get_entries() {
+                    if entry.email == normalized {
+                        return Ok(Some(entry.public_key.clone()));
                     }
                 }
                 Ok(None)
@@ -743,51 +1031,51 @@ impl<'a> StateDGC<'a> {
         }
     }
 
-    pub fn set_exchange(
+    /// Records `participant_id` as the user of `email`, or releases the
+    /// email if `participant_id` is None. An index address left without
+    /// entries is deleted.
+    pub fn set_email_owner(
         &mut self,
-        timestamp: u64,
-        exchange: proposal::Exchange,
+        email: &str,
+        participant_id: Option<&str>,
     ) -> Result<(), ApplyError> {
-        let address = make_exchange_address(timestamp);
+        let address = make_email_index_address(&self.namespace, email);
+        let normalized = normalize_email(email);
         let d = self.context.get_state(vec![address.clone()])?;
-        let mut exchange_container = match d {
+        let mut index_container = match d {
             Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
-                Ok(exchanges) => exchanges,
+                Ok(index) => index,
                 Err(_) => {
                     return Err(ApplyError::InternalError(String::from(
-                        "Cannot deserialize exchange container",
+                        "Cannot deserialize email index container",
                     )))
                 }
             },
-            None => proposal::ExchangeContainer::new(),
+            None => participant::EmailIndexContainer::new(),
         };
 
-        // remove old exchange if it exists and sort the exchanges by proposal_id
-        let exchanges = exchange_container.get_entries().to_vec();
-        let mut index = None;
-        let mut count = 0;
-        for exchange in exchanges.clone() {
-            if exchange.timestamp == timestamp {
-                index = Some(count);
-                break;
-            }
-            count = count + 1;
+        // remove the old entry if it exists and sort the entries by email
+        index_container.entries.retain(|entry| entry.email != normalized);
+        if let Some(participant_id) = participant_id {
+            let mut entry = participant::EmailIndex::new();
+            entry.set_email(normalized);
+            entry.set_public_key(participant_id.to_string());
+            index_container.entries.push(entry);
+            index_container.entries.sort_by_key(|r| r.clone().email);
         }
 
-        match index {
-            Some(x) => {
-                exchange_container.entries.remove(x);
-            }
-            None => (),
-        };
-        exchange_container.entries.push(exchange);
-        exchange_container.entries.sort_by_key(|r| r.clone().timestamp);
+        if index_container.get_entries().is_empty() {
+            self.context
+                .delete_state(vec![address])
+                .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+            return Ok(());
+        }
 
-        let serialized = match exchange_container.write_to_bytes() {
+        let serialized = match index_container.write_to_bytes() {
             Ok(serialized) => serialized,
             Err(_) => {
                 return Err(ApplyError::InternalError(String::from(
-                    "Cannot serialize exchange container",
+                    "Cannot serialize email index container",
                 )))
             }
         };
@@ -798,91 +1086,1219 @@ impl<'a> StateDGC<'a> {
             .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
         Ok(())
     }
-*/    
-}
 
-pub struct TransactionHandlerDGC {
-    family_name: String,
-    family_versions: Vec<String>,
-    namespaces: Vec<String>,
-}
+    /// The participant `public_key` signs for after a key rotation, if any.
+    pub fn get_key_alias(&mut self, public_key: &str) -> Result<Option<String>, ApplyError> {
+        let address = make_key_alias_address(&self.namespace, public_key);
+        let d = self.context.get_state(vec![address])?;
+        match d {
+            Some(packed) => {
+                let aliases: participant::KeyAliasContainer =
+                    match protobuf::parse_from_bytes(packed.as_slice()) {
+                        Ok(aliases) => aliases,
+                        Err(_) => {
+                            return Err(ApplyError::InternalError(String::from(
+                                "Cannot deserialize key alias container",
+                            )))
+                        }
+                    };
 
-impl TransactionHandlerDGC {
-    pub fn new() -> TransactionHandlerDGC {
-        TransactionHandlerDGC {
-            family_name: "dgc_REST_api".to_string(),
-            family_versions: vec!["1.1".to_string()],
-            namespaces: vec![get_dgc_rest_api_prefix().to_string()],
+                for alias in aliases.get_entries() {
+                    if alias.public_key == public_key {
+                        return Ok(Some(alias.participant_id.clone()));
+                    }
+                }
+                Ok(None)
+            }
+            None => Ok(None),
         }
     }
 
-    fn _create_participant(
-        &self,
-        payload: payload::CreateParticipantAction,
-        mut state: StateDGC,
-        signer: &str,
-        timestamp: u64,
-    ) -> Result<(), ApplyError> {
-        let name = payload.get_name();
-        match state.get_participant(signer) {
-            Ok(Some(_)) => {
-                return Err(ApplyError::InvalidTransaction(format!(
-                    "Participant already exists: {}",
-                    name
+    pub fn set_key_alias(&mut self, public_key: &str, participant_id: &str) -> Result<(), ApplyError> {
+        let address = make_key_alias_address(&self.namespace, public_key);
+        let d = self.context.get_state(vec![address.clone()])?;
+        let mut alias_container = match d {
+            Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
+                Ok(aliases) => aliases,
+                Err(_) => {
+                    return Err(ApplyError::InternalError(String::from(
+                        "Cannot deserialize key alias container",
+                    )))
+                }
+            },
+            None => participant::KeyAliasContainer::new(),
+        };
+
+        // remove the old alias if it exists and sort the aliases by public_key
+        alias_container.entries.retain(|alias| alias.public_key != public_key);
+        let mut alias = participant::KeyAlias::new();
+        alias.set_public_key(public_key.to_string());
+        alias.set_participant_id(participant_id.to_string());
+        alias_container.entries.push(alias);
+        alias_container.entries.sort_by_key(|r| r.clone().public_key);
+
+        let serialized = match alias_container.write_to_bytes() {
+            Ok(serialized) => serialized,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot serialize key alias container",
                 )))
             }
-            Ok(None) => (),
-            Err(err) => return Err(err),
-        }
-
-        let mut new_participant = participant::Participant::new();
-        new_participant.set_public_key(signer.to_string());
-        new_participant.set_name(name.to_string());
-        new_participant.set_timestamp(timestamp);
-        state.set_participant(signer, new_participant)?;
+        };
+        let mut sets = HashMap::new();
+        sets.insert(address, serialized);
+        self.context
+            .set_state(sets)
+            .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
         Ok(())
     }
 
-    fn _create_record(
-        &self,
-        payload: payload::CreateRecordAction,
-        mut state: StateDGC,
-        signer: &str,
-        timestamp: u64,
-    ) -> Result<(), ApplyError> {
-        match state.get_participant(signer) {
-            Ok(Some(_)) => (),
-            Ok(None) => {
+    /// The participant a transaction signed by `signing_key` acts for: the
+    /// participant the key was rotated into, or else the key itself. A key
+    /// which was rotated out of its participant can no longer sign.
+    pub fn resolve_signer(&mut self, signing_key: &str) -> Result<String, ApplyError> {
+        let participant_id = match self.get_key_alias(signing_key)? {
+            Some(participant_id) => participant_id,
+            None => signing_key.to_string(),
+        };
+        if let Some(participant) = self.get_participant(&participant_id)? {
+            if current_signing_key(&participant) != signing_key {
                 return Err(ApplyError::InvalidTransaction(format!(
-                    "Participant is not register: {}",
-                    signer
-                )))
+                    "Key {} was rotated and can no longer sign for participant {}",
+                    signing_key, participant_id
+                )));
             }
-            Err(err) => return Err(err),
         }
-        let record_id = payload.get_record_id();
-        match state.get_record(record_id) {
-            Ok(Some(_)) => {
-                return Err(ApplyError::InvalidTransaction(format!(
-                    "Record already exists: {}",
-                    record_id
-                )))
+        Ok(participant_id)
+    }
+
+    pub fn get_role_assignment(
+        &mut self,
+        public_key: &str,
+    ) -> Result<Option<role::RoleAssignment>, ApplyError> {
+        let address = make_role_address(&self.namespace, public_key);
+        let d = self.context.get_state(vec![address])?;
+        match d {
+            Some(packed) => {
+                let assignments: role::RoleAssignmentContainer =
+                    match protobuf::parse_from_bytes(packed.as_slice()) {
+                        Ok(assignments) => assignments,
+                        Err(_) => {
+                            return Err(ApplyError::InternalError(String::from(
+                                "Cannot deserialize role assignment container",
+                            )))
+                        }
+                    };
+
+                for assignment in assignments.get_entries() {
+                    if assignment.public_key == public_key {
+                        return Ok(Some(assignment.clone()));
+                    }
+                }
+                Ok(None)
             }
-            Ok(None) => (),
-            Err(err) => return Err(err),
+            None => Ok(None),
         }
+    }
 
-        let name = payload.get_table();
-        let table = match state.get_table(name) {
-            Ok(Some(table)) => table,
-            Ok(None) => {
-                return Err(ApplyError::InvalidTransaction(format!(
-                    "Table does not exist {}",
-                    name
-                )))
-            }
-            Err(err) => return Err(err),
-        };
+    /// Stores the roles of `public_key`, or removes them if `assignment` is
+    /// None. An address left without entries is deleted.
+    pub fn set_role_assignment(
+        &mut self,
+        public_key: &str,
+        assignment: Option<role::RoleAssignment>,
+    ) -> Result<(), ApplyError> {
+        let address = make_role_address(&self.namespace, public_key);
+        let d = self.context.get_state(vec![address.clone()])?;
+        let mut assignment_container = match d {
+            Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
+                Ok(assignments) => assignments,
+                Err(_) => {
+                    return Err(ApplyError::InternalError(String::from(
+                        "Cannot deserialize role assignment container",
+                    )))
+                }
+            },
+            None => role::RoleAssignmentContainer::new(),
+        };
+
+        // remove the old assignment if it exists and sort the assignments by public_key
+        assignment_container.entries.retain(|entry| entry.public_key != public_key);
+        if let Some(assignment) = assignment {
+            assignment_container.entries.push(assignment);
+            assignment_container.entries.sort_by_key(|r| r.clone().public_key);
+        }
+
+        if assignment_container.get_entries().is_empty() {
+            self.context
+                .delete_state(vec![address])
+                .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+            return Ok(());
+        }
+
+        let serialized = match assignment_container.write_to_bytes() {
+            Ok(serialized) => serialized,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot serialize role assignment container",
+                )))
+            }
+        };
+        let mut sets = HashMap::new();
+        sets.insert(address, serialized);
+        self.context
+            .set_state(sets)
+            .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+        Ok(())
+    }
+
+    pub fn get_organization(
+        &mut self,
+        organization_id: &str,
+    ) -> Result<Option<organization::Organization>, ApplyError> {
+        let address = make_organization_address(&self.namespace, organization_id);
+        let d = self.context.get_state(vec![address])?;
+        match d {
+            Some(packed) => {
+                let organizations: organization::OrganizationContainer =
+                    match protobuf::parse_from_bytes(packed.as_slice()) {
+                        Ok(organizations) => organizations,
+                        Err(_) => {
+                            return Err(ApplyError::InternalError(String::from(
+                                "Cannot deserialize organization container",
+                            )))
+                        }
+                    };
+
+                for organization in organizations.get_entries() {
+                    if organization.organization_id == organization_id {
+                        return Ok(Some(organization.clone()));
+                    }
+                }
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_organization(
+        &mut self,
+        organization_id: &str,
+        organization: organization::Organization,
+    ) -> Result<(), ApplyError> {
+        let address = make_organization_address(&self.namespace, organization_id);
+        let d = self.context.get_state(vec![address.clone()])?;
+        let mut organization_container = match d {
+            Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
+                Ok(organizations) => organizations,
+                Err(_) => {
+                    return Err(ApplyError::InternalError(String::from(
+                        "Cannot deserialize organization container",
+                    )))
+                }
+            },
+            None => organization::OrganizationContainer::new(),
+        };
+
+        // remove the old organization if it exists and sort the organizations by id
+        organization_container
+            .entries
+            .retain(|entry| entry.organization_id != organization_id);
+        organization_container.entries.push(organization);
+        organization_container
+            .entries
+            .sort_by_key(|o| o.clone().organization_id);
+
+        let serialized = match organization_container.write_to_bytes() {
+            Ok(serialized) => serialized,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot serialize organization container",
+                )))
+            }
+        };
+        let mut sets = HashMap::new();
+        sets.insert(address, serialized);
+        self.context
+            .set_state(sets)
+            .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+        Ok(())
+    }
+
+    pub fn get_multisig_policy(
+        &mut self,
+        policy_id: &str,
+    ) -> Result<Option<multisig::MultisigPolicy>, ApplyError> {
+        let address = make_multisig_policy_address(&self.namespace, policy_id);
+        let d = self.context.get_state(vec![address])?;
+        match d {
+            Some(packed) => {
+                let policies: multisig::MultisigPolicyContainer =
+                    match protobuf::parse_from_bytes(packed.as_slice()) {
+                        Ok(policies) => policies,
+                        Err(_) => {
+                            return Err(ApplyError::InternalError(String::from(
+                                "Cannot deserialize multisig policy container",
+                            )))
+                        }
+                    };
+
+                for policy in policies.get_entries() {
+                    if policy.policy_id == policy_id {
+                        return Ok(Some(policy.clone()));
+                    }
+                }
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_multisig_policy(
+        &mut self,
+        policy_id: &str,
+        policy: multisig::MultisigPolicy,
+    ) -> Result<(), ApplyError> {
+        let address = make_multisig_policy_address(&self.namespace, policy_id);
+        let d = self.context.get_state(vec![address.clone()])?;
+        let mut policy_container = match d {
+            Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
+                Ok(policies) => policies,
+                Err(_) => {
+                    return Err(ApplyError::InternalError(String::from(
+                        "Cannot deserialize multisig policy container",
+                    )))
+                }
+            },
+            None => multisig::MultisigPolicyContainer::new(),
+        };
+
+        // remove the old policy if it exists and sort the policies by id
+        policy_container.entries.retain(|entry| entry.policy_id != policy_id);
+        policy_container.entries.push(policy);
+        policy_container.entries.sort_by_key(|p| p.clone().policy_id);
+
+        let serialized = match policy_container.write_to_bytes() {
+            Ok(serialized) => serialized,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot serialize multisig policy container",
+                )))
+            }
+        };
+        let mut sets = HashMap::new();
+        sets.insert(address, serialized);
+        self.context
+            .set_state(sets)
+            .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+        Ok(())
+    }
+
+    pub fn get_pending_approval(
+        &mut self,
+        approval_id: &str,
+    ) -> Result<Option<multisig::PendingApproval>, ApplyError> {
+        let address = make_pending_approval_address(&self.namespace, approval_id);
+        let d = self.context.get_state(vec![address])?;
+        match d {
+            Some(packed) => {
+                let approvals: multisig::PendingApprovalContainer =
+                    match protobuf::parse_from_bytes(packed.as_slice()) {
+                        Ok(approvals) => approvals,
+                        Err(_) => {
+                            return Err(ApplyError::InternalError(String::from(
+                                "Cannot deserialize pending approval container",
+                            )))
+                        }
+                    };
+
+                for approval in approvals.get_entries() {
+                    if approval.approval_id == approval_id {
+                        return Ok(Some(approval.clone()));
+                    }
+                }
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Stores the approvals collected for `approval_id`, or removes them if
+    /// `approval` is None. An address left without entries is deleted.
+    pub fn set_pending_approval(
+        &mut self,
+        approval_id: &str,
+        approval: Option<multisig::PendingApproval>,
+    ) -> Result<(), ApplyError> {
+        let address = make_pending_approval_address(&self.namespace, approval_id);
+        let d = self.context.get_state(vec![address.clone()])?;
+        let mut approval_container = match d {
+            Some(packed) => match protobuf::parse_from_bytes(packed.as_slice()) {
+                Ok(approvals) => approvals,
+                Err(_) => {
+                    return Err(ApplyError::InternalError(String::from(
+                        "Cannot deserialize pending approval container",
+                    )))
+                }
+            },
+            None => multisig::PendingApprovalContainer::new(),
+        };
+
+        // remove the old approval if it exists and sort the approvals by id
+        approval_container.entries.retain(|entry| entry.approval_id != approval_id);
+        if let Some(approval) = approval {
+            approval_container.entries.push(approval);
+            approval_container.entries.sort_by_key(|a| a.clone().approval_id);
+        }
+
+        if approval_container.get_entries().is_empty() {
+            self.context
+                .delete_state(vec![address])
+                .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+            return Ok(());
+        }
+
+        let serialized = match approval_container.write_to_bytes() {
+            Ok(serialized) => serialized,
+            Err(_) => {
+                return Err(ApplyError::InternalError(String::from(
+                    "Cannot serialize pending approval container",
+                )))
+            }
+        };
+        let mut sets = HashMap::new();
+        sets.insert(address, serialized);
+        self.context
+            .set_state(sets)
+            .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+        Ok(())
+    }
+
+    /// Whether `id` is an organization or a multisig policy, which stand for
+    /// participants as owners and custodians of records.
+    pub fn is_shared_party(&mut self, id: &str) -> Result<bool, ApplyError> {
+        Ok(self.get_organization(id)?.is_some() || self.get_multisig_policy(id)?.is_some())
+    }
+
+    /// The participants who act for `party`: the members of an organization,
+    /// the signers of a multisig policy, or the participant itself.
+    pub fn party_members(&mut self, party: &str) -> Result<Vec<String>, ApplyError> {
+        if let Some(organization) = self.get_organization(party)? {
+            return Ok(organization
+                .get_members()
+                .iter()
+                .map(|member| member.get_participant_id().to_string())
+                .collect());
+        }
+        if let Some(policy) = self.get_multisig_policy(party)? {
+            return Ok(policy.get_signers().to_vec());
+        }
+        Ok(vec![party.to_string()])
+    }
+
+    /// Whether `signer` can act for `party`, the public key of a participant
+    /// or the id of an organization or multisig policy in a record or
+    /// proposal: it is the participant, a member of the organization with
+    /// `permission`, or one of the policy's signers. Actions which need a
+    /// policy's threshold go through `consent` instead.
+    pub fn acts_for(
+        &mut self,
+        party: &str,
+        signer: &str,
+        permission: organization::Organization_Member_Permission,
+    ) -> Result<bool, ApplyError> {
+        if party == signer {
+            return Ok(true);
+        }
+        if let Some(organization) = self.get_organization(party)? {
+            return Ok(organization.get_members().iter().any(|member| {
+                member.get_participant_id() == signer
+                    && member.get_permissions().contains(&permission)
+            }));
+        }
+        Ok(match self.get_multisig_policy(party)? {
+            Some(policy) => policy.get_signers().iter().any(|key| key == signer),
+            None => false,
+        })
+    }
+
+    /// Collects the consent of `signer` to `action`, which needs every one of
+    /// `parties`. Without a multisig policy among them the signer alone must
+    /// act for all of them. Otherwise each signer's approval is kept in a
+    /// pending approval until enough have been sent for every party. Only the
+    /// approvals of participants which are still active count, and they
+    /// expire after the `dgc.multisig.approval_lifetime` setting.
+    pub fn consent(
+        &mut self,
+        parties: &[&str],
+        signer: &str,
+        permission: organization::Organization_Member_Permission,
+        action: &PayloadAction,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<Consent, ApplyError> {
+        let mut policies = HashMap::new();
+        for party in parties {
+            if let Some(policy) = self.get_multisig_policy(party)? {
+                policies.insert(party.to_string(), policy);
+            }
+        }
+
+        let mut contributes = false;
+        for party in parties {
+            contributes |= self.acts_for(party, signer, permission)?;
+        }
+        if !contributes {
+            return Ok(Consent::Refused);
+        }
+
+        let now = current_time(timestamp, block_timestamp);
+        let approval_id = approval_id(action, parties)?;
+        let pending = if policies.is_empty() {
+            None
+        } else {
+            self.get_pending_approval(&approval_id)?
+        };
+        let mut approval = match pending {
+            Some(ref approval) if !approval_expired(approval, now) => approval.clone(),
+            _ => {
+                let mut approval = multisig::PendingApproval::new();
+                approval.set_approval_id(approval_id.clone());
+                approval.set_action(action.name().to_string());
+                approval.set_record_id(action.record_id().unwrap_or_default());
+                approval.set_timestamp(timestamp);
+                approval.set_expires_at(now + approval_lifetime(self.context)?);
+                approval
+            }
+        };
+        // Participants suspended or deactivated since they approved no
+        // longer count towards the threshold
+        let mut approvers = Vec::new();
+        for approver in approval.get_approvers() {
+            if let Some(participant) = self.get_participant(approver)? {
+                if participant.get_status() == participant::Participant_Status::ACTIVE {
+                    approvers.push(approver.clone());
+                }
+            }
+        }
+        approval.set_approvers(RepeatedField::from_vec(approvers));
+        if approval.get_approvers().iter().any(|approver| approver == signer) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant {} has already approved this {}",
+                signer,
+                action.name()
+            )));
+        }
+        approval.approvers.push(signer.to_string());
+        approval.approvers.sort();
+
+        let mut satisfied = true;
+        for party in parties {
+            satisfied &= match policies.get(*party) {
+                Some(policy) => {
+                    let approvals = policy
+                        .get_signers()
+                        .iter()
+                        .filter(|key| approval.get_approvers().contains(key))
+                        .count();
+                    approvals >= policy.get_threshold() as usize
+                }
+                None => {
+                    let mut approved = false;
+                    for approver in approval.get_approvers() {
+                        approved |= self.acts_for(party, approver, permission)?;
+                    }
+                    approved
+                }
+            };
+        }
+
+        if satisfied {
+            if !policies.is_empty() {
+                self.set_pending_approval(&approval_id, None)?;
+            }
+            Ok(Consent::Given)
+        } else if !policies.is_empty() {
+            self.set_pending_approval(&approval_id, Some(approval))?;
+            Ok(Consent::Pending)
+        } else {
+            Ok(Consent::Refused)
+        }
+    }
+
+    /// Whether `signer` holds a role granting `permission`. The keys in the
+    /// `dgc.admins` setting hold ADMIN.
+    pub fn holds(&mut self, signer: &str, permission: Permission) -> Result<bool, ApplyError> {
+        if is_admin(self.context, signer)? {
+            return Ok(true);
+        }
+        Ok(match self.get_role_assignment(signer)? {
+            Some(assignment) => grants(assignment.get_roles(), permission),
+            None => false,
+        })
+    }
+
+    /// Refuses `signer` unless it holds `permission`. Permissions which are
+    /// open by default are held by everyone until `dgc.roles.enforce` is set.
+    pub fn authorize(&mut self, signer: &str, permission: Permission) -> Result<(), ApplyError> {
+        if self.holds(signer, permission)? {
+            return Ok(());
+        }
+        if permission.open_by_default()
+            && get_setting(self.context, ENFORCE_KEY)? != Some(String::from("true"))
+        {
+            return Ok(());
+        }
+        Err(ApplyError::InvalidTransaction(format!(
+            "Participant {} does not have the {} permission",
+            signer,
+            permission.name()
+        )))
+    }
+
+    /// Rewrites the container at `address` in the current layout. Returns
+    /// whether anything was written.
+    pub fn migrate_entry(&mut self, address: &str) -> Result<bool, ApplyError> {
+        check_migratable(&self.namespace, address)?;
+        let data = match self.context.get_state(vec![address.to_string()])? {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+        match migrate_container(&self.namespace, address, &data)? {
+            Some(serialized) => {
+                let mut sets = HashMap::new();
+                sets.insert(address.to_string(), serialized);
+                self.context
+                    .set_state(sets)
+                    .map_err(|err| ApplyError::InternalError(format!("{}", err)))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn add_receipt_data(&mut self, data: &[u8]) -> Result<(), ApplyError> {
+        self.context
+            .add_receipt_data(data)
+            .map_err(|err| ApplyError::InternalError(format!("{}", err)))
+    }
+}
+
+pub struct TransactionHandlerDGC {
+    family: Family,
+    family_versions: Vec<String>,
+    limits: Limits,
+    metrics: Option<Arc<Metrics>>,
+    lifecycle: Option<Arc<Lifecycle>>,
+}
+
+impl TransactionHandlerDGC {
+    pub fn new() -> TransactionHandlerDGC {
+        TransactionHandlerDGC {
+            family: Family::default(),
+            family_versions: FAMILY_VERSIONS.iter().map(|v| v.to_string()).collect(),
+            limits: Limits::default(),
+            metrics: None,
+            lifecycle: None,
+        }
+    }
+
+    /// A handler which uses `limits` where the limit settings are unset.
+    pub fn with_limits(limits: Limits) -> TransactionHandlerDGC {
+        TransactionHandlerDGC {
+            limits: limits,
+            ..TransactionHandlerDGC::new()
+        }
+    }
+
+    /// Registers the handler for `family`, reading and writing state under
+    /// its namespace.
+    pub fn set_family(&mut self, family: Family) {
+        self.family = family;
+    }
+
+    /// Records every transaction applied, and the state calls it makes, in
+    /// `metrics`.
+    pub fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Counts transactions in flight in `lifecycle`, and refuses new ones
+    /// once it is shutting down.
+    pub fn set_lifecycle(&mut self, lifecycle: Arc<Lifecycle>) {
+        self.lifecycle = Some(lifecycle);
+    }
+
+    fn _create_participant(
+        &self,
+        payload: payload::CreateParticipantAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let name = payload.get_name();
+        match state.get_participant(signer) {
+            Ok(Some(_)) => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant already exists: {}",
+                    name
+                )))
+            }
+            Ok(None) => (),
+            Err(err) => return Err(err),
+        }
+        if state.is_shared_party(signer)? {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "An organization or multisig policy already uses the id {}",
+                signer
+            )));
+        }
+
+        let email = payload.get_email().trim();
+        if email != "" {
+            claim_email(&mut state, email, signer)?;
+        }
+
+        let mut new_participant = participant::Participant::new();
+        new_participant.set_public_key(signer.to_string());
+        new_participant.set_name(name.to_string());
+        new_participant.set_email(email.to_string());
+        new_participant.set_timestamp(timestamp);
+        state.set_participant(signer, new_participant)?;
+        Ok(())
+    }
+
+    fn _update_participant(
+        &self,
+        payload: payload_v2::UpdateParticipantAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let mut participant = match state.get_participant(signer)? {
+            Some(participant) => participant,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant does not exist: {}",
+                    signer
+                )))
+            }
+        };
+
+        let name = payload.get_name();
+        let email = payload.get_email().trim();
+        if payload.get_remove_recovery_key() && payload.get_recovery_key() != "" {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Recovery key cannot be both replaced and removed",
+            )));
+        }
+        let recovery_key = if payload.get_remove_recovery_key() {
+            String::new()
+        } else if payload.get_recovery_key() != "" {
+            payload.get_recovery_key().to_string()
+        } else {
+            participant.get_recovery_key().to_string()
+        };
+        let profile_changed = name != participant.get_name() || email != participant.get_email();
+        if !profile_changed && recovery_key == participant.get_recovery_key() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant profile is unchanged: {}",
+                signer
+            )));
+        }
+        if recovery_key != "" && recovery_key == current_signing_key(&participant) {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Recovery key must differ from the signing key",
+            )));
+        }
+
+        let old_email = participant.get_email().to_string();
+        if normalize_email(email) != normalize_email(&old_email) {
+            if email != "" {
+                claim_email(&mut state, email, signer)?;
+            }
+            if old_email != "" {
+                state.set_email_owner(&old_email, None)?;
+            }
+        }
+
+        if profile_changed {
+            let mut change = participant::Participant_ProfileChange::new();
+            change.set_name(participant.get_name().to_string());
+            change.set_email(old_email);
+            change.set_timestamp(timestamp);
+            change.set_block_timestamp(block_timestamp);
+            participant.profile_history.push(change);
+        }
+
+        participant.set_name(name.to_string());
+        participant.set_email(email.to_string());
+        participant.set_recovery_key(recovery_key);
+        state.set_participant(signer, participant)?;
+        Ok(())
+    }
+
+    fn _set_participant_status(
+        &self,
+        payload: payload_v2::SetParticipantStatusAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let participant_id = payload.get_participant_id();
+        let mut participant = match state.get_participant(participant_id)? {
+            Some(participant) => participant,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant does not exist: {}",
+                    participant_id
+                )))
+            }
+        };
+
+        let status = payload.get_status();
+        let self_deactivation =
+            signer == participant_id && status == participant::Participant_Status::DEACTIVATED;
+        if !self_deactivation {
+            if !state.holds(signer, Permission::ManageParticipants)? {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Only an administrator can set the status of {} to {:?}",
+                    participant_id, status
+                )));
+            }
+            // An administrator changing statuses must itself be active
+            if let Some(admin) = state.get_participant(signer)? {
+                check_active(&admin, "Participant")?;
+            }
+        }
+        if status == participant.get_status() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant {} is already {:?}",
+                participant_id, status
+            )));
+        }
+
+        let mut change = participant::Participant_StatusChange::new();
+        change.set_status(status);
+        change.set_changed_by(signer.to_string());
+        change.set_reason(payload.get_reason().to_string());
+        change.set_timestamp(timestamp);
+        change.set_block_timestamp(block_timestamp);
+        participant.status_history.push(change);
+        participant.set_status(status);
+
+        state.set_participant(participant_id, participant)?;
+        Ok(())
+    }
+
+    fn _set_roles(
+        &self,
+        payload: payload_v2::SetRolesAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        state.authorize(signer, Permission::ManageRoles)?;
+
+        let public_key = payload.get_public_key();
+        let mut roles = payload.get_roles().to_vec();
+        roles.sort_by_key(|role| *role as i32);
+        roles.dedup();
+
+        let current = state.get_role_assignment(public_key)?;
+        let current_roles = current.as_ref().map(|a| a.get_roles().to_vec()).unwrap_or_default();
+        if roles == current_roles {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant {} already has these roles",
+                public_key
+            )));
+        }
+
+        if roles.is_empty() {
+            return state.set_role_assignment(public_key, None);
+        }
+
+        let mut assignment = role::RoleAssignment::new();
+        assignment.set_public_key(public_key.to_string());
+        assignment.set_roles(roles);
+        assignment.set_assigned_by(signer.to_string());
+        assignment.set_timestamp(timestamp);
+        state.set_role_assignment(public_key, Some(assignment))
+    }
+
+    fn _create_organization(
+        &self,
+        payload: payload_v2::CreateOrganizationAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let organization_id = payload.get_organization_id();
+        if organization_id.trim().is_empty() {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Organization id cannot be empty",
+            )));
+        }
+        if state.get_organization(organization_id)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Organization already exists: {}",
+                organization_id
+            )));
+        }
+        if state.get_multisig_policy(organization_id)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "A multisig policy already uses the id {}",
+                organization_id
+            )));
+        }
+        // Records and proposals refer to organizations, multisig policies and
+        // participants alike, so their ids cannot overlap
+        if state.get_participant(organization_id)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "A participant already uses the id {}",
+                organization_id
+            )));
+        }
+        if state.get_participant(signer)?.is_none() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant does not exist: {}",
+                signer
+            )));
+        }
+
+        let mut founder = organization::Organization_Member::new();
+        founder.set_participant_id(signer.to_string());
+        founder.set_permissions(vec![
+            organization::Organization_Member_Permission::PROPOSE,
+            organization::Organization_Member_Permission::ANSWER,
+            organization::Organization_Member_Permission::FINALIZE,
+            organization::Organization_Member_Permission::REVOKE_REPORTER,
+            organization::Organization_Member_Permission::MANAGE_MEMBERS,
+        ]);
+
+        let mut new_organization = organization::Organization::new();
+        new_organization.set_organization_id(organization_id.to_string());
+        new_organization.set_name(payload.get_name().to_string());
+        new_organization.members.push(founder);
+        new_organization.set_created_by(signer.to_string());
+        new_organization.set_timestamp(timestamp);
+        state.set_organization(organization_id, new_organization)
+    }
+
+    fn _set_organization_member(
+        &self,
+        payload: payload_v2::SetOrganizationMemberAction,
+        mut state: StateDGC,
+        signer: &str,
+    ) -> Result<(), ApplyError> {
+        let organization_id = payload.get_organization_id();
+        let participant_id = payload.get_participant_id();
+        let mut organization = match state.get_organization(organization_id)? {
+            Some(organization) => organization,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Organization does not exist: {}",
+                    organization_id
+                )))
+            }
+        };
+        let manage = organization::Organization_Member_Permission::MANAGE_MEMBERS;
+        if !state.acts_for(organization_id, signer, manage)? {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Only a member allowed to manage members can change the members of {}",
+                organization_id
+            )));
+        }
+
+        let mut permissions = payload.get_permissions().to_vec();
+        permissions.sort_by_key(|permission| *permission as i32);
+        permissions.dedup();
+
+        let current = organization
+            .get_members()
+            .iter()
+            .find(|member| member.get_participant_id() == participant_id)
+            .map(|member| member.get_permissions().to_vec());
+        if current.is_none() && permissions.is_empty() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant {} is not a member of {}",
+                participant_id, organization_id
+            )));
+        }
+        if current.as_ref() == Some(&permissions) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Member {} of {} already has these permissions",
+                participant_id, organization_id
+            )));
+        }
+        if !permissions.is_empty() && state.get_participant(participant_id)?.is_none() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant does not exist: {}",
+                participant_id
+            )));
+        }
+
+        organization
+            .members
+            .retain(|member| member.get_participant_id() != participant_id);
+        if !permissions.is_empty() {
+            let mut member = organization::Organization_Member::new();
+            member.set_participant_id(participant_id.to_string());
+            member.set_permissions(permissions);
+            organization.members.push(member);
+            organization
+                .members
+                .sort_by_key(|m| m.get_participant_id().to_string());
+        }
+
+        // An organization nobody can manage could never change again
+        if !organization
+            .get_members()
+            .iter()
+            .any(|member| member.get_permissions().contains(&manage))
+        {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Organization {} must keep a member who can manage members",
+                organization_id
+            )));
+        }
+
+        state.set_organization(organization_id, organization)
+    }
+
+    fn _create_multisig_policy(
+        &self,
+        payload: payload_v2::CreateMultisigPolicyAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let policy_id = payload.get_policy_id();
+        if policy_id.trim().is_empty() {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Policy id cannot be empty",
+            )));
+        }
+        if state.get_multisig_policy(policy_id)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Multisig policy already exists: {}",
+                policy_id
+            )));
+        }
+        if state.get_organization(policy_id)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "An organization already uses the id {}",
+                policy_id
+            )));
+        }
+        if state.get_participant(policy_id)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "A participant already uses the id {}",
+                policy_id
+            )));
+        }
+        if state.get_participant(signer)?.is_none() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant does not exist: {}",
+                signer
+            )));
+        }
+
+        let mut signers = payload.get_signers().to_vec();
+        signers.sort();
+        signers.dedup();
+        for key in &signers {
+            if state.get_participant(key)?.is_none() {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant does not exist: {}",
+                    key
+                )));
+            }
+        }
+        let threshold = payload.get_threshold();
+        if threshold == 0 || threshold as usize > signers.len() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Threshold must be between 1 and the {} signers: {}",
+                signers.len(),
+                threshold
+            )));
+        }
+
+        let mut policy = multisig::MultisigPolicy::new();
+        policy.set_policy_id(policy_id.to_string());
+        policy.set_signers(RepeatedField::from_vec(signers));
+        policy.set_threshold(threshold);
+        policy.set_created_by(signer.to_string());
+        policy.set_timestamp(timestamp);
+        state.set_multisig_policy(policy_id, policy)
+    }
+
+    fn _withdraw_approval(
+        &self,
+        payload: payload_v2::WithdrawApprovalAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let approval_id = payload.get_approval_id();
+        let mut approval = match state.get_pending_approval(approval_id)? {
+            Some(approval) => approval,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Pending approval does not exist: {}",
+                    approval_id
+                )))
+            }
+        };
+
+        // Anyone can clear away approvals which no longer count
+        if approval_expired(&approval, current_time(timestamp, block_timestamp)) {
+            return state.set_pending_approval(approval_id, None);
+        }
+        if !approval.get_approvers().iter().any(|approver| approver == signer) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant {} has not approved {}",
+                signer, approval_id
+            )));
+        }
+        let approvers: Vec<String> = approval
+            .get_approvers()
+            .iter()
+            .filter(|approver| *approver != signer)
+            .cloned()
+            .collect();
+        if approvers.is_empty() {
+            return state.set_pending_approval(approval_id, None);
+        }
+        approval.set_approvers(RepeatedField::from_vec(approvers));
+        state.set_pending_approval(approval_id, Some(approval))
+    }
+
+    fn _rotate_key(
+        &self,
+        payload: payload_v2::RotateKeyAction,
+        mut state: StateDGC,
+        signer: &str,
+        signing_key: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let participant_id = payload.get_participant_id();
+        let mut participant = match state.get_participant(participant_id)? {
+            Some(participant) => participant,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant does not exist: {}",
+                    participant_id
+                )))
+            }
+        };
+
+        let by_recovery_key =
+            participant.get_recovery_key() != "" && signing_key == participant.get_recovery_key();
+        if signer != participant_id
+            && !by_recovery_key
+            && !state.holds(signer, Permission::ManageParticipants)?
+        {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Only the participant, its recovery key or an administrator can rotate the key of {}",
+                participant_id
+            )));
+        }
+        // A recovery key acts for its participant, so it is held to the
+        // same status check as the participant's own key
+        if by_recovery_key {
+            check_active(&participant, "Participant")?;
+        }
+
+        let new_key = payload.get_new_public_key();
+        if new_key == "" {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "New public key cannot be empty",
+            )));
+        }
+        if new_key == participant.get_recovery_key() {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "New public key must differ from the recovery key",
+            )));
+        }
+        // A key which ever signed for a participant stays tied to it
+        if state.get_participant(new_key)?.is_some() || state.get_key_alias(new_key)?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Key is already used by a participant: {}",
+                new_key
+            )));
+        }
+        if !verify_signature(new_key, payload.get_new_key_signature(), participant_id.as_bytes()) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "New key signature does not verify for participant {}",
+                participant_id
+            )));
+        }
+
+        let mut rotation = participant::Participant_KeyRotation::new();
+        rotation.set_previous_key(current_signing_key(&participant).to_string());
+        rotation.set_new_key(new_key.to_string());
+        rotation.set_rotated_by(signing_key.to_string());
+        rotation.set_timestamp(timestamp);
+        rotation.set_block_timestamp(block_timestamp);
+        participant.key_rotations.push(rotation);
+        participant.set_signing_key(new_key.to_string());
+
+        state.set_key_alias(new_key, participant_id)?;
+        state.set_participant(participant_id, participant)?;
+        Ok(())
+    }
+
+    fn _create_record(
+        &self,
+        payload: payload::CreateRecordAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+        limits: &Limits,
+    ) -> Result<(), ApplyError> {
+        match state.get_participant(signer) {
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant is not register: {}",
+                    signer
+                )))
+            }
+            Err(err) => return Err(err),
+        }
+        let record_id = payload.get_record_id();
+        match state.get_record(record_id) {
+            Ok(Some(_)) => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Record already exists: {}",
+                    record_id
+                )))
+            }
+            Ok(None) => (),
+            Err(err) => return Err(err),
+        }
+
+        let name = payload.get_table();
+        let table = match state.get_table(name) {
+            Ok(Some(table)) => table,
+            Ok(None) => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Table does not exist {}",
+                    name
+                )))
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Tables created before the limit was lowered can still be too wide,
+        // and every property of the table is written for each new record
+        limits.check_property_count(table.get_properties().len())?;
 
         let mut table_schema: HashMap<&str, property::PropertySchema> = HashMap::new();
         let mut required_properties: HashMap<&str, property::PropertySchema> = HashMap::new();
@@ -944,6 +2360,7 @@ impl TransactionHandlerDGC {
         let mut owner = record::Record_AssociatedParticipant::new();
         owner.set_participant_id(signer.to_string());
         owner.set_timestamp(timestamp);
+        owner.set_block_timestamp(block_timestamp);
         new_record.owners.push(owner.clone());
         new_record.custodians.push(owner.clone());
 
@@ -981,6 +2398,7 @@ impl TransactionHandlerDGC {
                 let reported_value = match self._make_new_reported_value(
                     0,
                     timestamp,
+                    block_timestamp,
                     provided_property,
                     &new_property,
                 ) {
@@ -1001,6 +2419,8 @@ impl TransactionHandlerDGC {
         payload: payload::FinalizeRecordAction,
         mut state: StateDGC,
         signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
     ) -> Result<(), ApplyError> {
         let record_id = payload.get_record_id();
         let final_record = match state.get_record(record_id) {
@@ -1030,17 +2450,32 @@ impl TransactionHandlerDGC {
             }
         };
 
-        if owner.participant_id != signer || custodian.participant_id != signer {
-            return Err(ApplyError::InvalidTransaction(format!(
-                "Must be owner and custodian to finalize record"
-            )));
-        }
         if final_record.get_field_final() {
             return Err(ApplyError::InvalidTransaction(format!(
                 "Record is already final: {}",
                 record_id
             )));
         }
+        let mut parties = vec![owner.get_participant_id()];
+        if custodian.get_participant_id() != owner.get_participant_id() {
+            parties.push(custodian.get_participant_id());
+        }
+        match state.consent(
+            &parties,
+            signer,
+            organization::Organization_Member_Permission::FINALIZE,
+            &PayloadAction::FinalizeRecord(payload.clone()),
+            timestamp,
+            block_timestamp,
+        )? {
+            Consent::Given => (),
+            Consent::Pending => return Ok(()),
+            Consent::Refused => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Must be owner and custodian to finalize record"
+                )))
+            }
+        }
 
         let mut record_clone = final_record.clone();
         record_clone.set_field_final(true);
@@ -1055,18 +2490,8 @@ impl TransactionHandlerDGC {
         mut state: StateDGC,
         signer: &str,
     ) -> Result<(), ApplyError> {
-/*        
-        match state.get_participant(signer) {
-            Ok(Some(_)) => (),
-            Ok(None) => {
-                return Err(ApplyError::InvalidTransaction(format!(
-                    "Participant is not register: {}",
-                    signer
-                )))
-            }
-            Err(err) => return Err(err),
-        }
-*/        
+        state.authorize(signer, Permission::CreateTable)?;
+
         let name = payload.get_name();
         let mut provided_properties: HashMap<&str, property::PropertySchema> = HashMap::new();
         for property in payload.get_properties() {
@@ -1097,6 +2522,7 @@ impl TransactionHandlerDGC {
         mut state: StateDGC,
         signer: &str,
         timestamp: u64,
+        block_timestamp: u64,
     ) -> Result<(), ApplyError> {
         let record_id = payload.get_record_id();
         let update_record = match state.get_record(record_id) {
@@ -1178,6 +2604,7 @@ impl TransactionHandlerDGC {
             let reported_value = match self._make_new_reported_value(
                 reporter_index,
                 timestamp,
+                block_timestamp,
                 update,
                 &prop,
             ) {
@@ -1220,15 +2647,29 @@ impl TransactionHandlerDGC {
         Ok(())
     }
 
+    /// `legacy` is set for family version 1.1 transactions in the blocks
+    /// `dgc.proposals.legacy_rules_until_block` keeps on the rules they were
+    /// made under: ids may be reused and amounts are not checked.
     fn _create_proposal(
         &self,
         payload: payload::CreateProposalAction,
         mut state: StateDGC,
         signer: &str,
         timestamp: u64,
+        block_timestamp: u64,
+        legacy: bool,
     ) -> Result<(), ApplyError> {
         let role = payload.get_role();
 
+        if payload.get_expires_at() != 0
+            && payload.get_expires_at() <= current_time(timestamp, block_timestamp)
+        {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal would expire at {}, which has already passed",
+                payload.get_expires_at()
+            )));
+        }
+
         let i_participant = match state.get_participant(signer) {
             Ok(Some(participant)) => participant,
             Ok(None) => {
@@ -1239,14 +2680,79 @@ impl TransactionHandlerDGC {
             }
             Err(err) => return Err(err),
         };
-            
+
+        if !legacy {
+            match state.get_proposal(payload.get_proposal_id()) {
+                Ok(Some(_)) => {
+                    return Err(ApplyError::InvalidTransaction(format!(
+                        "Proposal already exists: {}",
+                        payload.get_proposal_id()
+                    )))
+                }
+                Ok(None) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        if role == proposal::Proposal_Role::creditDGC {
+            state.authorize(signer, Permission::IssueCredit)?;
+            if payload.get_receiving_participant() == signer {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "A participant cannot extend credit to itself",
+                )));
+            }
+        }
+
+        if !legacy && (role == proposal::Proposal_Role::buyDGC
+        || role == proposal::Proposal_Role::sellDGC
+        || role == proposal::Proposal_Role::creditDGC
+        || role == proposal::Proposal_Role::transferDGC) {
+            let dg_coin_amount = payload.get_dg_coin_amount();
+            if !dg_coin_amount.is_finite() || dg_coin_amount <= 0.0 {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "The dg coin amount must be a positive number",
+                )));
+            }
+        }
+
+        // A transfer of ownership can be priced in dg coin, which the
+        // receiver pays the owner when accepting it
+        let price = if role == proposal::Proposal_Role::transferOwnership {
+            payload.get_dg_coin_amount()
+        } else {
+            0.0
+        };
+        if price != 0.0 {
+            if !price.is_finite() || price < 0.0 {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "The price must be a positive number",
+                )));
+            }
+            if state.is_shared_party(payload.get_receiving_participant())? {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Only a participant can pay the price of a record",
+                )));
+            }
+        }
+
+        // The party which issues the proposal: the signer, or the owner or
+        // custodian of the record it acts for
+        let mut issuing_participant = signer.to_string();
+        let action = PayloadAction::CreateProposal(payload.clone());
+
         if role == proposal::Proposal_Role::transferOwnership
         || role == proposal::Proposal_Role::authorizeReporter
         || role == proposal::Proposal_Role::transferCustodianship
         || role == proposal::Proposal_Role::creditDGC
         || role == proposal::Proposal_Role::transferDGC {
+            // Records can be transferred to organizations; everything else
+            // goes to a participant
+            let to_organization = role == proposal::Proposal_Role::transferOwnership
+                || role == proposal::Proposal_Role::transferCustodianship;
             match state.get_participant(&payload.get_receiving_participant()) {
-                Ok(Some(participant)) => participant,
+                Ok(Some(participant)) => check_active(&participant, "Receiving participant")?,
+                Ok(None) if to_organization
+                    && state.is_shared_party(payload.get_receiving_participant())? => (),
                 Ok(None) => {
                     return Err(ApplyError::InvalidTransaction(format!(
                         "Receiving participant does not exist: {}",
@@ -1286,11 +2792,29 @@ impl TransactionHandlerDGC {
                 }
             };
 
-            if owner.get_participant_id() != signer {
+            if price != 0.0 && state.is_shared_party(owner.get_participant_id())? {
                 return Err(ApplyError::InvalidTransaction(String::from(
-                    "Only the owner can create a proposal to change ownership",
+                    "Only a participant can be paid the price of a record",
                 )));
             }
+
+            match state.consent(
+                &[owner.get_participant_id()],
+                signer,
+                organization::Organization_Member_Permission::PROPOSE,
+                &action,
+                timestamp,
+                block_timestamp,
+            )? {
+                Consent::Given => (),
+                Consent::Pending => return Ok(()),
+                Consent::Refused => {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Only the owner can create a proposal to change ownership",
+                    )))
+                }
+            }
+            issuing_participant = owner.get_participant_id().to_string();
         }
 
         if role == proposal::Proposal_Role::transferCustodianship {
@@ -1321,28 +2845,30 @@ impl TransactionHandlerDGC {
                 }
             };
 
-            if custodian.get_participant_id() != signer {
-                return Err(ApplyError::InvalidTransaction(String::from(
-                    "Only the custodian can create a proposal to change custodianship",
-                )));
+            match state.consent(
+                &[custodian.get_participant_id()],
+                signer,
+                organization::Organization_Member_Permission::PROPOSE,
+                &action,
+                timestamp,
+                block_timestamp,
+            )? {
+                Consent::Given => (),
+                Consent::Pending => return Ok(()),
+                Consent::Refused => {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "Only the custodian can create a proposal to change custodianship",
+                    )))
+                }
             }
+            issuing_participant = custodian.get_participant_id().to_string();
         }
 
         if role == proposal::Proposal_Role::sellDGC {
-            // To confirm the dg_coin_amount is less than the balance + credit
-            let credit = match i_participant.clone().dg_coin_credits.last() {
-                Some(credit) => credit.clone(),
-                None => participant::Participant_CreditBalance::new(),
-            };
-            let i_dg_coin_credit = credit.get_dg_coin_amount();
-
-            let new_i_balance = match i_participant.clone().dg_coin_balances.last() {
-                Some(balance) => balance.clone(),
-                None => participant::Participant_CreditBalance::new(),
-            };
-            let i_dg_coin_balance = new_i_balance.get_dg_coin_amount();
-                                                        
-            if (i_dg_coin_balance + i_dg_coin_credit) < payload.get_dg_coin_amount()  {
+            // To confirm the dg_coin_amount is less than the balance and the
+            // credit which can still be drawn
+            let i_dg_coin_balance = latest_amount(i_participant.get_dg_coin_balances());
+            if (i_dg_coin_balance + available_credit(&i_participant)) < payload.get_dg_coin_amount()  {
                 return Err(ApplyError::InvalidTransaction(format!(
                     "The dg coin balance of issuing participant is not enough: {}",
                     signer
@@ -1362,7 +2888,6 @@ impl TransactionHandlerDGC {
                 return Err(ApplyError::InvalidTransaction(format!(
                     "The dg coin balance of issuing participant is not enough: {} /{:?}",
                     signer,
-                    //i_dg_coin_balance
                     new_i_balance
                 )))
             }
@@ -1373,30 +2898,36 @@ impl TransactionHandlerDGC {
         new_proposal.set_status(proposal::Proposal_Status::OPEN);
         new_proposal.set_role(role);
         new_proposal.set_timestamp(timestamp);
-        new_proposal.set_issuing_participant(signer.to_string());
+        new_proposal.set_issuing_participant(issuing_participant);
         new_proposal.set_receiving_participant(payload.get_receiving_participant().to_string());
         new_proposal.set_record_id(payload.get_record_id().to_string());
         new_proposal.set_properties(RepeatedField::from_vec(payload.get_properties().to_vec()));
         new_proposal.set_dg_coin_amount(payload.get_dg_coin_amount());
         new_proposal.set_currency_iso_codes(payload.get_currency_iso_codes().to_string());
         new_proposal.set_currency_quote_amount(payload.get_currency_quote_amount());
+        new_proposal.set_expires_at(payload.get_expires_at());
+        new_proposal.set_terms(payload.get_terms().to_string());
         state.set_proposal(payload.get_proposal_id(), new_proposal)?;
 
         Ok(())
     }
 
+    /// `legacy` is set for family version 1.1 transactions in the blocks
+    /// `dgc.proposals.legacy_rules_until_block` keeps on the rules they were
+    /// made under, where answers repeat the terms of the proposal and are
+    /// settled on those terms. Every other answer is settled on the terms
+    /// stored with the proposal.
     fn _answer_proposal(
         &self,
         payload: payload::AnswerProposalAction,
         mut state: StateDGC,
         signer: &str,
         timestamp: u64,
+        block_timestamp: u64,
+        legacy: bool,
     ) -> Result<(), ApplyError> {
         let proposal_id = payload.get_proposal_id();
         let response = payload.get_response();
-        let role = payload.get_role();
-        let receiving_participant = payload.get_receiving_participant();
-        let record_id = payload.get_record_id();
         let dg_coin_amount = payload.get_dg_coin_amount();
 
         let mut current_proposal = match state.get_proposal(proposal_id) {
@@ -1408,13 +2939,81 @@ impl TransactionHandlerDGC {
             }
             Err(err) => return Err(err),
         };
+
+        if !legacy && current_proposal.get_status() != proposal::Proposal_Status::OPEN {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal is not open: {}",
+                proposal_id
+            )));
+        }
+
+        // An expired proposal can still be rejected or canceled, which
+        // closes it as well as expiring it would
+        if response != payload::AnswerProposalAction_Response::REJECT
+            && response != payload::AnswerProposalAction_Response::CANCEL
+            && is_expired(&current_proposal, current_time(timestamp, block_timestamp))
+        {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal has expired: {}",
+                proposal_id
+            )));
+        }
+
+        // The terms of the answer are those of the proposal, whatever the
+        // payload repeats of them, except in version 1.1 where the answer
+        // carries them
+        let terms = if legacy {
+            (
+                payload.get_role(),
+                payload.get_receiving_participant().to_string(),
+                payload.get_record_id().to_string(),
+                dg_coin_amount,
+            )
+        } else {
+            (
+                current_proposal.get_role(),
+                current_proposal.get_receiving_participant().to_string(),
+                current_proposal.get_record_id().to_string(),
+                current_proposal.get_dg_coin_amount(),
+            )
+        };
+        let (role, receiving_participant, record_id, proposal_amount) = terms;
+        let receiving_participant = receiving_participant.as_str();
+        let record_id = record_id.as_str();
+        let issuer = current_proposal.get_issuing_participant().to_string();
+        let issuer = issuer.as_str();
+        let (answering, offering) = answering_parties(&current_proposal);
+
+        if response == payload::AnswerProposalAction_Response::autoOPEN
+        || response == payload::AnswerProposalAction_Response::autoCLOSE {
+            // An exchange operator settles on behalf of the issuing participant
+            state.authorize(signer, Permission::SettleExchange)?;
+        }
+
+        if !legacy && (response == payload::AnswerProposalAction_Response::autoOPEN
+        || response == payload::AnswerProposalAction_Response::autoCLOSE) {
+            if !dg_coin_amount.is_finite() || dg_coin_amount <= 0.0 {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "The dg coin amount must be a positive number",
+                )));
+            }
+            if current_proposal.get_dg_coin_exchanged() + dg_coin_amount > proposal_amount {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "The dg coin amount exceeds what remains of the proposal: {}",
+                    proposal_id
+                )));
+            }
+        }
             
-        let mut i_participant = match state.get_participant(signer) {
-            Ok(Some(participant)) => participant.clone(),
+        // Organizations and multisig policies issue proposals for records,
+        // and have no balances for a proposal to move
+        let i_participant = match state.get_participant(issuer) {
+            Ok(Some(participant)) => Some(participant),
+            Ok(None) if state.is_shared_party(issuer)? => None,
             Ok(None) => {
                 return Err(ApplyError::InvalidTransaction(format!(
                 "Issuing participant does not exist: {}",
-                    signer
+                    issuer
                 )))
             }
             Err(err) => return Err(err),
@@ -1422,19 +3021,41 @@ impl TransactionHandlerDGC {
 
         match response {
             payload::AnswerProposalAction_Response::CANCEL => {
-                if current_proposal.get_issuing_participant() != signer {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Only the issuing participant can cancel a proposal",
-                    )));
+                match state.consent(
+                    &[offering.as_str()],
+                    signer,
+                    organization::Organization_Member_Permission::PROPOSE,
+                    &PayloadAction::AnswerProposal(payload.clone()),
+                    timestamp,
+                    block_timestamp,
+                )? {
+                    Consent::Given => (),
+                    Consent::Pending => return Ok(()),
+                    Consent::Refused => {
+                        return Err(ApplyError::InvalidTransaction(String::from(
+                            "Only the participant who made a proposal can cancel it",
+                        )))
+                    }
                 }
                 current_proposal.set_status(proposal::Proposal_Status::CANCELED);
                 state.set_proposal(proposal_id, current_proposal)?;
             }
             payload::AnswerProposalAction_Response::REJECT => {
-                if current_proposal.get_receiving_participant() != signer {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Only the receiving participant can reject a proposal",
-                    )));
+                match state.consent(
+                    &[answering.as_str()],
+                    signer,
+                    organization::Organization_Member_Permission::ANSWER,
+                    &PayloadAction::AnswerProposal(payload.clone()),
+                    timestamp,
+                    block_timestamp,
+                )? {
+                    Consent::Given => (),
+                    Consent::Pending => return Ok(()),
+                    Consent::Refused => {
+                        return Err(ApplyError::InvalidTransaction(String::from(
+                            "Only the participant a proposal was sent to can reject it",
+                        )))
+                    }
                 }
                 current_proposal.set_status(proposal::Proposal_Status::REJECTED);
                 state.set_proposal(proposal_id, current_proposal)?;
@@ -1446,6 +3067,8 @@ impl TransactionHandlerDGC {
                     proposal::Proposal_Role::authorizeReporter => {}
                     proposal::Proposal_Role::buyDGC => {
                         // To increase the dg_coin_balance for issuing_participant(buy)
+                        let mut i_participant =
+                            balance_holder(i_participant, "Issuing participant", issuer)?;
                         let mut new_i_balance = match i_participant.clone().dg_coin_balances.last() {
                             Some(balance) => balance.clone(),
                             None => participant::Participant_CreditBalance::new(),
@@ -1454,9 +3077,10 @@ impl TransactionHandlerDGC {
                                                         
                         new_i_balance.set_proposal_id(proposal_id.to_string());
                         new_i_balance.set_timestamp(timestamp);
+                        new_i_balance.set_block_timestamp(block_timestamp);
                         new_i_balance.set_dg_coin_amount(i_dg_coin_balance + dg_coin_amount);
                         i_participant.dg_coin_balances.push(new_i_balance);
-                        state.set_participant(signer, i_participant.clone())?;
+                        state.set_participant(issuer, i_participant.clone())?;
 
                         let dg_coin_exchanged = current_proposal.get_dg_coin_exchanged();
                         current_proposal.set_dg_coin_exchanged(dg_coin_exchanged+dg_coin_amount);
@@ -1464,31 +3088,18 @@ impl TransactionHandlerDGC {
                         state.set_proposal(payload.get_proposal_id(), current_proposal)?;
                     }
                     proposal::Proposal_Role::sellDGC => {
-                        // To decrease the dg_coin_balance for issuing_participant(sell)
-                        let credit = match i_participant.clone().dg_coin_credits.last() {
-                            Some(credit) => credit.clone(),
-                            None => participant::Participant_CreditBalance::new(),
-                        };
-                        let i_dg_coin_credit = credit.get_dg_coin_amount();
-
-                        let mut new_i_balance = match i_participant.clone().dg_coin_balances.last() {
-                            Some(balance) => balance.clone(),
-                            None => participant::Participant_CreditBalance::new(),
-                        };
-                        let i_dg_coin_balance = new_i_balance.get_dg_coin_amount();
-                                                        
-                        if (i_dg_coin_balance + i_dg_coin_credit) < (dg_coin_amount) {
-                            return Err(ApplyError::InvalidTransaction(format!(
-                                "The dg coin balance of issuing participant is not enough: {}",
-                                signer
-                            )))
-                        }
-
-                        new_i_balance.set_proposal_id(proposal_id.to_string());
-                        new_i_balance.set_timestamp(timestamp);
-                        new_i_balance.set_dg_coin_amount(i_dg_coin_balance - dg_coin_amount);
-                        i_participant.dg_coin_balances.push(new_i_balance);
-                        state.set_participant(signer, i_participant.clone())?;
+                        // To decrease the dg_coin_balance for issuing_participant(sell),
+                        // drawing on its credit for what the balance does not cover
+                        let mut i_participant =
+                            balance_holder(i_participant, "Issuing participant", issuer)?;
+                        spend_dg_coin(
+                            &mut i_participant,
+                            dg_coin_amount,
+                            proposal_id,
+                            timestamp,
+                            block_timestamp,
+                        )?;
+                        state.set_participant(issuer, i_participant.clone())?;
 
                         let dg_coin_exchanged = current_proposal.get_dg_coin_exchanged();
                         current_proposal.set_dg_coin_exchanged(dg_coin_exchanged+dg_coin_amount);
@@ -1506,6 +3117,8 @@ impl TransactionHandlerDGC {
                     proposal::Proposal_Role::authorizeReporter => {}
                     proposal::Proposal_Role::buyDGC => {
                         // To increase the dg_coin_balance for issuing_participant(buy)
+                        let mut i_participant =
+                            balance_holder(i_participant, "Issuing participant", issuer)?;
                         let mut new_i_balance = match i_participant.clone().dg_coin_balances.last() {
                             Some(balance) => balance.clone(),
                             None => participant::Participant_CreditBalance::new(),
@@ -1514,15 +3127,12 @@ impl TransactionHandlerDGC {
                                                         
                         new_i_balance.set_proposal_id(proposal_id.to_string());
                         new_i_balance.set_timestamp(timestamp);
+                        new_i_balance.set_block_timestamp(block_timestamp);
                         new_i_balance.set_dg_coin_amount(i_dg_coin_balance + dg_coin_amount);
                         i_participant.dg_coin_balances.push(new_i_balance);
-                        state.set_participant(signer, i_participant.clone())?;
+                        state.set_participant(issuer, i_participant.clone())?;
 
-                        //let mut exchanges: HashMap<&str, proposal::Exchange> = HashMap::new();
                         for exchange in payload.get_exchanges() {
-                            //exchanges.insert(exchange.get_buy_proposal_id(), exchange.clone());
-                        //}
-                        //for (buy_proposal_id, exchange) in exchanges {
                             let mut new_exchange = proposal::Exchange::new();
                             new_exchange.set_buy_proposal_id(exchange.get_buy_proposal_id().to_string());
                             new_exchange.set_sell_proposal_id(exchange.get_sell_proposal_id().to_string());
@@ -1539,37 +3149,20 @@ impl TransactionHandlerDGC {
                         state.set_proposal(proposal_id, current_proposal)?;
                     }
                     proposal::Proposal_Role::sellDGC => {
-                        // To decrease the dg_coin_balance for issuing_participant(sell)
-                        let credit = match i_participant.clone().dg_coin_credits.last() {
-                            Some(credit) => credit.clone(),
-                            None => participant::Participant_CreditBalance::new(),
-                        };
-                        let i_dg_coin_credit = credit.get_dg_coin_amount();
-
-                        let mut new_i_balance = match i_participant.clone().dg_coin_balances.last() {
-                            Some(balance) => balance.clone(),
-                            None => participant::Participant_CreditBalance::new(),
-                        };
-                        let i_dg_coin_balance = new_i_balance.get_dg_coin_amount();
-                                                        
-                        if (i_dg_coin_balance + i_dg_coin_credit) < (dg_coin_amount) {
-                            return Err(ApplyError::InvalidTransaction(format!(
-                                "The dg coin balance of issuing participant is not enough: {}",
-                                signer
-                            )))
-                        }
+                        // To decrease the dg_coin_balance for issuing_participant(sell),
+                        // drawing on its credit for what the balance does not cover
+                        let mut i_participant =
+                            balance_holder(i_participant, "Issuing participant", issuer)?;
+                        spend_dg_coin(
+                            &mut i_participant,
+                            dg_coin_amount,
+                            proposal_id,
+                            timestamp,
+                            block_timestamp,
+                        )?;
+                        state.set_participant(issuer, i_participant.clone())?;
 
-                        new_i_balance.set_proposal_id(proposal_id.to_string());
-                        new_i_balance.set_timestamp(timestamp);
-                        new_i_balance.set_dg_coin_amount(i_dg_coin_balance - dg_coin_amount);
-                        i_participant.dg_coin_balances.push(new_i_balance);
-                        state.set_participant(signer, i_participant.clone())?;
-
-                        //let mut exchanges: HashMap<&str, proposal::Exchange> = HashMap::new();
                         for exchange in payload.get_exchanges() {
-                            //exchanges.insert(exchange.get_sell_proposal_id(), exchange.clone());
-                        //}
-                        //for (sell_proposal_id, exchange) in exchanges {
                             let mut new_exchange = proposal::Exchange::new();
                             new_exchange.set_buy_proposal_id(exchange.get_buy_proposal_id().to_string());
                             new_exchange.set_sell_proposal_id(exchange.get_sell_proposal_id().to_string());
@@ -1590,15 +3183,28 @@ impl TransactionHandlerDGC {
                 }
             }
             payload::AnswerProposalAction_Response::ACCEPT => {
- 
-                if current_proposal.get_receiving_participant() != signer {
-                    return Err(ApplyError::InvalidTransaction(String::from(
-                        "Only the receiving participant can Accept a proposal",
-                    )));
-                };
+                match state.consent(
+                    &[answering.as_str()],
+                    signer,
+                    organization::Organization_Member_Permission::ANSWER,
+                    &PayloadAction::AnswerProposal(payload.clone()),
+                    timestamp,
+                    block_timestamp,
+                )? {
+                    Consent::Given => (),
+                    Consent::Pending => return Ok(()),
+                    Consent::Refused => {
+                        return Err(ApplyError::InvalidTransaction(String::from(
+                            "Only the participant a proposal was sent to can accept it",
+                        )))
+                    }
+                }
 
-                let mut r_participant = match state.get_participant(&receiving_participant) {
-                    Ok(Some(participant)) => participant.clone(),
+                // Organizations and multisig policies only receive records,
+                // and have no balances for a proposal to move
+                let r_participant = match state.get_participant(receiving_participant) {
+                    Ok(Some(participant)) => Some(participant),
+                    Ok(None) if state.is_shared_party(receiving_participant)? => None,
                     Ok(None) => {
                         return Err(ApplyError::InvalidTransaction(format!(
                             "Receiving participant does not exist: {}",
@@ -1611,6 +3217,11 @@ impl TransactionHandlerDGC {
                 match role {
                     proposal::Proposal_Role::creditDGC => {
                         // To increase the dg_coin_credit for receiving_participant
+                        let mut r_participant = balance_holder(
+                            r_participant,
+                            "Receiving participant",
+                            receiving_participant,
+                        )?;
                         let r_credit = match r_participant.clone().dg_coin_credits.last() {
                             Some(credit) => credit.clone(),
                             None => participant::Participant_CreditBalance::new(),
@@ -1620,9 +3231,10 @@ impl TransactionHandlerDGC {
                         let mut new_credit = participant::Participant_CreditBalance::new();
                         new_credit.set_proposal_id(proposal_id.to_string());
                         new_credit.set_timestamp(timestamp);
-                        new_credit.set_dg_coin_amount(r_dg_coin_credit + dg_coin_amount);
+                        new_credit.set_block_timestamp(block_timestamp);
+                        new_credit.set_dg_coin_amount(r_dg_coin_credit + proposal_amount);
                         r_participant.dg_coin_credits.push(new_credit);
-                        state.set_participant(&receiving_participant, r_participant.clone())?;
+                        state.set_participant(receiving_participant, r_participant.clone())?;
 
                         current_proposal.set_status(proposal::Proposal_Status::ACCEPTED);
                         state.set_proposal(proposal_id, current_proposal)?;
@@ -1630,27 +3242,51 @@ impl TransactionHandlerDGC {
                     proposal::Proposal_Role::buyDGC => {}
                     proposal::Proposal_Role::sellDGC => {}
                     proposal::Proposal_Role::transferDGC => {
-                        // To decrease the dg_coin_balance for issuing_participant(transfer out)
+                        // To decrease the dg_coin_balance for issuing_participant(transfer out),
+                        // which in version 1.1 is whoever accepts the transfer
+                        let issuing_participant = if legacy { signer } else { issuer }.to_string();
+                        let mut i_participant = match state.get_participant(&issuing_participant) {
+                            Ok(Some(participant)) => participant,
+                            Ok(None) => {
+                                return Err(ApplyError::InvalidTransaction(format!(
+                                    "Issuing participant does not exist: {}",
+                                    issuing_participant
+                                )))
+                            }
+                            Err(err) => return Err(err),
+                        };
+                        check_active(&i_participant, "Issuing participant")?;
                         let mut new_i_balance = match i_participant.clone().dg_coin_balances.last() {
                             Some(balance) => balance.clone(),
                             None => participant::Participant_CreditBalance::new(),
                         };
                         let i_dg_coin_balance = new_i_balance.get_dg_coin_amount();
 
-                        if i_dg_coin_balance < dg_coin_amount {
+                        if i_dg_coin_balance < proposal_amount {
                             return Err(ApplyError::InvalidTransaction(format!(
-                                "The dg coin balance of issuving participant is not enough: {}",
-                                signer
+                                "The dg coin balance of issuing participant is not enough: {}",
+                                issuing_participant
                             )))
                         }
 
                         new_i_balance.set_proposal_id(proposal_id.to_string());
                         new_i_balance.set_timestamp(timestamp);
-                        new_i_balance.set_dg_coin_amount(i_dg_coin_balance - dg_coin_amount);
+                        new_i_balance.set_block_timestamp(block_timestamp);
+                        new_i_balance.set_dg_coin_amount(i_dg_coin_balance - proposal_amount);
                         i_participant.dg_coin_balances.push(new_i_balance);
-                        state.set_participant(signer, i_participant.clone())?;
-
-                        // To increse the dg_coin_balance for receiving_participant(transfer in)
+                        state.set_participant(&issuing_participant, i_participant)?;
+
+                        // To increse the dg_coin_balance for receiving_participant(transfer in),
+                        // read again in case the issuing participant is also the receiver
+                        let mut r_participant = match state.get_participant(receiving_participant) {
+                            Ok(Some(participant)) => participant,
+                            Ok(None) => balance_holder(
+                                r_participant,
+                                "Receiving participant",
+                                receiving_participant,
+                            )?,
+                            Err(err) => return Err(err),
+                        };
                         let mut new_r_balance = match r_participant.clone().dg_coin_balances.last() {
                             Some(balance) => balance.clone(),
                             None => participant::Participant_CreditBalance::new(),
@@ -1659,9 +3295,10 @@ impl TransactionHandlerDGC {
                         
                         new_r_balance.set_proposal_id(proposal_id.to_string());
                         new_r_balance.set_timestamp(timestamp);
-                        new_r_balance.set_dg_coin_amount(r_dg_coin_balance + dg_coin_amount);
+                        new_r_balance.set_block_timestamp(block_timestamp);
+                        new_r_balance.set_dg_coin_amount(r_dg_coin_balance + proposal_amount);
                         r_participant.dg_coin_balances.push(new_r_balance);
-                        state.set_participant(&receiving_participant, r_participant.clone())?;
+                        state.set_participant(receiving_participant, r_participant.clone())?;
 
                         current_proposal.set_status(proposal::Proposal_Status::ACCEPTED);
                         state.set_proposal(proposal_id, current_proposal)?;
@@ -1686,19 +3323,67 @@ impl TransactionHandlerDGC {
                                 )))
                             }
                         };
-/*
-                        if owner.get_participant_id() != current_proposal.get_issuing_participant() {
-                            current_proposal.set_status(proposal::Proposal_Status::CANCELED);
-                            info!("Record owner does not match the issuing participant of the proposal");
-                            state.set_proposal(proposal_id, current_proposal)?;
-                            return Err(ApplyError::InvalidTransaction(String::from(
-                                "Record owner does not match the issuing participant of the proposal",
-                            )))
+                        // Only the owner who made the proposal can give the
+                        // record away, priced or not
+                        if owner.get_participant_id() != issuer {
+                            return Err(ApplyError::InvalidTransaction(format!(
+                                "Record has changed owner since the proposal was made: {}",
+                                record_id
+                            )));
+                        }
+
+                        // The price is paid in the same transaction as the
+                        // record changes hands, so neither happens without
+                        // the other. The price is always the one stored with
+                        // the proposal
+                        let price = current_proposal.get_dg_coin_amount();
+                        if price > 0.0 {
+                            let mut r_participant = balance_holder(
+                                r_participant,
+                                "Receiving participant",
+                                receiving_participant,
+                            )?;
+                            let i_participant =
+                                balance_holder(i_participant, "Issuing participant", issuer)?;
+                            check_active(&r_participant, "Receiving participant")?;
+                            check_active(&i_participant, "Issuing participant")?;
+                            if latest_amount(r_participant.get_dg_coin_balances()) < price {
+                                return Err(ApplyError::InvalidTransaction(format!(
+                                    "The dg coin balance of receiving participant is not enough: {}",
+                                    receiving_participant
+                                )));
+                            }
+
+                            change_amount(
+                                &mut r_participant.dg_coin_balances,
+                                -price,
+                                proposal_id,
+                                "",
+                                timestamp,
+                                block_timestamp,
+                            );
+                            state.set_participant(receiving_participant, r_participant.clone())?;
+
+                            // Read again in case the receiver is also the issuer
+                            let mut i_participant = match state.get_participant(issuer)? {
+                                Some(participant) => participant,
+                                None => i_participant.clone(),
+                            };
+                            change_amount(
+                                &mut i_participant.dg_coin_balances,
+                                price,
+                                proposal_id,
+                                "",
+                                timestamp,
+                                block_timestamp,
+                            );
+                            state.set_participant(issuer, i_participant)?;
                         }
-*/
+
                         let mut new_owner = record::Record_AssociatedParticipant::new();
                         new_owner.set_participant_id(receiving_participant.to_string());
                         new_owner.set_timestamp(timestamp);
+                        new_owner.set_block_timestamp(block_timestamp);
                         proposal_record.owners.push(new_owner);
                         state.set_record(record_id, proposal_record.clone())?;
 
@@ -1714,6 +3399,18 @@ impl TransactionHandlerDGC {
                                 Err(err) => return Err(err),
                             };
 
+                        // The reporters of an organization or multisig policy
+                        // are its members. The previous owner's stop
+                        // reporting, and the participant who accepted reports
+                        // for the new owner, or all of the new owner's when
+                        // the issuer accepted a counter-offer
+                        let previous_reporters = state.party_members(owner.get_participant_id())?;
+                        let accepting_reporters = if current_proposal.get_sent_to_issuer() {
+                            state.party_members(receiving_participant)?
+                        } else {
+                            vec![signer.to_string()]
+                        };
+
                         for prop_schema in table.get_properties() {
                             let mut prop =
                                 match state.get_property(record_id, prop_schema.get_name()) {
@@ -1726,135 +3423,410 @@ impl TransactionHandlerDGC {
                                     Err(err) => return Err(err),
                                 };
 
-                            let mut authorized = false;
+                            let mut unlisted = accepting_reporters.clone();
                             let mut new_reporters: Vec<
                                 property::Property_Reporter,
                             > = Vec::new();
                             let temp_prob = prop.clone();
                             let reporters = temp_prob.get_reporters();
                             for reporter in reporters {
-                                if reporter.get_public_key() == owner.get_participant_id() {
+                                if accepting_reporters.iter().any(|key| key == reporter.get_public_key()) {
                                     let mut new_reporter = reporter.clone();
-                                    new_reporter.set_authorized(false);
+                                    new_reporter.set_authorized(true);
+                                    unlisted.retain(|key| key != reporter.get_public_key());
                                     new_reporters.push(new_reporter);
-                                } else if reporter.get_public_key() == receiving_participant {
+                                } else if previous_reporters.iter().any(|key| key == reporter.get_public_key()) {
                                     let mut new_reporter = reporter.clone();
-                                    new_reporter.set_authorized(true);
-                                    authorized = true;
+                                    new_reporter.set_authorized(false);
                                     new_reporters.push(new_reporter);
                                 } else {
                                     new_reporters.push(reporter.clone());
                                 }
                             }
 
-                            if !authorized {
-                                let mut reporter = property::Property_Reporter::new();
-                                reporter.set_public_key(receiving_participant.to_string());
-                                reporter.set_authorized(true);
-                                reporter.set_index(prop.reporters.len() as u32);
-                                new_reporters.push(reporter);
-                            }
+                            for key in unlisted {
+                                let mut reporter = property::Property_Reporter::new();
+                                reporter.set_public_key(key);
+                                reporter.set_authorized(true);
+                                reporter.set_index(new_reporters.len() as u32);
+                                new_reporters.push(reporter);
+                            }
+
+                            prop.set_reporters(RepeatedField::from_vec(new_reporters));
+                            state.set_property(record_id, prop.get_name(), prop.clone())?;
+                        }
+                        current_proposal.status = proposal::Proposal_Status::ACCEPTED;
+                        state.set_proposal(proposal_id, current_proposal)?;
+                    }
+                    proposal::Proposal_Role::transferCustodianship => {
+                        let mut proposal_record = match state.get_record(record_id) {
+                            Ok(Some(record)) => record,
+                            Ok(None) => {
+                                return Err(ApplyError::InvalidTransaction(format!(
+                                    "Record in proposal does not exist: {}",
+                                    record_id
+                                )))
+                            }
+                            Err(err) => return Err(err),
+                        };
+
+                        let custodian = match proposal_record.clone().custodians.last() {
+                            Some(custodian) => custodian.clone(),
+                            None => {
+                                return Err(ApplyError::InvalidTransaction(String::from(
+                                    "Custodian not found",
+                                )))
+                            }
+                        };
+                        let mut new_custodian = record::Record_AssociatedParticipant::new();
+                        new_custodian.set_participant_id(receiving_participant.to_string());
+                        new_custodian.set_timestamp(timestamp);
+                        new_custodian.set_block_timestamp(block_timestamp);
+                        proposal_record.custodians.push(new_custodian.clone());
+                        state.set_record(record_id, proposal_record)?;
+                        current_proposal.status = proposal::Proposal_Status::ACCEPTED;
+                        state.set_proposal(proposal_id, current_proposal)?;
+                    }
+                    proposal::Proposal_Role::authorizeReporter => {
+                        let proposal_record = match state.get_record(record_id) {
+                            Ok(Some(record)) => record,
+                            Ok(None) => {
+                                return Err(ApplyError::InvalidTransaction(format!(
+                                    "Record in proposal does not exist: {}",
+                                    record_id
+                                )))
+                            }
+                            Err(err) => return Err(err),
+                        };
+
+                        let owner = match proposal_record.clone().owners.last() {
+                            Some(owner) => owner.clone(),
+                            None => {
+                                return Err(ApplyError::InvalidTransaction(String::from(
+                                    "Owner not found",
+                                )))
+                            }
+                        };
+                        let mut reporter = property::Property_Reporter::new();
+                        reporter.set_public_key(receiving_participant.to_string());
+                        reporter.set_authorized(true);
+
+                        for prop_name in current_proposal.get_properties() {
+                            let mut prop = match state.get_property(record_id, prop_name) {
+                                Ok(Some(prop)) => prop,
+                                Ok(None) => {
+                                    return Err(ApplyError::InvalidTransaction(String::from(
+                                        "Property does not exist",
+                                    )))
+                                }
+                                Err(err) => return Err(err),
+                            };
+                            reporter.set_index(prop.reporters.len() as u32);
+                            prop.reporters.push(reporter.clone());
+                            state.set_property(record_id, prop_name, prop)?;
+                        }
+                        current_proposal.status = proposal::Proposal_Status::ACCEPTED;
+                        state.set_proposal(proposal_id, current_proposal)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn _counter_proposal(
+        &self,
+        payload: payload_v2::AnswerProposalV2Action,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let proposal_id = payload.get_proposal_id();
+        let counter = payload.get_counter();
+        let now = current_time(timestamp, block_timestamp);
+
+        let mut current_proposal = match state.get_proposal(proposal_id)? {
+            Some(proposal) => proposal,
+            None => {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Proposal does not exist",
+                )))
+            }
+        };
+
+        // Countering closes the proposal, so each negotiation has one open
+        // proposal at a time
+        if current_proposal.get_status() != proposal::Proposal_Status::OPEN {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal is not open: {}",
+                proposal_id
+            )));
+        }
+        if is_expired(&current_proposal, now) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal has expired: {}",
+                proposal_id
+            )));
+        }
+
+        let role = current_proposal.get_role();
+        if role == proposal::Proposal_Role::buyDGC || role == proposal::Proposal_Role::sellDGC {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "A {:?} proposal has no receiving participant to counter it",
+                role
+            )));
+        }
+
+        if counter.get_proposal_id() == "" {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "Counter-offer proposal id cannot be empty",
+            )));
+        }
+        if state.get_proposal(counter.get_proposal_id())?.is_some() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal already exists: {}",
+                counter.get_proposal_id()
+            )));
+        }
+        if counter.get_expires_at() != 0 && counter.get_expires_at() <= now {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Proposal would expire at {}, which has already passed",
+                counter.get_expires_at()
+            )));
+        }
+
+        // The counter-offer must pass the checks a new proposal on its terms
+        // would. Only a transfer of ownership can be countered down to no
+        // amount, which gives the record away
+        let amount = counter.get_dg_coin_amount();
+        let dg_coin_amount = if counter.get_has_dg_coin_amount() || amount != 0.0 {
+            if role == proposal::Proposal_Role::transferOwnership {
+                if !amount.is_finite() || amount < 0.0 {
+                    return Err(ApplyError::InvalidTransaction(String::from(
+                        "The price must be a positive number",
+                    )));
+                }
+            } else if !amount.is_finite() || amount <= 0.0 {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "The dg coin amount must be a positive number",
+                )));
+            }
+            amount
+        } else {
+            current_proposal.get_dg_coin_amount()
+        };
+        let issuer = current_proposal.get_issuing_participant().to_string();
+        let receiver = current_proposal.get_receiving_participant().to_string();
+        if role == proposal::Proposal_Role::transferOwnership && dg_coin_amount != 0.0 {
+            if state.is_shared_party(&receiver)? {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Only a participant can pay the price of a record",
+                )));
+            }
+            if state.is_shared_party(&issuer)? {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Only a participant can be paid the price of a record",
+                )));
+            }
+        }
+        if let Some(participant) = state.get_participant(&receiver)? {
+            check_active(&participant, "Receiving participant")?;
+        }
+        if let Some(participant) = state.get_participant(&issuer)? {
+            check_active(&participant, "Issuing participant")?;
+            if role == proposal::Proposal_Role::transferDGC
+                && latest_amount(participant.get_dg_coin_balances()) < dg_coin_amount
+            {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "The dg coin balance of issuing participant is not enough: {}",
+                    issuer
+                )));
+            }
+        }
+
+        let (answering, _) = answering_parties(&current_proposal);
+        match state.consent(
+            &[answering.as_str()],
+            signer,
+            organization::Organization_Member_Permission::ANSWER,
+            &PayloadAction::CounterProposal(payload.clone()),
+            timestamp,
+            block_timestamp,
+        )? {
+            Consent::Given => (),
+            Consent::Pending => return Ok(()),
+            Consent::Refused => {
+                return Err(ApplyError::InvalidTransaction(String::from(
+                    "Only the participant a proposal was sent to can counter it",
+                )))
+            }
+        }
+
+        let mut new_proposal = current_proposal.clone();
+        new_proposal.set_proposal_id(counter.get_proposal_id().to_string());
+        new_proposal.set_status(proposal::Proposal_Status::OPEN);
+        new_proposal.set_timestamp(timestamp);
+        new_proposal.set_terms(counter.get_terms().to_string());
+        new_proposal.set_dg_coin_amount(dg_coin_amount);
+        if !counter.get_properties().is_empty() {
+            new_proposal.set_properties(RepeatedField::from_vec(counter.get_properties().to_vec()));
+        }
+        new_proposal.set_expires_at(counter.get_expires_at());
+        new_proposal.set_parent_proposal_id(proposal_id.to_string());
+        new_proposal.set_sent_to_issuer(!current_proposal.get_sent_to_issuer());
+        state.set_proposal(counter.get_proposal_id(), new_proposal)?;
+
+        current_proposal.set_status(proposal::Proposal_Status::COUNTERED);
+        state.set_proposal(proposal_id, current_proposal)?;
+        Ok(())
+    }
+
+    fn _set_credit_limit(
+        &self,
+        payload: payload_v2::SetCreditLimitAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        // Setting limits is never open to everyone, whether or not roles are
+        // enforced
+        if !state.holds(signer, Permission::IssueCredit)? {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Participant {} does not have the {} permission",
+                signer,
+                Permission::IssueCredit.name()
+            )));
+        }
+
+        let participant_id = payload.get_participant_id();
+        if participant_id == signer {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "A participant cannot set its own credit limit",
+            )));
+        }
+        let limit = payload.get_dg_coin_amount();
+        if !limit.is_finite() || limit < 0.0 {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "The credit limit must be zero or a positive number",
+            )));
+        }
+
+        let mut participant = match state.get_participant(participant_id)? {
+            Some(participant) => participant,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant does not exist: {}",
+                    participant_id
+                )))
+            }
+        };
+
+        let change = limit - latest_amount(participant.get_dg_coin_credits());
+        change_amount(
+            &mut participant.dg_coin_credits,
+            change,
+            "",
+            signer,
+            timestamp,
+            block_timestamp,
+        );
+        state.set_participant(participant_id, participant)
+    }
 
-                            prop.set_reporters(RepeatedField::from_vec(new_reporters));
-                            state.set_property(record_id, prop.get_name(), prop.clone())?;
-                        }
-                        current_proposal.status = proposal::Proposal_Status::ACCEPTED;
-                        state.set_proposal(proposal_id, current_proposal)?;
-                    }
-                    proposal::Proposal_Role::transferCustodianship => {
-                        let mut proposal_record = match state.get_record(record_id) {
-                            Ok(Some(record)) => record,
-                            Ok(None) => {
-                                return Err(ApplyError::InvalidTransaction(format!(
-                                    "Record in proposal does not exist: {}",
-                                    record_id
-                                )))
-                            }
-                            Err(err) => return Err(err),
-                        };
+    fn _repay_credit(
+        &self,
+        payload: payload_v2::RepayCreditAction,
+        mut state: StateDGC,
+        signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let mut participant = match state.get_participant(signer)? {
+            Some(participant) => participant,
+            None => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Participant does not exist: {}",
+                    signer
+                )))
+            }
+        };
 
-                        let custodian = match proposal_record.clone().custodians.last() {
-                            Some(custodian) => custodian.clone(),
-                            None => {
-                                return Err(ApplyError::InvalidTransaction(String::from(
-                                    "Custodian not found",
-                                )))
-                            }
-                        };
-/*                        
-                        if custodian.get_participant_id() != current_proposal.get_issuing_participant() {
-                            current_proposal.set_status(proposal::Proposal_Status::CANCELED);
-                            info!("Record custodian does not match the issuing participant of the proposal");
-                            state.set_proposal(proposal_id, current_proposal)?;
-                            return Err(ApplyError::InvalidTransaction(String::from(
-                                "Record custodian does not match the issuing participant of the proposal",
-                            )))
-                        }
-*/
-                        let mut new_custodian = record::Record_AssociatedParticipant::new();
-                        new_custodian.set_participant_id(receiving_participant.to_string());
-                        new_custodian.set_timestamp(timestamp);
-                        proposal_record.custodians.push(new_custodian.clone());
-                        state.set_record(record_id, proposal_record)?;
-                        current_proposal.status = proposal::Proposal_Status::ACCEPTED;
-                        state.set_proposal(proposal_id, current_proposal)?;
-                    }
-                    proposal::Proposal_Role::authorizeReporter => {
-                        let proposal_record = match state.get_record(record_id) {
-                            Ok(Some(record)) => record,
-                            Ok(None) => {
-                                return Err(ApplyError::InvalidTransaction(format!(
-                                    "Record in proposal does not exist: {}",
-                                    record_id
-                                )))
-                            }
-                            Err(err) => return Err(err),
-                        };
+        let amount = payload.get_dg_coin_amount();
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err(ApplyError::InvalidTransaction(String::from(
+                "The dg coin amount must be a positive number",
+            )));
+        }
+        if amount > latest_amount(participant.get_dg_coin_drawn_credits()) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "The dg coin amount is more than the credit drawn by participant: {}",
+                signer
+            )));
+        }
+        if amount > latest_amount(participant.get_dg_coin_balances()) {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "The dg coin balance of participant is not enough: {}",
+                signer
+            )));
+        }
 
-                        let owner = match proposal_record.clone().owners.last() {
-                            Some(owner) => owner.clone(),
-                            None => {
-                                return Err(ApplyError::InvalidTransaction(String::from(
-                                    "Owner not found",
-                                )))
-                            }
-                        };
-/*
-                        if owner.get_participant_id() != current_proposal.get_issuing_participant() {
-                            current_proposal.set_status(proposal::Proposal_Status::CANCELED);
-                            info!("Record owner does not match the issuing participant of the proposal");
-                            state.set_proposal(proposal_id, current_proposal)?;
-                            return Err(ApplyError::InvalidTransaction(String::from(
-                                "Record owner does not match the issuing participant of the proposal",
-                            )))
-                        }
-*/
-                        let mut reporter = property::Property_Reporter::new();
-                        reporter.set_public_key(receiving_participant.to_string());
-                        reporter.set_authorized(true);
+        change_amount(
+            &mut participant.dg_coin_balances,
+            -amount,
+            "",
+            signer,
+            timestamp,
+            block_timestamp,
+        );
+        change_amount(
+            &mut participant.dg_coin_drawn_credits,
+            -amount,
+            "",
+            signer,
+            timestamp,
+            block_timestamp,
+        );
+        state.set_participant(signer, participant)
+    }
 
-                        for prop_name in current_proposal.get_properties() {
-                            let mut prop = match state.get_property(record_id, prop_name) {
-                                Ok(Some(prop)) => prop,
-                                Ok(None) => {
-                                    return Err(ApplyError::InvalidTransaction(String::from(
-                                        "Property does not exist",
-                                    )))
-                                }
-                                Err(err) => return Err(err),
-                            };
-                            reporter.set_index(prop.reporters.len() as u32);
-                            prop.reporters.push(reporter.clone());
-                            state.set_property(record_id, prop_name, prop)?;
-                        }
-                        current_proposal.status = proposal::Proposal_Status::ACCEPTED;
-                        state.set_proposal(proposal_id, current_proposal)?;
-                    }
+    fn _expire_proposals(
+        &self,
+        payload: payload_v2::ExpireProposalsAction,
+        mut state: StateDGC,
+        timestamp: u64,
+        block_timestamp: u64,
+    ) -> Result<(), ApplyError> {
+        let now = current_time(timestamp, block_timestamp);
+        for proposal_id in payload.get_proposal_ids() {
+            let mut current_proposal = match state.get_proposal(proposal_id)? {
+                Some(proposal) => proposal,
+                None => {
+                    return Err(ApplyError::InvalidTransaction(format!(
+                        "Proposal does not exist: {}",
+                        proposal_id
+                    )))
                 }
+            };
+
+            // A proposal answered since the expiry was submitted is left
+            // as it is
+            if current_proposal.get_status() != proposal::Proposal_Status::OPEN {
+                continue;
+            }
+            if !is_expired(&current_proposal, now) {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Proposal has not expired: {}",
+                    proposal_id
+                )));
             }
-        }
-        //state.set_proposal(proposal_id, current_proposal)?;
 
+            // Proposals hold no funds while open: transfers are paid when
+            // accepted and sales when settled, so there is nothing to release
+            current_proposal.set_status(proposal::Proposal_Status::EXPIRED);
+            state.set_proposal(proposal_id, current_proposal)?;
+        }
         Ok(())
     }
 
@@ -1863,6 +3835,8 @@ impl TransactionHandlerDGC {
         payload: payload::RevokeReporterAction,
         mut state: StateDGC,
         signer: &str,
+        timestamp: u64,
+        block_timestamp: u64,
     ) -> Result<(), ApplyError> {
         let record_id = payload.get_record_id();
         let reporter_id = payload.get_reporter_id();
@@ -1888,12 +3862,6 @@ impl TransactionHandlerDGC {
             }
         };
 
-        if owner.get_participant_id() != signer {
-            return Err(ApplyError::InvalidTransaction(format!(
-                "Must be owner to revoke reporters"
-            )));
-        }
-
         if revoke_record.get_field_final() {
             return Err(ApplyError::InvalidTransaction(format!(
                 "Record is final: {}",
@@ -1901,6 +3869,23 @@ impl TransactionHandlerDGC {
             )));
         }
 
+        match state.consent(
+            &[owner.get_participant_id()],
+            signer,
+            organization::Organization_Member_Permission::REVOKE_REPORTER,
+            &PayloadAction::RevokeReporter(payload.clone()),
+            timestamp,
+            block_timestamp,
+        )? {
+            Consent::Given => (),
+            Consent::Pending => return Ok(()),
+            Consent::Refused => {
+                return Err(ApplyError::InvalidTransaction(format!(
+                    "Must be owner to revoke reporters"
+                )))
+            }
+        }
+
         for prop_name in properties {
             let mut prop = match state.get_property(record_id, prop_name) {
                 Ok(Some(prop)) => prop,
@@ -1943,16 +3928,60 @@ impl TransactionHandlerDGC {
         Ok(())
     }
 
+    fn _migrate(
+        &self,
+        payload: payload_v2::MigrateAction,
+        mut state: StateDGC,
+        signer: &str,
+        limits: &Limits,
+    ) -> Result<(), ApplyError> {
+        state.authorize(signer, Permission::Migrate)?;
+
+        let addresses = payload.get_addresses();
+        let cursor = payload.get_cursor() as usize;
+        if cursor > addresses.len() {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Cursor {} is past the end of the {} addresses",
+                cursor,
+                addresses.len()
+            )));
+        }
+        let end = addresses.len().min(cursor + limits.max_migrate_entries);
+
+        let mut migrated = 0;
+        for address in &addresses[cursor..end] {
+            if state.migrate_entry(address)? {
+                migrated += 1;
+            }
+        }
+        info!(
+            "Migrated {} of addresses {} to {} of {}",
+            migrated,
+            cursor,
+            end,
+            addresses.len()
+        );
+
+        let receipt = json!({
+            "migrated": migrated,
+            "next_cursor": end,
+            "done": end == addresses.len(),
+        });
+        state.add_receipt_data(receipt.to_string().as_bytes())
+    }
+
     fn _make_new_reported_value(
         &self,
         reporter_index: u32,
         timestamp: u64,
+        block_timestamp: u64,
         value: &property::PropertyValue,
         property: &property::Property,
     ) -> Result<property::PropertyPage_ReportedValue, ApplyError> {
         let mut reported_value = property::PropertyPage_ReportedValue::new();
         reported_value.set_reporter_index(reporter_index);
         reported_value.set_timestamp(timestamp);
+        reported_value.set_block_timestamp(block_timestamp);
 
         match value.get_data_type() {
             property::PropertySchema_DataType::TYPE_UNSET => {
@@ -1987,7 +4016,7 @@ impl TransactionHandlerDGC {
                 reported_value.set_enum_value(enum_index as u32)
             }
             property::PropertySchema_DataType::STRUCT => {
-                match self._validate_struct_values(
+                match validate_struct_values(
                     &value.struct_values,
                     &property.struct_properties
                 ) {
@@ -2004,55 +4033,11 @@ impl TransactionHandlerDGC {
         };
         Ok(reported_value)
     }
-
-    fn _validate_struct_values(
-        &self,
-        struct_values: &RepeatedField<property::PropertyValue>,
-        schema_values: &RepeatedField<property::PropertySchema>
-    ) -> Result<(), ApplyError> {
-        if struct_values.len() != schema_values.len() {
-            return Err(ApplyError::InvalidTransaction(format!(
-                "Provided struct does not match schema length: {:?} != {:?}",
-                struct_values.len(),
-                schema_values.len(),
-            )))
-        }
-
-        for schema in schema_values.iter() {
-            let value = match struct_values.iter().find(|val| val.name == schema.name) {
-                Some(val) => val,
-                None => return Err(ApplyError::InvalidTransaction(format!(
-                    "Provided struct missing required property from schema: {}",
-                    schema.name,
-                )))
-            };
-
-            if value.data_type != schema.data_type {
-                return Err(ApplyError::InvalidTransaction(format!(
-                    "Struct property \"{}\" must have data type: {:?}",
-                    schema.name,
-                    schema.data_type,
-                )))
-            }
-
-            if schema.data_type == property::PropertySchema_DataType::STRUCT {
-                match self._validate_struct_values(
-                    &value.struct_values,
-                    &schema.struct_properties
-                ) {
-                    Ok(_) => (),
-                    Err(e) => return Err(e),
-                }
-            }
-        }
-
-        Ok(())
-    }
 }
 
 impl TransactionHandler for TransactionHandlerDGC {
     fn family_name(&self) -> String {
-        return self.family_name.clone();
+        return self.family.name().to_string();
     }
 
     fn family_versions(&self) -> Vec<String> {
@@ -2060,7 +4045,7 @@ impl TransactionHandler for TransactionHandlerDGC {
     }
 
     fn namespaces(&self) -> Vec<String> {
-        return self.namespaces.clone();
+        return vec![self.family.namespace().to_string()];
     }
 
     fn apply(
@@ -2068,7 +4053,50 @@ impl TransactionHandler for TransactionHandlerDGC {
         request: &TpProcessRequest,
         context: &mut TransactionContext,
     ) -> Result<(), ApplyError> {
-        let payload = PayloadDGC::new(request.get_payload());
+        let _in_flight = match self.lifecycle {
+            Some(ref lifecycle) => match lifecycle.begin() {
+                Some(in_flight) => Some(in_flight),
+                // An internal error makes the validator retry the transaction
+                // once a processor is available again.
+                None => {
+                    return Err(ApplyError::InternalError(String::from(
+                        "Transaction processor is shutting down",
+                    )))
+                }
+            },
+            None => None,
+        };
+        let scope = logging::TransactionScope::enter(request);
+        let started = Instant::now();
+        let result = match self.metrics {
+            Some(ref metrics) => {
+                let mut context = MeteredContext::new(context, metrics, self.family.namespace());
+                self.apply_payload(request, &mut context)
+            }
+            None => self.apply_payload(request, context),
+        };
+        let duration = started.elapsed();
+
+        logging::log_result(&result, duration);
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_transaction(scope.action().as_ref().map(|a| a.as_str()), &result, duration);
+        }
+        result
+    }
+}
+
+impl TransactionHandlerDGC {
+    fn apply_payload(
+        &self,
+        request: &TpProcessRequest,
+        context: &mut TransactionContext,
+    ) -> Result<(), ApplyError> {
+        let limits = Limits::load(context, &self.limits)?;
+        let payload = PayloadDGC::new(
+            request.get_header().get_family_version(),
+            request.get_payload(),
+            &limits,
+        );
         let payload = match payload {
             Err(e) => return Err(e),
             Ok(payload) => payload,
@@ -2082,47 +4110,167 @@ impl TransactionHandler for TransactionHandlerDGC {
             }
         };
 
-        let signer = request.get_header().get_signer_public_key();
-        let state = StateDGC::new(context);
+        let action = payload.get_action();
+        logging::set_action(action.name(), action.record_id(), action.proposal_id());
 
-        info!(
-            "payload: {:?} {} {} {}",
-            payload.get_action(),
-            payload.get_timestamp(),
-            request.get_header().get_inputs()[0],
-            request.get_header().get_outputs()[0]
-        );
+        let block_timestamp = check_timestamp(context, payload.get_timestamp())?;
+        let legacy = request.get_header().get_family_version() == FAMILY_VERSION
+            && within_legacy_rules(context)?;
 
-        match payload.get_action() {
-            Action::CreateParticipant(participant_payload) => {
-                self._create_participant(participant_payload, state, signer, payload.get_timestamp())?
+        let signing_key = request.get_header().get_signer_public_key();
+        let signer = &StateDGC::new(context, self.family.namespace()).resolve_signer(signing_key)?;
+        let mut state = StateDGC::new(context, self.family.namespace());
+
+        // An inactive participant can only have its status changed
+        match action {
+            PayloadAction::SetParticipantStatus(_) => (),
+            _ => {
+                if let Some(participant) = state.get_participant(signer)? {
+                    check_active(&participant, "Participant")?;
+                }
             }
-            Action::CreateRecord(record_payload) => {
-                self._create_record(record_payload, state, signer, payload.get_timestamp())?
+        }
+
+        match action {
+            PayloadAction::CreateParticipant(participant_payload) => {
+                self._create_participant(participant_payload, state, signer, payload.get_timestamp())?
             }
-            Action::FinalizeRecord(finalize_payload) => {
-                self._finalize_record(finalize_payload, state, signer)?
+            PayloadAction::CreateRecord(record_payload) => {
+                self._create_record(
+                    record_payload,
+                    state,
+                    signer,
+                    payload.get_timestamp(),
+                    block_timestamp,
+                    &limits,
+                )?
             }
-            Action::CreateTable(table_payload) => {
+            PayloadAction::FinalizeRecord(finalize_payload) => self._finalize_record(
+                finalize_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::CreateTable(table_payload) => {
                 self._create_table(table_payload, state, signer)?
             }
-            Action::UpdateProperties(update_properties_payload) => self._update_properties(
+            PayloadAction::UpdateProperties(update_properties_payload) => self._update_properties(
                 update_properties_payload,
                 state,
                 signer,
                 payload.get_timestamp(),
+                block_timestamp,
             )?,
-            Action::CreateProposal(proposal_payload) => {
-                self._create_proposal(proposal_payload, state, signer, payload.get_timestamp())?
-            }
-            Action::AnswerProposal(answer_proposal_payload) => self._answer_proposal(
+            PayloadAction::CreateProposal(proposal_payload) => self._create_proposal(
+                proposal_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+                legacy,
+            )?,
+            PayloadAction::AnswerProposal(answer_proposal_payload) => self._answer_proposal(
                 answer_proposal_payload,
                 state,
                 signer,
                 payload.get_timestamp(),
+                block_timestamp,
+                legacy,
+            )?,
+            PayloadAction::RevokeReporter(revoke_reporter_payload) => self._revoke_reporter(
+                revoke_reporter_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::SettleProposal(settle_proposal_payload) => self._answer_proposal(
+                settlement_answer(&settle_proposal_payload),
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+                false,
+            )?,
+            PayloadAction::Migrate(migrate_payload) => {
+                self._migrate(migrate_payload, state, signer, &limits)?
+            }
+            PayloadAction::RotateKey(rotate_key_payload) => self._rotate_key(
+                rotate_key_payload,
+                state,
+                signer,
+                signing_key,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::SetParticipantStatus(status_payload) => self._set_participant_status(
+                status_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::SetRoles(roles_payload) => {
+                self._set_roles(roles_payload, state, signer, payload.get_timestamp())?
+            }
+            PayloadAction::CreateOrganization(organization_payload) => self._create_organization(
+                organization_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+            )?,
+            PayloadAction::SetOrganizationMember(member_payload) => {
+                self._set_organization_member(member_payload, state, signer)?
+            }
+            PayloadAction::CreateMultisigPolicy(policy_payload) => self._create_multisig_policy(
+                policy_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+            )?,
+            PayloadAction::CounterProposal(counter_payload) => self._counter_proposal(
+                counter_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
             )?,
-            Action::RevokeReporter(revoke_reporter_payload) => {
-                self._revoke_reporter(revoke_reporter_payload, state, signer)?
+            PayloadAction::SetCreditLimit(limit_payload) => self._set_credit_limit(
+                limit_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::RepayCredit(repay_payload) => self._repay_credit(
+                repay_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::WithdrawApproval(withdraw_payload) => self._withdraw_approval(
+                withdraw_payload,
+                state,
+                signer,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::ExpireProposals(expire_payload) => self._expire_proposals(
+                expire_payload,
+                state,
+                payload.get_timestamp(),
+                block_timestamp,
+            )?,
+            PayloadAction::UpdateParticipant(update_participant_payload) => {
+                self._update_participant(
+                    update_participant_payload,
+                    state,
+                    signer,
+                    payload.get_timestamp(),
+                    block_timestamp,
+                )?
             }
         }
         Ok(())
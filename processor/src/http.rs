@@ -0,0 +1,67 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// Serves plain text GET requests on `address` from a background thread.
+/// `route` is given the request path and returns the status line and body.
+pub fn serve<F>(address: &str, route: F) -> io::Result<thread::JoinHandle<()>>
+where
+    F: Fn(&str) -> (&'static str, String) + Send + 'static,
+{
+    let listener = TcpListener::bind(address)?;
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = respond(stream, &route) {
+                        debug!("HTTP request failed: {}", err);
+                    }
+                }
+                Err(err) => warn!("Unable to accept HTTP connection: {}", err),
+            }
+        }
+    }))
+}
+
+fn respond<F>(stream: TcpStream, route: &F) -> io::Result<()>
+where
+    F: Fn(&str) -> (&'static str, String),
+{
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Read the rest of the headers so the client sees a complete exchange.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => route(path),
+        _ => (
+            "405 Method Not Allowed",
+            String::from("Method Not Allowed\n"),
+        ),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+pub fn not_found() -> (&'static str, String) {
+    ("404 Not Found", String::from("Not Found\n"))
+}
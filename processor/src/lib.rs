@@ -0,0 +1,38 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+extern crate crypto;
+extern crate libc;
+#[macro_use]
+extern crate log;
+extern crate log4rs;
+extern crate protobuf;
+extern crate rand;
+extern crate rustc_serialize;
+extern crate sawtooth_sdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
+
+pub mod addressing;
+pub mod block_time;
+pub mod config;
+pub mod context;
+pub mod decode;
+pub mod handler;
+pub mod http;
+pub mod lifecycle;
+pub mod limits;
+pub mod logging;
+pub mod messages;
+pub mod metrics;
+pub mod migrate;
+pub mod roles;
+pub mod schema;
+pub mod settings;
+pub mod snapshot;
+pub mod transaction;
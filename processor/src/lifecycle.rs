@@ -0,0 +1,219 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::{self, File};
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use http;
+
+static SIGNALLED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_signal: libc::c_int) {
+    SIGNALLED.store(true, Ordering::SeqCst);
+}
+
+/// Records SIGTERM and SIGINT instead of exiting, so `signalled` can start
+/// an orderly shutdown.
+pub fn install_signal_handlers() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+/// Whether SIGTERM or SIGINT has been received.
+pub fn signalled() -> bool {
+    SIGNALLED.load(Ordering::SeqCst)
+}
+
+/// Whether the processor is connected, how many transactions it is applying,
+/// and whether it is shutting down. Shared by the handler, the connection
+/// loop and the health probe.
+#[derive(Debug, Default)]
+pub struct Lifecycle {
+    connected: AtomicBool,
+    stopping: AtomicBool,
+    in_flight: AtomicUsize,
+    ready_file: Option<String>,
+}
+
+/// Counts a transaction as in flight until it is dropped.
+pub struct InFlight<'a> {
+    lifecycle: &'a Lifecycle,
+}
+
+impl<'a> Drop for InFlight<'a> {
+    fn drop(&mut self) {
+        self.lifecycle.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Lifecycle {
+    /// `ready_file` is created while the processor is ready, for probes which
+    /// check a file rather than HTTP.
+    pub fn new(ready_file: Option<String>) -> Lifecycle {
+        Lifecycle {
+            ready_file: ready_file,
+            ..Lifecycle::default()
+        }
+    }
+
+    /// Starts a transaction, or returns None once shutdown has begun.
+    pub fn begin(&self) -> Option<InFlight> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let in_flight = InFlight { lifecycle: self };
+        if self.is_stopping() {
+            return None;
+        }
+        Some(in_flight)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.connected.load(Ordering::SeqCst) && !self.is_stopping()
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::SeqCst);
+        self.update_ready_file();
+    }
+
+    /// Stops new transactions from being applied.
+    pub fn request_stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        self.update_ready_file();
+    }
+
+    /// Waits for the transactions in flight to finish. Returns false if some
+    /// were still running after `timeout`.
+    pub fn drain(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        while self.in_flight() > 0 {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    fn update_ready_file(&self) {
+        if let Some(ref path) = self.ready_file {
+            let result = if self.is_ready() {
+                File::create(path).map(|_| ())
+            } else {
+                match fs::remove_file(path) {
+                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                    result => result,
+                }
+            };
+            if let Err(err) = result {
+                warn!("Unable to update ready file {}: {}", path, err);
+            }
+        }
+    }
+}
+
+/// Serves `/healthz`, which answers while the process is running, and
+/// `/readyz`, which answers 200 only while connected and not shutting down.
+pub fn serve_health(
+    address: &str,
+    lifecycle: Arc<Lifecycle>,
+) -> io::Result<thread::JoinHandle<()>> {
+    let handle = http::serve(address, move |path| match path {
+        "/healthz" => ("200 OK", String::from("ok\n")),
+        "/readyz" if lifecycle.is_ready() => ("200 OK", String::from("ready\n")),
+        "/readyz" => ("503 Service Unavailable", String::from("not ready\n")),
+        _ => http::not_found(),
+    })?;
+    info!(
+        "Serving health probes on http://{}/healthz and /readyz",
+        address
+    );
+    Ok(handle)
+}
+
+/// Exponentially growing delays between attempts to reach the validator.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Backoff {
+        Backoff {
+            initial: initial,
+            max: max,
+            next: initial,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+/// Retries a TCP connection to the validator until it succeeds, waiting
+/// longer after each failure. Returns false if shutdown began first.
+pub fn wait_for_validator(endpoint: &str, backoff: &mut Backoff, lifecycle: &Lifecycle) -> bool {
+    let address = endpoint.trim_left_matches("tcp://");
+    loop {
+        if lifecycle.is_stopping() {
+            return false;
+        }
+        let reachable =
+            address
+                .to_socket_addrs()
+                .and_then(|mut addresses| match addresses.next() {
+                    Some(socket) => TcpStream::connect_timeout(&socket, Duration::from_secs(5)),
+                    None => Err(io::Error::new(io::ErrorKind::NotFound, "no address")),
+                });
+        match reachable {
+            Ok(_) => {
+                backoff.reset();
+                return true;
+            }
+            Err(err) => {
+                let delay = backoff.next_delay();
+                warn!(
+                    "Unable to reach the validator at {}: {}. Retrying in {}ms",
+                    endpoint,
+                    err,
+                    delay.as_secs() * 1000 + u64::from(delay.subsec_millis())
+                );
+                sleep_unless_stopping(delay, lifecycle);
+            }
+        }
+    }
+}
+
+/// Sleeps for `duration`, waking early if shutdown begins.
+pub fn sleep_unless_stopping(duration: Duration, lifecycle: &Lifecycle) {
+    let started = Instant::now();
+    while started.elapsed() < duration && !lifecycle.is_stopping() && !signalled() {
+        thread::sleep(Duration::from_millis(100));
+    }
+}
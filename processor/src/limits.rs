@@ -0,0 +1,336 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use sawtooth_sdk::processor::handler::ApplyError;
+use sawtooth_sdk::processor::handler::TransactionContext;
+
+use addressing::make_setting_address;
+use messages::*;
+use settings::get_setting;
+use transaction::PayloadAction;
+
+pub const MAX_PAYLOAD_BYTES_KEY: &str = "dgc.limits.max_payload_bytes";
+pub const MAX_PROPERTIES_KEY: &str = "dgc.limits.max_properties";
+pub const MAX_STRUCT_DEPTH_KEY: &str = "dgc.limits.max_struct_depth";
+pub const MAX_STRUCT_WIDTH_KEY: &str = "dgc.limits.max_struct_width";
+pub const MAX_STRING_LENGTH_KEY: &str = "dgc.limits.max_string_length";
+pub const MAX_BYTES_LENGTH_KEY: &str = "dgc.limits.max_bytes_length";
+pub const MAX_ENUM_OPTIONS_KEY: &str = "dgc.limits.max_enum_options";
+pub const MAX_MIGRATE_ENTRIES_KEY: &str = "dgc.limits.max_migrate_entries";
+
+/// Bounds on the size of what a single transaction can write. Each limit can
+/// be changed with the on-chain setting of the same name; unset or invalid
+/// settings fall back to the defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    pub max_payload_bytes: usize,
+    /// Properties per action, and per table.
+    pub max_properties: usize,
+    /// How deep STRUCT properties can nest, counting the outermost as 1.
+    pub max_struct_depth: usize,
+    /// Fields per STRUCT.
+    pub max_struct_width: usize,
+    /// Length in bytes of names, ids and STRING values.
+    pub max_string_length: usize,
+    pub max_bytes_length: usize,
+    pub max_enum_options: usize,
+    /// Addresses rewritten by one MIGRATE transaction.
+    pub max_migrate_entries: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_payload_bytes: 64 * 1024,
+            max_properties: 64,
+            max_struct_depth: 4,
+            max_struct_width: 32,
+            max_string_length: 1024,
+            max_bytes_length: 16 * 1024,
+            max_enum_options: 64,
+            max_migrate_entries: 16,
+        }
+    }
+}
+
+impl Limits {
+    /// The setting keys read by `load`.
+    pub fn setting_keys() -> Vec<&'static str> {
+        vec![
+            MAX_PAYLOAD_BYTES_KEY,
+            MAX_PROPERTIES_KEY,
+            MAX_STRUCT_DEPTH_KEY,
+            MAX_STRUCT_WIDTH_KEY,
+            MAX_STRING_LENGTH_KEY,
+            MAX_BYTES_LENGTH_KEY,
+            MAX_ENUM_OPTIONS_KEY,
+            MAX_MIGRATE_ENTRIES_KEY,
+        ]
+    }
+
+    /// The addresses transactions must list as inputs so `load` can read them.
+    pub fn setting_addresses() -> Vec<String> {
+        Limits::setting_keys()
+            .into_iter()
+            .map(make_setting_address)
+            .collect()
+    }
+
+    /// Reads the limit settings, using `defaults` for those that are unset.
+    pub fn load(context: &mut TransactionContext, defaults: &Limits) -> Result<Limits, ApplyError> {
+        let mut limits = defaults.clone();
+        {
+            let fields = vec![
+                (MAX_PAYLOAD_BYTES_KEY, &mut limits.max_payload_bytes),
+                (MAX_PROPERTIES_KEY, &mut limits.max_properties),
+                (MAX_STRUCT_DEPTH_KEY, &mut limits.max_struct_depth),
+                (MAX_STRUCT_WIDTH_KEY, &mut limits.max_struct_width),
+                (MAX_STRING_LENGTH_KEY, &mut limits.max_string_length),
+                (MAX_BYTES_LENGTH_KEY, &mut limits.max_bytes_length),
+                (MAX_ENUM_OPTIONS_KEY, &mut limits.max_enum_options),
+                (MAX_MIGRATE_ENTRIES_KEY, &mut limits.max_migrate_entries),
+            ];
+            for (key, field) in fields {
+                if let Some(value) = get_setting(context, key)? {
+                    match value.parse::<usize>() {
+                        Ok(limit) if limit > 0 => *field = limit,
+                        _ => warn!("Ignoring invalid setting {}: {}", key, value),
+                    }
+                }
+            }
+        }
+        Ok(limits)
+    }
+
+    pub fn check_payload_size(&self, size: usize) -> Result<(), ApplyError> {
+        if size > self.max_payload_bytes {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Payload is {} bytes, more than the limit of {}",
+                size, self.max_payload_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_property_count(&self, count: usize) -> Result<(), ApplyError> {
+        if count > self.max_properties {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "{} properties given, more than the limit of {}",
+                count, self.max_properties
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_string(&self, field: &str, value: &str) -> Result<(), ApplyError> {
+        if value.len() > self.max_string_length {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "{} is {} bytes long, more than the limit of {}",
+                field,
+                value.len(),
+                self.max_string_length
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks every field of an action which can grow without bound.
+    pub fn check_action(&self, action: &PayloadAction) -> Result<(), ApplyError> {
+        match *action {
+            PayloadAction::CreateParticipant(ref action) => {
+                self.check_string("Participant name", action.get_name())?;
+                self.check_string("Participant email", action.get_email())
+            }
+            PayloadAction::UpdateParticipant(ref action) => {
+                self.check_string("Participant name", action.get_name())?;
+                self.check_string("Participant email", action.get_email())?;
+                self.check_string("Recovery key", action.get_recovery_key())
+            }
+            PayloadAction::RotateKey(ref action) => {
+                self.check_string("Participant id", action.get_participant_id())?;
+                self.check_string("New public key", action.get_new_public_key())?;
+                self.check_string("New key signature", action.get_new_key_signature())
+            }
+            PayloadAction::SetRoles(ref action) => self.check_string("Public key", action.get_public_key()),
+            PayloadAction::CreateOrganization(ref action) => {
+                self.check_string("Organization id", action.get_organization_id())?;
+                self.check_string("Organization name", action.get_name())
+            }
+            PayloadAction::SetOrganizationMember(ref action) => {
+                self.check_string("Organization id", action.get_organization_id())?;
+                self.check_string("Participant id", action.get_participant_id())
+            }
+            PayloadAction::CreateMultisigPolicy(ref action) => {
+                self.check_string("Policy id", action.get_policy_id())?;
+                for signer in action.get_signers() {
+                    self.check_string("Signer", signer)?;
+                }
+                Ok(())
+            }
+            PayloadAction::ExpireProposals(ref action) => {
+                for proposal_id in action.get_proposal_ids() {
+                    self.check_string("Proposal id", proposal_id)?;
+                }
+                Ok(())
+            }
+            PayloadAction::SetCreditLimit(ref action) => {
+                self.check_string("Participant id", action.get_participant_id())
+            }
+            PayloadAction::RepayCredit(_) => Ok(()),
+            PayloadAction::WithdrawApproval(ref action) => {
+                self.check_string("Approval id", action.get_approval_id())
+            }
+            PayloadAction::SetParticipantStatus(ref action) => {
+                self.check_string("Participant id", action.get_participant_id())?;
+                self.check_string("Status reason", action.get_reason())
+            }
+            PayloadAction::CreateRecord(ref action) => {
+                self.check_string("Record id", action.get_record_id())?;
+                self.check_string("Table name", action.get_table())?;
+                self.check_values(action.get_properties())
+            }
+            PayloadAction::FinalizeRecord(ref action) => {
+                self.check_string("Record id", action.get_record_id())
+            }
+            PayloadAction::CreateTable(ref action) => {
+                self.check_string("Table name", action.get_name())?;
+                self.check_property_count(action.get_properties().len())?;
+                for schema in action.get_properties() {
+                    self.check_schema(schema, 1)?;
+                }
+                Ok(())
+            }
+            PayloadAction::UpdateProperties(ref action) => {
+                self.check_string("Record id", action.get_record_id())?;
+                self.check_values(action.get_properties())
+            }
+            PayloadAction::CreateProposal(ref action) => {
+                self.check_string("Proposal id", action.get_proposal_id())?;
+                self.check_string("Record id", action.get_record_id())?;
+                self.check_string("Proposal terms", action.get_terms())?;
+                self.check_names(action.get_properties())
+            }
+            PayloadAction::CounterProposal(ref action) => {
+                self.check_string("Proposal id", action.get_proposal_id())?;
+                self.check_string("Proposal id", action.get_counter().get_proposal_id())?;
+                self.check_string("Proposal terms", action.get_counter().get_terms())?;
+                self.check_names(action.get_counter().get_properties())
+            }
+            PayloadAction::AnswerProposal(ref action) => {
+                self.check_string("Proposal id", action.get_proposal_id())?;
+                self.check_exchange_count(action.get_exchanges().len())
+            }
+            PayloadAction::SettleProposal(ref action) => {
+                self.check_string("Proposal id", action.get_proposal_id())?;
+                self.check_exchange_count(action.get_exchanges().len())
+            }
+            PayloadAction::RevokeReporter(ref action) => {
+                self.check_string("Record id", action.get_record_id())?;
+                self.check_names(action.get_properties())
+            }
+            // The addresses are bounded by the payload size, and only
+            // max_migrate_entries of them are read.
+            PayloadAction::Migrate(_) => Ok(()),
+        }
+    }
+
+    fn check_exchange_count(&self, count: usize) -> Result<(), ApplyError> {
+        if count > self.max_properties {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "{} exchanges given, more than the limit of {}",
+                count, self.max_properties
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_names(&self, names: &[String]) -> Result<(), ApplyError> {
+        self.check_property_count(names.len())?;
+        for name in names {
+            self.check_string("Property name", name)?;
+        }
+        Ok(())
+    }
+
+    fn check_values(&self, values: &[property::PropertyValue]) -> Result<(), ApplyError> {
+        self.check_property_count(values.len())?;
+        for value in values {
+            self.check_value(value, 1)?;
+        }
+        Ok(())
+    }
+
+    fn check_schema(
+        &self,
+        schema: &property::PropertySchema,
+        depth: usize,
+    ) -> Result<(), ApplyError> {
+        self.check_string("Property name", schema.get_name())?;
+        self.check_string("Property unit", schema.get_unit())?;
+
+        if schema.get_enum_options().len() > self.max_enum_options {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Property \"{}\" has {} enum options, more than the limit of {}",
+                schema.get_name(),
+                schema.get_enum_options().len(),
+                self.max_enum_options
+            )));
+        }
+        for option in schema.get_enum_options() {
+            self.check_string("Enum option", option)?;
+        }
+
+        if schema.get_data_type() == property::PropertySchema_DataType::STRUCT {
+            self.check_struct(schema.get_name(), schema.get_struct_properties().len(), depth)?;
+            for field in schema.get_struct_properties() {
+                self.check_schema(field, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_value(&self, value: &property::PropertyValue, depth: usize) -> Result<(), ApplyError> {
+        self.check_string("Property name", value.get_name())?;
+
+        match value.get_data_type() {
+            property::PropertySchema_DataType::STRING => {
+                self.check_string(&format!("Property \"{}\"", value.get_name()), value.get_string_value())?
+            }
+            property::PropertySchema_DataType::BYTES => {
+                if value.get_bytes_value().len() > self.max_bytes_length {
+                    return Err(ApplyError::InvalidTransaction(format!(
+                        "Property \"{}\" is {} bytes long, more than the limit of {}",
+                        value.get_name(),
+                        value.get_bytes_value().len(),
+                        self.max_bytes_length
+                    )));
+                }
+            }
+            property::PropertySchema_DataType::STRUCT => {
+                self.check_struct(value.get_name(), value.get_struct_values().len(), depth)?;
+                for field in value.get_struct_values() {
+                    self.check_value(field, depth + 1)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn check_struct(&self, name: &str, width: usize, depth: usize) -> Result<(), ApplyError> {
+        if depth > self.max_struct_depth {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Struct \"{}\" is nested {} deep, more than the limit of {}",
+                name, depth, self.max_struct_depth
+            )));
+        }
+        if width > self.max_struct_width {
+            return Err(ApplyError::InvalidTransaction(format!(
+                "Struct \"{}\" has {} fields, more than the limit of {}",
+                name, width, self.max_struct_width
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::LogRecord;
+use log4rs::encode::pattern::PatternEncoder;
+use log4rs::encode::{Encode, Write};
+use serde_json;
+use serde_json::Value;
+
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use config::LogFormat;
+
+const TEXT_PATTERN: &str = "{h({l:5.5})} | {({M}:{L}):20.20} | {m}";
+
+/// What is known about the transaction being applied on this thread. Every
+/// line logged while it is applied carries these fields.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransactionFields {
+    pub signature: String,
+    pub signer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposal_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
+}
+
+thread_local! {
+    static TRANSACTION: RefCell<Option<TransactionFields>> = RefCell::new(None);
+}
+
+/// Sets the transaction fields of this thread until it is dropped.
+pub struct TransactionScope;
+
+impl TransactionScope {
+    /// Takes the signature and signer from the request. Missing header fields
+    /// are logged as empty strings.
+    pub fn enter(request: &TpProcessRequest) -> TransactionScope {
+        let fields = TransactionFields {
+            signature: request.get_signature().to_string(),
+            signer: request.get_header().get_signer_public_key().to_string(),
+            ..TransactionFields::default()
+        };
+        TRANSACTION.with(|transaction| *transaction.borrow_mut() = Some(fields));
+        TransactionScope
+    }
+
+    /// The action set with `set_action`, if the payload got that far.
+    pub fn action(&self) -> Option<String> {
+        current().and_then(|fields| fields.action)
+    }
+}
+
+impl Drop for TransactionScope {
+    fn drop(&mut self) {
+        TRANSACTION.with(|transaction| *transaction.borrow_mut() = None);
+    }
+}
+
+/// Records the decoded action of the current transaction.
+pub fn set_action(action: &str, record_id: Option<String>, proposal_id: Option<String>) {
+    update(|fields| {
+        fields.action = Some(action.to_string());
+        fields.record_id = record_id;
+        fields.proposal_id = proposal_id;
+    });
+}
+
+/// Logs the outcome of the current transaction: info when it was applied or
+/// rejected as invalid, error when it failed internally.
+pub fn log_result(result: &Result<(), ApplyError>, duration: Duration) {
+    let duration_ms =
+        duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0;
+    let (outcome, error) = match *result {
+        Ok(()) => ("applied", None),
+        Err(ApplyError::InvalidTransaction(ref msg)) => ("invalid", Some(msg.clone())),
+        Err(ApplyError::InternalError(ref msg)) => ("internal_error", Some(msg.clone())),
+    };
+    update(|fields| {
+        fields.result = Some(outcome.to_string());
+        fields.error = error.clone();
+        fields.duration_ms = Some(duration_ms);
+    });
+
+    match error {
+        None => info!("Transaction applied"),
+        Some(ref msg) if outcome == "invalid" => info!("Transaction rejected: {}", msg),
+        Some(ref msg) => error!("Transaction failed: {}", msg),
+    }
+}
+
+fn update<F: FnOnce(&mut TransactionFields)>(f: F) {
+    TRANSACTION.with(|transaction| {
+        if let Some(ref mut fields) = *transaction.borrow_mut() {
+            f(fields);
+        }
+    });
+}
+
+fn current() -> Option<TransactionFields> {
+    TRANSACTION.with(|transaction| transaction.borrow().clone())
+}
+
+/// The encoder for log lines in the given format.
+pub fn encoder(format: LogFormat) -> Box<Encode> {
+    match format {
+        LogFormat::Text => Box::new(TextEncoder {
+            pattern: PatternEncoder::new(TEXT_PATTERN),
+        }),
+        LogFormat::Json => Box::new(JsonEncoder),
+    }
+}
+
+/// The console pattern, followed by the transaction fields as `key=value`.
+#[derive(Debug)]
+pub struct TextEncoder {
+    pattern: PatternEncoder,
+}
+
+impl Encode for TextEncoder {
+    fn encode(&self, w: &mut Write, record: &LogRecord) -> Result<(), Box<StdError + Sync + Send>> {
+        self.pattern.encode(w, record)?;
+        if let Some(fields) = current() {
+            if let Value::Object(map) = serde_json::to_value(&fields)? {
+                for (key, value) in map {
+                    match value {
+                        Value::String(s) => write!(w, " {}={}", key, s)?,
+                        other => write!(w, " {}={}", key, other)?,
+                    }
+                }
+            }
+        }
+        writeln!(w)?;
+        Ok(())
+    }
+}
+
+/// One JSON object per line, with the transaction fields under
+/// `transaction`.
+#[derive(Debug)]
+pub struct JsonEncoder;
+
+impl Encode for JsonEncoder {
+    fn encode(&self, w: &mut Write, record: &LogRecord) -> Result<(), Box<StdError + Sync + Send>> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs() * 1000 + u64::from(since.subsec_nanos()) / 1_000_000)
+            .unwrap_or(0);
+        let mut line = json!({
+            "time_ms": time,
+            "level": record.level().to_string(),
+            "target": record.target(),
+            "module": record.location().module_path(),
+            "line": record.location().line(),
+            "message": format!("{}", record.args()),
+        });
+        if let Some(fields) = current() {
+            line["transaction"] = serde_json::to_value(&fields)?;
+        }
+        writeln!(w, "{}", line)?;
+        Ok(())
+    }
+}
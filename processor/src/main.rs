@@ -3,74 +3,298 @@
 
 #[macro_use]
 extern crate clap;
-extern crate crypto;
+extern crate dgc_rest_api_tp;
 extern crate log4rs;
 #[macro_use]
 extern crate log;
-extern crate protobuf;
-extern crate rustc_serialize;
 extern crate sawtooth_sdk;
 
-mod handler;
-mod addressing;
-mod messages;
-
 use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use clap::{App, Arg, ArgMatches};
 use log::LogLevelFilter;
 use log4rs::append::console::ConsoleAppender;
+use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
+use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
+use log4rs::append::rolling_file::RollingFileAppender;
 use log4rs::config::{Appender, Config, Root};
-use log4rs::encode::pattern::PatternEncoder;
 
 use sawtooth_sdk::processor::TransactionProcessor;
 
-use handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::config::{
+    ConfigError, LogConfig, LogFormat, ProcessorConfig, ReconnectConfig,
+};
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::lifecycle::{self, Backoff, Lifecycle};
+use dgc_rest_api_tp::logging;
+use dgc_rest_api_tp::metrics::{self, Metrics};
 
 fn main() {
-    let matches = clap_app!(intkey =>
-        (version: crate_version!())
-        (about: "dgc-REST-api Transaction Processor (Rust)")
-        (@arg connect: -C --connect +takes_value
-         "connection endpoint for validator")
-        (@arg verbose: -v --verbose +multiple
-         "increase output verbosity"))
+    let matches = App::new("dgc-REST-api-tp")
+        .version(crate_version!())
+        .about("dgc-REST-api Transaction Processor (Rust)")
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .takes_value(true)
+                .help("TOML configuration file"),
+        )
+        .arg(
+            Arg::with_name("connect")
+                .short("C")
+                .long("connect")
+                .takes_value(true)
+                .help("connection endpoint for validator"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .multiple(true)
+                .help("increase output verbosity"),
+        )
+        .arg(
+            Arg::with_name("metrics_address")
+                .long("metrics")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .help("serve Prometheus metrics at http://ADDRESS/metrics, such as 127.0.0.1:9102"),
+        )
+        .arg(
+            Arg::with_name("health_address")
+                .long("health")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .help("serve /healthz and /readyz probes at http://ADDRESS"),
+        )
+        .arg(
+            Arg::with_name("ready_file")
+                .long("ready-file")
+                .takes_value(true)
+                .help("file which exists while the processor is ready"),
+        )
+        .arg(
+            Arg::with_name("log_format")
+                .long("log-format")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .help("format of log lines"),
+        )
+        .arg(
+            Arg::with_name("log_file")
+                .long("log-file")
+                .takes_value(true)
+                .help("also log to this file, rolling it over as it grows"),
+        )
+        .arg(
+            Arg::with_name("family_name")
+                .long("family-name")
+                .takes_value(true)
+                .help("transaction family to register"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .takes_value(true)
+                .help("address prefix of the transaction family"),
+        )
+        .arg(
+            Arg::with_name("print_config")
+                .long("print-config")
+                .help("print the effective configuration and exit"),
+        )
         .get_matches();
 
-    let endpoint = matches
-        .value_of("connect")
-        .unwrap_or("tcp://localhost:4004");
+    let config = match load_config(&matches) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Invalid configuration: {}", err);
+            process::exit(1);
+        }
+    };
 
-    let console_log_level;
-    match matches.occurrences_of("verbose") {
-        0 => console_log_level = LogLevelFilter::Warn,
-        1 => console_log_level = LogLevelFilter::Info,
-        2 => console_log_level = LogLevelFilter::Debug,
-        3 | _ => console_log_level = LogLevelFilter::Trace,
+    if matches.is_present("print_config") {
+        match config.to_toml() {
+            Ok(toml) => print!("{}", toml),
+            Err(err) => {
+                eprintln!("Unable to print configuration: {}", err);
+                process::exit(1);
+            }
+        }
+        return;
     }
 
-    let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "{h({l:5.5})} | {({M}:{L}):20.20} | {m}{n}",
-        )))
-        .build();
+    let log_level = match init_logging(&config.log) {
+        Ok(level) => level,
+        Err(err) => {
+            eprintln!("Unable to start logging: {}", err);
+            process::exit(1);
+        }
+    };
+
+    info!("Logging level: {}", log_level);
 
-    let config = match Config::builder()
-        .appender(Appender::builder().build("stdout", Box::new(stdout)))
-        .build(Root::builder().appender("stdout").build(console_log_level))
+    let lifecycle = Arc::new(Lifecycle::new(config.ready_file.clone()));
+    let mut handler = TransactionHandlerDGC::with_limits(config.limits.clone());
+    handler.set_lifecycle(lifecycle.clone());
+    match config.family() {
+        Ok(family) => {
+            info!(
+                "Family {} stored under namespace {}",
+                family.name(),
+                family.namespace()
+            );
+            handler.set_family(family);
+        }
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(ref address) = config.metrics_address {
+        let registry = Arc::new(Metrics::new());
+        if let Err(err) = metrics::serve(address, registry.clone()) {
+            error!("Unable to serve metrics on {}: {}", address, err);
+            process::exit(1);
+        }
+        handler.set_metrics(registry);
+    }
+    if let Some(ref address) = config.health_address {
+        if let Err(err) = lifecycle::serve_health(address, lifecycle.clone()) {
+            error!("Unable to serve health probes on {}: {}", address, err);
+            process::exit(1);
+        }
+    }
+
+    lifecycle::install_signal_handlers();
     {
-        Ok(x) => x,
-        Err(_) => process::exit(1),
-    };
+        let lifecycle = lifecycle.clone();
+        let endpoint = config.connect.clone();
+        let reconnect = config.reconnect.clone();
+        thread::spawn(move || run(&endpoint, &handler, &reconnect, &lifecycle));
+    }
+
+    while !lifecycle::signalled() {
+        thread::sleep(Duration::from_millis(100));
+    }
+    info!(
+        "Shutting down with {} transactions in flight",
+        lifecycle.in_flight()
+    );
+    lifecycle.request_stop();
+    if !lifecycle.drain(Duration::from_secs(config.shutdown_timeout_seconds)) {
+        warn!(
+            "Stopping with {} transactions still in flight",
+            lifecycle.in_flight()
+        );
+    }
+    // Returning closes the connection, which unregisters the processor from
+    // the validator.
+}
+
+/// Processes transactions from the validator at `endpoint`, connecting again
+/// with backoff whenever the connection ends, until shutdown begins.
+fn run(
+    endpoint: &str,
+    handler: &TransactionHandlerDGC,
+    reconnect: &ReconnectConfig,
+    lifecycle: &Lifecycle,
+) {
+    let mut backoff = Backoff::new(
+        Duration::from_millis(reconnect.initial_delay_ms),
+        Duration::from_millis(reconnect.max_delay_ms),
+    );
+    while lifecycle::wait_for_validator(endpoint, &mut backoff, lifecycle) {
+        let mut processor = TransactionProcessor::new(endpoint);
+        processor.add_handler(handler);
+        lifecycle.set_connected(true);
+        info!("Connected to the validator at {}", endpoint);
+
+        processor.start();
 
-    match log4rs::init_config(config) {
-        Ok(_) => (),
-        Err(_) => process::exit(1),
+        lifecycle.set_connected(false);
+        if lifecycle.is_stopping() || lifecycle::signalled() {
+            break;
+        }
+        warn!("Lost the connection to the validator at {}", endpoint);
+        lifecycle::sleep_unless_stopping(backoff.next_delay(), lifecycle);
     }
+}
 
-    let handler = TransactionHandlerDGC::new();
-    let mut processor = TransactionProcessor::new(endpoint);
+/// The configuration file, overridden by the environment and then by the
+/// command line.
+fn load_config(matches: &ArgMatches) -> Result<ProcessorConfig, ConfigError> {
+    let mut config = ProcessorConfig::load(matches.value_of("config"))?;
+    config.apply_env()?;
 
-    info!("Console logging level: {}", console_log_level);
+    if let Some(connect) = matches.value_of("connect") {
+        config.connect = connect.to_string();
+    }
+    if let Some(address) = matches.value_of("metrics_address") {
+        config.metrics_address = Some(address.to_string());
+    }
+    match matches.occurrences_of("verbose") {
+        0 => (),
+        1 => config.log.level = String::from("info"),
+        2 => config.log.level = String::from("debug"),
+        3 | _ => config.log.level = String::from("trace"),
+    }
+    if let Some(address) = matches.value_of("health_address") {
+        config.health_address = Some(address.to_string());
+    }
+    if let Some(path) = matches.value_of("ready_file") {
+        config.ready_file = Some(path.to_string());
+    }
+    if let Some(format) = matches.value_of("log_format") {
+        config.log.format = LogFormat::parse(format)?;
+    }
+    if let Some(file) = matches.value_of("log_file") {
+        config.log.file = Some(file.to_string());
+    }
+    if let Some(name) = matches.value_of("family_name") {
+        config.family.name = name.to_string();
+    }
+    if let Some(namespace) = matches.value_of("namespace") {
+        config.family.namespace = Some(namespace.to_string());
+    }
+
+    config.validate()?;
+    Ok(config)
+}
+
+fn init_logging(log: &LogConfig) -> Result<LogLevelFilter, String> {
+    let level = log.level_filter().map_err(|err| format!("{}", err))?;
+
+    let stdout = ConsoleAppender::builder()
+        .encoder(logging::encoder(log.format))
+        .build();
+    let mut builder =
+        Config::builder().appender(Appender::builder().build("stdout", Box::new(stdout)));
+    let mut root = Root::builder().appender("stdout");
+
+    if let Some(ref path) = log.file {
+        let roller = FixedWindowRoller::builder()
+            .build(&format!("{}.{{}}", path), log.max_files)
+            .map_err(|err| format!("{}", err))?;
+        let policy = CompoundPolicy::new(
+            Box::new(SizeTrigger::new(log.max_file_size)),
+            Box::new(roller),
+        );
+        let file = RollingFileAppender::builder()
+            .encoder(logging::encoder(log.format))
+            .build(path, Box::new(policy))
+            .map_err(|err| format!("{}: {}", path, err))?;
+        builder = builder.appender(Appender::builder().build("file", Box::new(file)));
+        root = root.appender("file");
+    }
 
-    processor.add_handler(&handler);
-    processor.start();
+    let config = builder
+        .build(root.build(level))
+        .map_err(|err| format!("{:?}", err))?;
+    log4rs::init_config(config).map_err(|err| format!("{:?}", err))?;
+    Ok(level)
 }
@@ -0,0 +1,411 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use sawtooth_sdk::processor::handler::{ApplyError, ContextError, TransactionContext};
+
+use decode::EntryType;
+use http;
+
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+const SIZE_BUCKETS: [f64; 8] = [
+    256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0,
+];
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Histogram {
+        Histogram {
+            bounds: bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let braces = |labels: &str| {
+            if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels)
+            }
+        };
+        let _ = writeln!(out, "{}_sum{} {}", name, braces(labels), self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braces(labels), self.count);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Registry {
+    transactions: BTreeMap<(String, String), u64>,
+    rejections: BTreeMap<String, u64>,
+    apply_seconds: BTreeMap<String, Histogram>,
+    state_gets: u64,
+    state_get_bytes: u64,
+    state_sets: u64,
+    state_set_bytes: u64,
+    state_deletes: u64,
+    container_bytes: BTreeMap<String, Histogram>,
+}
+
+/// Counters and histograms of the transactions applied by this processor,
+/// rendered in the Prometheus text format.
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Mutex<Registry>,
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            registry: Mutex::new(Registry {
+                transactions: BTreeMap::new(),
+                rejections: BTreeMap::new(),
+                apply_seconds: BTreeMap::new(),
+                state_gets: 0,
+                state_get_bytes: 0,
+                state_sets: 0,
+                state_set_bytes: 0,
+                state_deletes: 0,
+                container_bytes: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn update<F: FnOnce(&mut Registry)>(&self, f: F) {
+        // A panic while holding the lock leaves plain counters behind, which
+        // are still fine to use.
+        let mut registry = match self.registry.lock() {
+            Ok(registry) => registry,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        f(&mut registry);
+    }
+
+    /// Records a finished transaction. `action` is None when the payload did
+    /// not decode.
+    pub fn record_transaction(
+        &self,
+        action: Option<&str>,
+        result: &Result<(), ApplyError>,
+        duration: Duration,
+    ) {
+        let action = action.unwrap_or("UNKNOWN").to_string();
+        let outcome = match *result {
+            Ok(()) => "applied",
+            Err(ApplyError::InvalidTransaction(_)) => "invalid",
+            Err(ApplyError::InternalError(_)) => "internal_error",
+        };
+        let seconds =
+            duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0;
+
+        self.update(|registry| {
+            *registry
+                .transactions
+                .entry((action.clone(), outcome.to_string()))
+                .or_insert(0) += 1;
+            if let Err(ref err) = *result {
+                *registry
+                    .rejections
+                    .entry(error_code(err).to_string())
+                    .or_insert(0) += 1;
+            }
+            registry
+                .apply_seconds
+                .entry(action)
+                .or_insert_with(|| Histogram::new(&LATENCY_BUCKETS))
+                .observe(seconds);
+        });
+    }
+
+    fn record_get(&self, bytes: usize) {
+        self.update(|registry| {
+            registry.state_gets += 1;
+            registry.state_get_bytes += bytes as u64;
+        });
+    }
+
+    fn record_set(&self, namespace: &str, entries: &HashMap<String, Vec<u8>>) {
+        self.update(|registry| {
+            for (address, data) in entries {
+                registry.state_sets += 1;
+                registry.state_set_bytes += data.len() as u64;
+                let address_type = EntryType::from_address(namespace, address)
+                    .map(|entry_type| entry_type.name())
+                    .unwrap_or("other");
+                registry
+                    .container_bytes
+                    .entry(address_type.to_string())
+                    .or_insert_with(|| Histogram::new(&SIZE_BUCKETS))
+                    .observe(data.len() as f64);
+            }
+        });
+    }
+
+    fn record_delete(&self, count: usize) {
+        self.update(|registry| registry.state_deletes += count as u64);
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let registry = match self.registry.lock() {
+            Ok(registry) => registry.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "dgc_transactions_total",
+            "Transactions applied, by action and result.",
+            "counter",
+        );
+        for (&(ref action, ref result), count) in &registry.transactions {
+            let _ = writeln!(
+                out,
+                "dgc_transactions_total{{action=\"{}\",result=\"{}\"}} {}",
+                action, result, count
+            );
+        }
+
+        header(
+            &mut out,
+            "dgc_rejections_total",
+            "Rejected transactions, by error code.",
+            "counter",
+        );
+        for (code, count) in &registry.rejections {
+            let _ = writeln!(out, "dgc_rejections_total{{code=\"{}\"}} {}", code, count);
+        }
+
+        header(
+            &mut out,
+            "dgc_apply_duration_seconds",
+            "Time taken to apply a transaction, by action.",
+            "histogram",
+        );
+        for (action, histogram) in &registry.apply_seconds {
+            histogram.render(
+                &mut out,
+                "dgc_apply_duration_seconds",
+                &format!("action=\"{}\"", action),
+            );
+        }
+
+        let counters = [
+            (
+                "dgc_state_get_total",
+                "Addresses read with get_state.",
+                registry.state_gets,
+            ),
+            (
+                "dgc_state_get_bytes_total",
+                "Bytes read with get_state.",
+                registry.state_get_bytes,
+            ),
+            (
+                "dgc_state_set_total",
+                "Addresses written with set_state.",
+                registry.state_sets,
+            ),
+            (
+                "dgc_state_set_bytes_total",
+                "Bytes written with set_state.",
+                registry.state_set_bytes,
+            ),
+            (
+                "dgc_state_delete_total",
+                "Addresses removed with delete_state.",
+                registry.state_deletes,
+            ),
+        ];
+        for &(name, help, value) in counters.iter() {
+            header(&mut out, name, help, "counter");
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        header(
+            &mut out,
+            "dgc_container_bytes",
+            "Size of the containers written, by address type.",
+            "histogram",
+        );
+        for (address_type, histogram) in &registry.container_bytes {
+            histogram.render(
+                &mut out,
+                "dgc_container_bytes",
+                &format!("address_type=\"{}\"", address_type),
+            );
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// A short, stable name for the kind of error, for counting rejections. The
+/// handler's messages carry ids and amounts, so they are grouped by wording.
+pub fn error_code(err: &ApplyError) -> &'static str {
+    let msg = match *err {
+        ApplyError::InternalError(_) => return "internal_error",
+        ApplyError::InvalidTransaction(ref msg) => msg,
+    };
+    let rules: [(&[&str], &'static str); 9] = [
+        (
+            &["deserialize", "must contain a payload"],
+            "malformed_payload",
+        ),
+        (&["more than the limit"], "limit_exceeded"),
+        (&["Timestamp"], "timestamp"),
+        (
+            &[
+                "Only the",
+                "Must be",
+                "not authorized",
+                "does not match the issuing",
+                "cannot be revoked",
+            ],
+            "unauthorized",
+        ),
+        (
+            &[
+                "does not exist",
+                "not found",
+                "not register",
+                "does not have",
+                "not in schemata",
+            ],
+            "not_found",
+        ),
+        (&["already"], "already_exists"),
+        (&["final", "not open", "fixed", "delayed"], "invalid_state"),
+        (&["balance", "amount"], "invalid_amount"),
+        (
+            &["type", "schema", "empty", "enum", "property"],
+            "invalid_value",
+        ),
+    ];
+    for &(patterns, code) in rules.iter() {
+        if patterns.iter().any(|pattern| msg.contains(pattern)) {
+            return code;
+        }
+    }
+    "invalid"
+}
+
+/// Counts the state calls made through it, then passes them to the
+/// validator's context.
+pub struct MeteredContext<'a> {
+    context: &'a mut TransactionContext,
+    metrics: &'a Metrics,
+    namespace: &'a str,
+}
+
+impl<'a> MeteredContext<'a> {
+    /// `namespace` is used to tell the address type of each container
+    /// written.
+    pub fn new(
+        context: &'a mut TransactionContext,
+        metrics: &'a Metrics,
+        namespace: &'a str,
+    ) -> MeteredContext<'a> {
+        MeteredContext {
+            context: context,
+            metrics: metrics,
+            namespace: namespace,
+        }
+    }
+}
+
+impl<'a> TransactionContext for MeteredContext<'a> {
+    fn get_state(&mut self, addresses: Vec<String>) -> Result<Option<Vec<u8>>, ContextError> {
+        let data = self.context.get_state(addresses)?;
+        self.metrics
+            .record_get(data.as_ref().map(|data| data.len()).unwrap_or(0));
+        Ok(data)
+    }
+
+    fn set_state(&mut self, entries: HashMap<String, Vec<u8>>) -> Result<(), ContextError> {
+        self.metrics.record_set(self.namespace, &entries);
+        self.context.set_state(entries)
+    }
+
+    fn delete_state(
+        &mut self,
+        addresses: Vec<String>,
+    ) -> Result<Option<Vec<String>>, ContextError> {
+        self.metrics.record_delete(addresses.len());
+        self.context.delete_state(addresses)
+    }
+
+    fn add_receipt_data(&mut self, data: &[u8]) -> Result<(), ContextError> {
+        self.context.add_receipt_data(data)
+    }
+
+    fn add_event(
+        &mut self,
+        event_type: String,
+        attributes: Vec<(String, String)>,
+        data: &[u8],
+    ) -> Result<(), ContextError> {
+        self.context.add_event(event_type, attributes, data)
+    }
+}
+
+/// Serves `GET /metrics` on `address` from a background thread.
+pub fn serve(address: &str, metrics: Arc<Metrics>) -> io::Result<thread::JoinHandle<()>> {
+    let handle = http::serve(address, move |path| match path {
+        "/metrics" => ("200 OK", metrics.render()),
+        _ => http::not_found(),
+    })?;
+    info!("Serving metrics on http://{}/metrics", address);
+    Ok(handle)
+}
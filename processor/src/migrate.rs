@@ -0,0 +1,147 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use protobuf;
+use protobuf::Message;
+
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use decode::EntryType;
+use messages::*;
+
+/// The layouts written by this handler. Entries with a lower format version
+/// are upgraded when read, and rewritten by MIGRATE; a higher version was
+/// written by a newer handler and is refused.
+pub const PARTICIPANT_FORMAT: u32 = 2;
+pub const PROPOSAL_FORMAT: u32 = 1;
+pub const RECORD_FORMAT: u32 = 1;
+
+fn check_format(kind: &str, id: &str, version: u32, current: u32) -> Result<(), ApplyError> {
+    if version > current {
+        return Err(ApplyError::InvalidTransaction(format!(
+            "{} {} has format version {}, newer than the supported {}",
+            kind, id, version, current
+        )));
+    }
+    Ok(())
+}
+
+// Each upgrade applies the steps from the entry's version to the current
+// one. Format 1 only records the version, so going from 0 changes nothing
+// else. Participant format 2 tracks drawn credit apart from the balance,
+// which earlier formats let go below zero instead.
+
+/// Brings a participant to `PARTICIPANT_FORMAT`. Returns whether it changed.
+pub fn upgrade_participant(participant: &mut participant::Participant) -> Result<bool, ApplyError> {
+    let version = participant.get_format_version();
+    check_format("Participant", participant.get_public_key(), version, PARTICIPANT_FORMAT)?;
+    if version == PARTICIPANT_FORMAT {
+        return Ok(false);
+    }
+    if version < 2 {
+        let balance = participant.get_dg_coin_balances().last().cloned();
+        if let Some(balance) = balance {
+            if balance.get_dg_coin_amount() < 0.0 {
+                let mut drawn = balance.clone();
+                drawn.set_dg_coin_amount(-balance.get_dg_coin_amount());
+                participant.mut_dg_coin_drawn_credits().push(drawn);
+                let mut cleared = balance;
+                cleared.set_dg_coin_amount(0.0);
+                participant.mut_dg_coin_balances().push(cleared);
+            }
+        }
+    }
+    participant.set_format_version(PARTICIPANT_FORMAT);
+    Ok(true)
+}
+
+/// Brings a proposal to `PROPOSAL_FORMAT`. Returns whether it changed.
+pub fn upgrade_proposal(proposal: &mut proposal::Proposal) -> Result<bool, ApplyError> {
+    let version = proposal.get_format_version();
+    check_format("Proposal", proposal.get_proposal_id(), version, PROPOSAL_FORMAT)?;
+    if version == PROPOSAL_FORMAT {
+        return Ok(false);
+    }
+    proposal.set_format_version(PROPOSAL_FORMAT);
+    Ok(true)
+}
+
+/// Brings a record to `RECORD_FORMAT`. Returns whether it changed.
+pub fn upgrade_record(record: &mut record::Record) -> Result<bool, ApplyError> {
+    let version = record.get_format_version();
+    check_format("Record", record.get_record_id(), version, RECORD_FORMAT)?;
+    if version == RECORD_FORMAT {
+        return Ok(false);
+    }
+    record.set_format_version(RECORD_FORMAT);
+    Ok(true)
+}
+
+/// Checks that `address` holds participants, proposals or records, the
+/// entities which have a format version.
+pub fn check_migratable(namespace: &str, address: &str) -> Result<EntryType, ApplyError> {
+    match EntryType::from_address(namespace, address) {
+        Some(entry_type @ EntryType::Participant)
+        | Some(entry_type @ EntryType::Proposal)
+        | Some(entry_type @ EntryType::Record) => Ok(entry_type),
+        _ => Err(ApplyError::InvalidTransaction(format!(
+            "Not a participant, proposal or record address: {}",
+            address
+        ))),
+    }
+}
+
+/// Upgrades every entry of the container stored at `address`. Returns the
+/// container to write back, or None if it was already current.
+pub fn migrate_container(
+    namespace: &str,
+    address: &str,
+    data: &[u8],
+) -> Result<Option<Vec<u8>>, ApplyError> {
+    match check_migratable(namespace, address)? {
+        EntryType::Participant => {
+            let mut container: participant::ParticipantContainer = parse(address, data)?;
+            let mut changed = false;
+            for participant in container.mut_entries().iter_mut() {
+                changed |= upgrade_participant(participant)?;
+            }
+            serialize_if(changed, address, &container)
+        }
+        EntryType::Proposal => {
+            let mut container: proposal::ProposalContainer = parse(address, data)?;
+            let mut changed = false;
+            for proposal in container.mut_entries().iter_mut() {
+                changed |= upgrade_proposal(proposal)?;
+            }
+            serialize_if(changed, address, &container)
+        }
+        EntryType::Record => {
+            let mut container: record::RecordContainer = parse(address, data)?;
+            let mut changed = false;
+            for record in container.mut_entries().iter_mut() {
+                changed |= upgrade_record(record)?;
+            }
+            serialize_if(changed, address, &container)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn parse<M: Message>(address: &str, data: &[u8]) -> Result<M, ApplyError> {
+    protobuf::parse_from_bytes(data).map_err(|_| {
+        ApplyError::InternalError(format!("Cannot deserialize container at {}", address))
+    })
+}
+
+fn serialize_if<M: Message>(
+    changed: bool,
+    address: &str,
+    container: &M,
+) -> Result<Option<Vec<u8>>, ApplyError> {
+    if !changed {
+        return Ok(None);
+    }
+    container.write_to_bytes().map(Some).map_err(|_| {
+        ApplyError::InternalError(format!("Cannot serialize container at {}", address))
+    })
+}
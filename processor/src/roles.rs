@@ -0,0 +1,75 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use messages::role::RoleAssignment_Role as Role;
+
+/// Set to "true" to require a role for creating tables, which anyone could
+/// do before roles were added.
+pub const ENFORCE_KEY: &str = "dgc.roles.enforce";
+
+/// What a handler path asks `authorize` for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ManageRoles,
+    ManageParticipants,
+    Migrate,
+    CreateTable,
+    IssueCredit,
+    SettleExchange,
+    Audit,
+}
+
+impl Permission {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Permission::ManageRoles => "manage roles",
+            Permission::ManageParticipants => "manage participants",
+            Permission::Migrate => "migrate",
+            Permission::CreateTable => "create tables",
+            Permission::IssueCredit => "issue credit",
+            Permission::SettleExchange => "settle exchanges",
+            Permission::Audit => "audit",
+        }
+    }
+
+    /// The role which grants the permission besides ADMIN, which grants
+    /// them all.
+    fn role(&self) -> Option<Role> {
+        match *self {
+            Permission::ManageRoles | Permission::ManageParticipants | Permission::Migrate => None,
+            Permission::CreateTable => Some(Role::TABLE_ADMIN),
+            Permission::IssueCredit => Some(Role::CREDIT_ISSUER),
+            Permission::SettleExchange => Some(Role::EXCHANGE_OPERATOR),
+            Permission::Audit => Some(Role::AUDITOR),
+        }
+    }
+
+    /// Whether every participant has the permission until `ENFORCE_KEY` is
+    /// set, as they did before roles were added. Issuing credit and settling
+    /// exchanges move balances, so they need a role from the start.
+    pub fn open_by_default(&self) -> bool {
+        match *self {
+            Permission::CreateTable => true,
+            _ => false,
+        }
+    }
+}
+
+/// Whether holding `roles` grants `permission`.
+pub fn grants(roles: &[Role], permission: Permission) -> bool {
+    roles
+        .iter()
+        .any(|role| *role == Role::ADMIN || Some(*role) == permission.role())
+}
+
+/// Parses a role as written in the proto, such as "TABLE_ADMIN".
+pub fn parse_role(role: &str) -> Option<Role> {
+    match role {
+        "ADMIN" => Some(Role::ADMIN),
+        "TABLE_ADMIN" => Some(Role::TABLE_ADMIN),
+        "CREDIT_ISSUER" => Some(Role::CREDIT_ISSUER),
+        "EXCHANGE_OPERATOR" => Some(Role::EXCHANGE_OPERATOR),
+        "AUDITOR" => Some(Role::AUDITOR),
+        _ => None,
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use std::path::Path;
+
+use protobuf::RepeatedField;
+use rustc_serialize::hex::FromHex;
+use serde_json;
+use serde_yaml;
+
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use handler::validate_create_table;
+use messages::*;
+
+#[derive(Debug)]
+pub enum SchemaError {
+    ParseError(String),
+    InvalidSchema(String),
+}
+
+impl StdError for SchemaError {
+    fn description(&self) -> &str {
+        match *self {
+            SchemaError::ParseError(ref msg) => msg,
+            SchemaError::InvalidSchema(ref msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaError::ParseError(ref s) => write!(f, "ParseError: {}", s),
+            SchemaError::InvalidSchema(ref s) => write!(f, "InvalidSchema: {}", s),
+        }
+    }
+}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(e: serde_json::Error) -> Self {
+        SchemaError::ParseError(format!("{}", e))
+    }
+}
+
+impl From<serde_yaml::Error> for SchemaError {
+    fn from(e: serde_yaml::Error) -> Self {
+        SchemaError::ParseError(format!("{}", e))
+    }
+}
+
+impl From<ApplyError> for SchemaError {
+    fn from(e: ApplyError) -> Self {
+        match e {
+            ApplyError::InvalidTransaction(msg) => SchemaError::InvalidSchema(msg),
+            ApplyError::InternalError(msg) => SchemaError::InvalidSchema(msg),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemaFormat {
+    Json,
+    Yaml,
+}
+
+impl SchemaFormat {
+    /// Picks the format from a file extension, defaulting to JSON.
+    pub fn from_path(path: &str) -> SchemaFormat {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => SchemaFormat::Yaml,
+            _ => SchemaFormat::Json,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<SchemaFormat, SchemaError> {
+        match name.to_lowercase().as_str() {
+            "json" => Ok(SchemaFormat::Json),
+            "yaml" | "yml" => Ok(SchemaFormat::Yaml),
+            _ => Err(SchemaError::ParseError(format!(
+                "Unknown schema format: {}",
+                name
+            ))),
+        }
+    }
+}
+
+/// A human readable description of a Table, as read from a schema file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableDefinition {
+    pub name: String,
+    pub properties: Vec<PropertyDefinition>,
+}
+
+/// Mirrors `PropertySchema` field by field. Fields left at their protobuf
+/// default are omitted when exporting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertyDefinition {
+    pub name: String,
+    pub data_type: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub fixed: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub delayed: bool,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub number_exponent: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enum_options: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub struct_properties: Vec<PropertyDefinition>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub unit: String,
+}
+
+/// Mirrors `PropertyValue` field by field. Bytes are hex encoded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropertyValueDefinition {
+    pub name: String,
+    pub data_type: String,
+    #[serde(default)]
+    pub bytes_value: String,
+    #[serde(default)]
+    pub boolean_value: bool,
+    #[serde(default)]
+    pub number_value: i64,
+    #[serde(default)]
+    pub string_value: String,
+    #[serde(default)]
+    pub enum_value: String,
+    #[serde(default)]
+    pub struct_values: Vec<PropertyValueDefinition>,
+    #[serde(default)]
+    pub location_value: Option<LocationDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationDefinition {
+    pub latitude: i64,
+    pub longitude: i64,
+}
+
+pub fn parse_data_type(name: &str) -> Result<property::PropertySchema_DataType, SchemaError> {
+    match name.to_uppercase().as_str() {
+        "BYTES" => Ok(property::PropertySchema_DataType::BYTES),
+        "BOOLEAN" => Ok(property::PropertySchema_DataType::BOOLEAN),
+        "NUMBER" => Ok(property::PropertySchema_DataType::NUMBER),
+        "STRING" => Ok(property::PropertySchema_DataType::STRING),
+        "ENUM" => Ok(property::PropertySchema_DataType::ENUM),
+        "STRUCT" => Ok(property::PropertySchema_DataType::STRUCT),
+        "LOCATION" => Ok(property::PropertySchema_DataType::LOCATION),
+        "TYPE_UNSET" => Ok(property::PropertySchema_DataType::TYPE_UNSET),
+        _ => Err(SchemaError::InvalidSchema(format!(
+            "Unknown data type: {}",
+            name
+        ))),
+    }
+}
+
+pub fn data_type_name(data_type: property::PropertySchema_DataType) -> &'static str {
+    match data_type {
+        property::PropertySchema_DataType::TYPE_UNSET => "TYPE_UNSET",
+        property::PropertySchema_DataType::BYTES => "BYTES",
+        property::PropertySchema_DataType::BOOLEAN => "BOOLEAN",
+        property::PropertySchema_DataType::NUMBER => "NUMBER",
+        property::PropertySchema_DataType::STRING => "STRING",
+        property::PropertySchema_DataType::ENUM => "ENUM",
+        property::PropertySchema_DataType::STRUCT => "STRUCT",
+        property::PropertySchema_DataType::LOCATION => "LOCATION",
+    }
+}
+
+impl TableDefinition {
+    pub fn parse(contents: &str, format: SchemaFormat) -> Result<TableDefinition, SchemaError> {
+        match format {
+            SchemaFormat::Json => Ok(serde_json::from_str(contents)?),
+            SchemaFormat::Yaml => Ok(serde_yaml::from_str(contents)?),
+        }
+    }
+
+    pub fn to_string(&self, format: SchemaFormat) -> Result<String, SchemaError> {
+        match format {
+            SchemaFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            SchemaFormat::Yaml => Ok(serde_yaml::to_string(self)?),
+        }
+    }
+
+    /// Builds the action for the table, rejecting it if the handler would.
+    pub fn to_create_table_action(&self) -> Result<payload::CreateTableAction, SchemaError> {
+        let mut properties = Vec::new();
+        for property in &self.properties {
+            properties.push(property.to_property_schema()?);
+        }
+
+        let mut action = payload::CreateTableAction::new();
+        action.set_name(self.name.clone());
+        action.set_properties(RepeatedField::from_vec(properties));
+        validate_create_table(&action)?;
+        Ok(action)
+    }
+
+    pub fn from_table(table: &record::Table) -> TableDefinition {
+        TableDefinition {
+            name: table.get_name().to_string(),
+            properties: table
+                .get_properties()
+                .iter()
+                .map(PropertyDefinition::from_property_schema)
+                .collect(),
+        }
+    }
+
+    pub fn to_table(&self) -> Result<record::Table, SchemaError> {
+        let action = self.to_create_table_action()?;
+        let mut table = record::Table::new();
+        table.set_name(action.get_name().to_string());
+        table.set_properties(RepeatedField::from_vec(action.get_properties().to_vec()));
+        Ok(table)
+    }
+}
+
+impl PropertyDefinition {
+    pub fn to_property_schema(&self) -> Result<property::PropertySchema, SchemaError> {
+        let mut struct_properties = Vec::new();
+        for property in &self.struct_properties {
+            struct_properties.push(property.to_property_schema()?);
+        }
+
+        let mut schema = property::PropertySchema::new();
+        schema.set_name(self.name.clone());
+        schema.set_data_type(parse_data_type(&self.data_type)?);
+        schema.set_required(self.required);
+        schema.set_fixed(self.fixed);
+        schema.set_delayed(self.delayed);
+        schema.set_number_exponent(self.number_exponent);
+        schema.set_enum_options(RepeatedField::from_vec(self.enum_options.clone()));
+        schema.set_struct_properties(RepeatedField::from_vec(struct_properties));
+        schema.set_unit(self.unit.clone());
+        Ok(schema)
+    }
+
+    pub fn from_property_schema(schema: &property::PropertySchema) -> PropertyDefinition {
+        PropertyDefinition {
+            name: schema.get_name().to_string(),
+            data_type: data_type_name(schema.get_data_type()).to_string(),
+            required: schema.get_required(),
+            fixed: schema.get_fixed(),
+            delayed: schema.get_delayed(),
+            number_exponent: schema.get_number_exponent(),
+            enum_options: schema.get_enum_options().to_vec(),
+            struct_properties: schema
+                .get_struct_properties()
+                .iter()
+                .map(PropertyDefinition::from_property_schema)
+                .collect(),
+            unit: schema.get_unit().to_string(),
+        }
+    }
+}
+
+impl PropertyValueDefinition {
+    pub fn parse_list(
+        contents: &str,
+        format: SchemaFormat,
+    ) -> Result<Vec<PropertyValueDefinition>, SchemaError> {
+        match format {
+            SchemaFormat::Json => Ok(serde_json::from_str(contents)?),
+            SchemaFormat::Yaml => Ok(serde_yaml::from_str(contents)?),
+        }
+    }
+
+    pub fn to_property_value(&self) -> Result<property::PropertyValue, SchemaError> {
+        let data_type = parse_data_type(&self.data_type)?;
+
+        let mut value = property::PropertyValue::new();
+        value.set_name(self.name.clone());
+        value.set_data_type(data_type);
+        match data_type {
+            property::PropertySchema_DataType::TYPE_UNSET => (),
+            property::PropertySchema_DataType::BYTES => {
+                value.set_bytes_value(parse_hex(&self.bytes_value)?)
+            }
+            property::PropertySchema_DataType::BOOLEAN => {
+                value.set_boolean_value(self.boolean_value)
+            }
+            property::PropertySchema_DataType::NUMBER => value.set_number_value(self.number_value),
+            property::PropertySchema_DataType::STRING => {
+                value.set_string_value(self.string_value.clone())
+            }
+            property::PropertySchema_DataType::ENUM => value.set_enum_value(self.enum_value.clone()),
+            property::PropertySchema_DataType::STRUCT => {
+                let mut struct_values = Vec::new();
+                for struct_value in &self.struct_values {
+                    struct_values.push(struct_value.to_property_value()?);
+                }
+                value.set_struct_values(RepeatedField::from_vec(struct_values))
+            }
+            property::PropertySchema_DataType::LOCATION => {
+                let location = match self.location_value {
+                    Some(ref location) => location,
+                    None => {
+                        return Err(SchemaError::InvalidSchema(format!(
+                            "Location value not provided for {}",
+                            self.name
+                        )))
+                    }
+                };
+                let mut location_value = property::Location::new();
+                location_value.set_latitude(location.latitude);
+                location_value.set_longitude(location.longitude);
+                value.set_location_value(location_value)
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, SchemaError> {
+    hex.from_hex()
+        .map_err(|err| SchemaError::ParseError(format!("Invalid hex string {}: {}", hex, err)))
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
@@ -0,0 +1,65 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use protobuf;
+
+use sawtooth_sdk::messages::setting::Setting;
+use sawtooth_sdk::processor::handler::ApplyError;
+use sawtooth_sdk::processor::handler::TransactionContext;
+
+use addressing::make_setting_address;
+
+/// Comma separated public keys of the participants which hold the ADMIN
+/// role without an assignment in state, to bootstrap the roles registry.
+pub const ADMINS_KEY: &str = "dgc.admins";
+
+/// How many seconds the approvals collected for a multisig policy count
+/// before they expire.
+pub const APPROVAL_LIFETIME_KEY: &str = "dgc.multisig.approval_lifetime";
+
+pub const DEFAULT_APPROVAL_LIFETIME: u64 = 7 * 24 * 60 * 60;
+
+/// Reads the value of an on-chain setting, or None if it has not been set.
+pub fn get_setting(context: &mut TransactionContext, key: &str) -> Result<Option<String>, ApplyError> {
+    let data = match context.get_state(vec![make_setting_address(key)])? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let setting: Setting = match protobuf::parse_from_bytes(&data) {
+        Ok(setting) => setting,
+        Err(_) => {
+            return Err(ApplyError::InternalError(format!(
+                "Cannot deserialize setting: {}",
+                key
+            )))
+        }
+    };
+    Ok(setting
+        .get_entries()
+        .iter()
+        .find(|entry| entry.get_key() == key)
+        .map(|entry| entry.get_value().to_string()))
+}
+
+/// Whether `signer` is listed in the `dgc.admins` setting.
+pub fn is_admin(context: &mut TransactionContext, signer: &str) -> Result<bool, ApplyError> {
+    Ok(match get_setting(context, ADMINS_KEY)? {
+        Some(admins) => admins.split(',').any(|admin| admin.trim() == signer),
+        None => false,
+    })
+}
+
+/// The `dgc.multisig.approval_lifetime` setting, or the default when it is
+/// unset or invalid.
+pub fn approval_lifetime(context: &mut TransactionContext) -> Result<u64, ApplyError> {
+    Ok(match get_setting(context, APPROVAL_LIFETIME_KEY)? {
+        Some(value) => match value.parse::<u64>() {
+            Ok(lifetime) if lifetime > 0 => lifetime,
+            _ => {
+                warn!("Ignoring invalid setting {}: {}", APPROVAL_LIFETIME_KEY, value);
+                DEFAULT_APPROVAL_LIFETIME
+            }
+        },
+        None => DEFAULT_APPROVAL_LIFETIME,
+    })
+}
@@ -0,0 +1,320 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+use protobuf;
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::ToHex;
+use serde_json;
+use serde_json::Value;
+
+use decode::{property_value_to_json, DecodeError, EntryType};
+use messages::*;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    ParseError(String),
+    DecodeError(DecodeError),
+}
+
+impl StdError for SnapshotError {
+    fn description(&self) -> &str {
+        match *self {
+            SnapshotError::ParseError(ref msg) => msg,
+            SnapshotError::DecodeError(ref err) => err.description(),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::ParseError(ref s) => write!(f, "ParseError: {}", s),
+            SnapshotError::DecodeError(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<DecodeError> for SnapshotError {
+    fn from(e: DecodeError) -> Self {
+        SnapshotError::DecodeError(e)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(e: serde_json::Error) -> Self {
+        SnapshotError::ParseError(format!("{}", e))
+    }
+}
+
+/// Reads a state snapshot saved from the REST API. Accepts the body of a
+/// `/state` response, a bare array of `{"address", "data"}` entries, or an
+/// object mapping addresses to data. Data is base64 encoded throughout.
+pub fn parse_snapshot(contents: &str) -> Result<BTreeMap<String, Vec<u8>>, SnapshotError> {
+    let value: Value = serde_json::from_str(contents)?;
+    let entries = match value {
+        Value::Object(ref object) if object.contains_key("data") => object["data"].clone(),
+        other => other,
+    };
+
+    let mut state = BTreeMap::new();
+    match entries {
+        Value::Array(entries) => for entry in entries {
+            let address = match entry["address"].as_str() {
+                Some(address) => address.to_string(),
+                None => {
+                    return Err(SnapshotError::ParseError(String::from(
+                        "State entry is missing its address",
+                    )))
+                }
+            };
+            let data = decode_data(&address, &entry["data"])?;
+            state.insert(address, data);
+        },
+        Value::Object(entries) => for (address, data) in entries {
+            let data = decode_data(&address, &data)?;
+            state.insert(address, data);
+        },
+        _ => {
+            return Err(SnapshotError::ParseError(String::from(
+                "Snapshot must be an array or object of state entries",
+            )))
+        }
+    }
+    Ok(state)
+}
+
+fn decode_data(address: &str, data: &Value) -> Result<Vec<u8>, SnapshotError> {
+    match data.as_str() {
+        Some(data) => data.from_base64().map_err(|err| {
+            SnapshotError::ParseError(format!("Invalid data at {}: {}", address, err))
+        }),
+        None => Err(SnapshotError::ParseError(format!(
+            "State entry is missing its data: {}",
+            address
+        ))),
+    }
+}
+
+/// Columns of the long-format property history CSV.
+pub const HISTORY_HEADER: [&str; 7] = [
+    "record_id",
+    "property",
+    "page",
+    "timestamp",
+    "block_timestamp",
+    "reporter",
+    "value",
+];
+
+/// The rows of the CSV files exported for one Table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableExport {
+    pub name: String,
+    /// record_id, owner, custodian and final, then one column per property.
+    pub header: Vec<String>,
+    /// One row per record, with the current value of every property.
+    pub records: Vec<Vec<String>>,
+    /// One row per reported value of every property of every record.
+    pub history: Vec<Vec<String>>,
+}
+
+/// Builds the record and property history rows for every Table stored under
+/// `namespace`.
+pub fn export_tables(
+    namespace: &str,
+    state: &BTreeMap<String, Vec<u8>>,
+) -> Result<Vec<TableExport>, SnapshotError> {
+    let mut tables: BTreeMap<String, record::Table> = BTreeMap::new();
+    let mut records: BTreeMap<String, record::Record> = BTreeMap::new();
+    let mut properties: BTreeMap<(String, String), property::Property> = BTreeMap::new();
+    let mut pages: BTreeMap<(String, String), Vec<(u32, property::PropertyPage)>> =
+        BTreeMap::new();
+
+    for (address, data) in state {
+        let to_err = |err| DecodeError::ProtobufError(address.to_string(), err);
+        match EntryType::from_address(namespace, address) {
+            Some(EntryType::Table) => {
+                let container: record::TableContainer =
+                    protobuf::parse_from_bytes(data).map_err(to_err)?;
+                for table in container.get_entries() {
+                    tables.insert(table.get_name().to_string(), table.clone());
+                }
+            }
+            Some(EntryType::Record) => {
+                let container: record::RecordContainer =
+                    protobuf::parse_from_bytes(data).map_err(to_err)?;
+                for record in container.get_entries() {
+                    records.insert(record.get_record_id().to_string(), record.clone());
+                }
+            }
+            Some(EntryType::Property) => {
+                let container: property::PropertyContainer =
+                    protobuf::parse_from_bytes(data).map_err(to_err)?;
+                for property in container.get_entries() {
+                    let key = (
+                        property.get_record_id().to_string(),
+                        property.get_name().to_string(),
+                    );
+                    properties.insert(key, property.clone());
+                }
+            }
+            Some(EntryType::PropertyPage) => {
+                let container: property::PropertyPageContainer =
+                    protobuf::parse_from_bytes(data).map_err(to_err)?;
+                let page_number = u32::from_str_radix(&address[66..], 16).unwrap_or(0);
+                for page in container.get_entries() {
+                    let key = (page.get_record_id().to_string(), page.get_name().to_string());
+                    pages
+                        .entry(key)
+                        .or_insert_with(Vec::new)
+                        .push((page_number, page.clone()));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut exports = Vec::new();
+    for (name, table) in tables {
+        let mut header: Vec<String> = ["record_id", "owner", "custodian", "final"]
+            .iter()
+            .map(|column| column.to_string())
+            .collect();
+        for property in table.get_properties() {
+            header.push(property.get_name().to_string());
+        }
+
+        let mut export = TableExport {
+            name: name.clone(),
+            header: header,
+            records: Vec::new(),
+            history: Vec::new(),
+        };
+
+        for record in records.values().filter(|r| r.get_table() == name) {
+            let record_id = record.get_record_id().to_string();
+            let mut row = vec![
+                record_id.clone(),
+                last_participant(record.get_owners()),
+                last_participant(record.get_custodians()),
+                record.get_field_final().to_string(),
+            ];
+
+            for schema in table.get_properties() {
+                let key = (record_id.clone(), schema.get_name().to_string());
+                let property = match properties.get(&key) {
+                    Some(property) => property,
+                    None => {
+                        row.push(String::new());
+                        continue;
+                    }
+                };
+
+                let mut values = Vec::new();
+                if let Some(property_pages) = pages.get(&key) {
+                    for &(page_number, ref page) in property_pages {
+                        for value in page.get_reported_values() {
+                            values.push((page_number, value));
+                        }
+                    }
+                }
+                values.sort_by_key(|&(_, value)| {
+                    (value.get_timestamp(), value.get_reporter_index())
+                });
+
+                row.push(match values.last() {
+                    Some(&(_, value)) => format_value(value, property),
+                    None => String::new(),
+                });
+
+                for (page_number, value) in values {
+                    let reporter = property
+                        .get_reporters()
+                        .iter()
+                        .find(|r| r.get_index() == value.get_reporter_index())
+                        .map(|r| r.get_public_key().to_string())
+                        .unwrap_or_default();
+                    export.history.push(vec![
+                        record_id.clone(),
+                        property.get_name().to_string(),
+                        page_number.to_string(),
+                        value.get_timestamp().to_string(),
+                        value.get_block_timestamp().to_string(),
+                        reporter,
+                        format_value(value, property),
+                    ]);
+                }
+            }
+            export.records.push(row);
+        }
+        exports.push(export);
+    }
+    Ok(exports)
+}
+
+fn last_participant(participants: &[record::Record_AssociatedParticipant]) -> String {
+    match participants.last() {
+        Some(participant) => participant.get_participant_id().to_string(),
+        None => String::new(),
+    }
+}
+
+/// Renders a reported value as text: numbers are scaled by the property's
+/// exponent, enums are given by option name and structs as JSON.
+pub fn format_value(
+    value: &property::PropertyPage_ReportedValue,
+    property: &property::Property,
+) -> String {
+    match property.get_data_type() {
+        property::PropertySchema_DataType::TYPE_UNSET => String::new(),
+        property::PropertySchema_DataType::BYTES => value.get_bytes_value().to_hex(),
+        property::PropertySchema_DataType::BOOLEAN => value.get_boolean_value().to_string(),
+        property::PropertySchema_DataType::NUMBER => {
+            format_number(value.get_number_value(), property.get_number_exponent())
+        }
+        property::PropertySchema_DataType::STRING => value.get_string_value().to_string(),
+        property::PropertySchema_DataType::ENUM => {
+            match property.get_enum_options().get(value.get_enum_value() as usize) {
+                Some(option) => option.clone(),
+                None => value.get_enum_value().to_string(),
+            }
+        }
+        property::PropertySchema_DataType::STRUCT => Value::Array(
+            value
+                .get_struct_values()
+                .iter()
+                .map(property_value_to_json)
+                .collect(),
+        ).to_string(),
+        property::PropertySchema_DataType::LOCATION => format!(
+            "{},{}",
+            value.get_location_value().get_latitude(),
+            value.get_location_value().get_longitude()
+        ),
+    }
+}
+
+/// Formats `value * 10^exponent` exactly, without going through floats.
+pub fn format_number(value: i64, exponent: i32) -> String {
+    if value == 0 {
+        return String::from("0");
+    }
+    if exponent >= 0 {
+        return format!("{}{}", value, "0".repeat(exponent as usize));
+    }
+
+    let sign = if value < 0 { "-" } else { "" };
+    let digits = (value as i128).abs().to_string();
+    let scale = (-exponent) as usize;
+    let digits = if digits.len() <= scale {
+        format!("{}{}", "0".repeat(scale - digits.len() + 1), digits)
+    } else {
+        digits
+    };
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, whole, fraction)
+}
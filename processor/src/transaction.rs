@@ -0,0 +1,781 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use protobuf;
+use protobuf::Message;
+use protobuf::RepeatedField;
+use rand;
+use rand::Rng;
+
+use sawtooth_sdk::messages::batch::{Batch, BatchHeader, BatchList};
+use sawtooth_sdk::messages::transaction::{Transaction, TransactionHeader};
+use sawtooth_sdk::signing;
+
+use messages::*;
+use addressing::*;
+use block_time::block_time_addresses;
+use limits::Limits;
+use roles::ENFORCE_KEY;
+use settings::{ADMINS_KEY, APPROVAL_LIFETIME_KEY};
+
+pub const FAMILY_VERSION: &str = "1.1";
+pub const FAMILY_VERSION_2: &str = "2";
+
+/// The family versions the handler accepts.
+pub const FAMILY_VERSIONS: [&str; 2] = [FAMILY_VERSION, FAMILY_VERSION_2];
+
+#[derive(Debug)]
+pub enum TransactionError {
+    SigningError(signing::Error),
+    SerializationError(protobuf::ProtobufError),
+    UnsupportedAction(String),
+}
+
+impl StdError for TransactionError {
+    fn description(&self) -> &str {
+        match *self {
+            TransactionError::SigningError(ref err) => err.description(),
+            TransactionError::SerializationError(ref err) => err.description(),
+            TransactionError::UnsupportedAction(ref msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransactionError::SigningError(ref err) => write!(f, "SigningError: {}", err),
+            TransactionError::SerializationError(ref err) => {
+                write!(f, "SerializationError: {}", err)
+            }
+            TransactionError::UnsupportedAction(ref msg) => write!(f, "UnsupportedAction: {}", msg),
+        }
+    }
+}
+
+impl From<signing::Error> for TransactionError {
+    fn from(e: signing::Error) -> Self {
+        TransactionError::SigningError(e)
+    }
+}
+
+impl From<protobuf::ProtobufError> for TransactionError {
+    fn from(e: protobuf::ProtobufError) -> Self {
+        TransactionError::SerializationError(e)
+    }
+}
+
+/// Wraps an action in a `PayloadDGC`, setting the action tag to match. A
+/// settlement is sent as the autoOPEN or autoCLOSE answer it replaces;
+/// actions added in version 2 cannot be sent.
+pub fn make_payload(
+    action: PayloadAction,
+    timestamp: u64,
+) -> Result<payload::PayloadDGC, TransactionError> {
+    let mut payload = payload::PayloadDGC::new();
+    payload.set_timestamp(timestamp);
+    match action {
+        PayloadAction::CreateParticipant(action) => {
+            payload.set_action(payload::PayloadDGC_Action::CREATE_PARTICIPANT);
+            payload.set_create_participant(action);
+        }
+        PayloadAction::CreateRecord(action) => {
+            payload.set_action(payload::PayloadDGC_Action::CREATE_RECORD);
+            payload.set_create_record(action);
+        }
+        PayloadAction::FinalizeRecord(action) => {
+            payload.set_action(payload::PayloadDGC_Action::FINALIZE_RECORD);
+            payload.set_finalize_record(action);
+        }
+        PayloadAction::CreateTable(action) => {
+            payload.set_action(payload::PayloadDGC_Action::CREATE_TABLE);
+            payload.set_create_table(action);
+        }
+        PayloadAction::UpdateProperties(action) => {
+            payload.set_action(payload::PayloadDGC_Action::UPDATE_PROPERTIES);
+            payload.set_update_properties(action);
+        }
+        PayloadAction::CreateProposal(action) => {
+            payload.set_action(payload::PayloadDGC_Action::CREATE_PROPOSAL);
+            payload.set_create_proposal(action);
+        }
+        PayloadAction::AnswerProposal(action) => {
+            payload.set_action(payload::PayloadDGC_Action::ANSWER_PROPOSAL);
+            payload.set_answer_proposal(action);
+        }
+        PayloadAction::RevokeReporter(action) => {
+            payload.set_action(payload::PayloadDGC_Action::REVOKE_REPORTER);
+            payload.set_revoke_reporter(action);
+        }
+        PayloadAction::SettleProposal(action) => {
+            payload.set_action(payload::PayloadDGC_Action::ANSWER_PROPOSAL);
+            payload.set_answer_proposal(settlement_answer(&action));
+        }
+        PayloadAction::CounterProposal(_) => {
+            return Err(TransactionError::UnsupportedAction(format!(
+                "COUNTER answers are only available in family version {}",
+                FAMILY_VERSION_2
+            )))
+        }
+        PayloadAction::Migrate(_)
+        | PayloadAction::UpdateParticipant(_)
+        | PayloadAction::RotateKey(_)
+        | PayloadAction::SetParticipantStatus(_)
+        | PayloadAction::SetRoles(_)
+        | PayloadAction::CreateOrganization(_)
+        | PayloadAction::SetOrganizationMember(_)
+        | PayloadAction::CreateMultisigPolicy(_)
+        | PayloadAction::ExpireProposals(_)
+        | PayloadAction::SetCreditLimit(_)
+        | PayloadAction::RepayCredit(_)
+        | PayloadAction::WithdrawApproval(_) => {
+            return Err(TransactionError::UnsupportedAction(format!(
+                "{} is only available in family version {}",
+                action.name(),
+                FAMILY_VERSION_2
+            )))
+        }
+    }
+    Ok(payload)
+}
+
+/// Wraps an action in a `PayloadV2`, setting the action tag to match. An
+/// autoOPEN or autoCLOSE answer is sent as the settlement it stands for.
+pub fn make_payload_v2(action: PayloadAction, timestamp: u64) -> payload_v2::PayloadV2 {
+    let mut payload = payload_v2::PayloadV2::new();
+    payload.set_timestamp(timestamp);
+    match action {
+        PayloadAction::CreateParticipant(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::CREATE_PARTICIPANT);
+            payload.set_create_participant(action);
+        }
+        PayloadAction::CreateRecord(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::CREATE_RECORD);
+            payload.set_create_record(action);
+        }
+        PayloadAction::FinalizeRecord(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::FINALIZE_RECORD);
+            payload.set_finalize_record(action);
+        }
+        PayloadAction::CreateTable(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::CREATE_TABLE);
+            payload.set_create_table(action);
+        }
+        PayloadAction::UpdateProperties(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::UPDATE_PROPERTIES);
+            payload.set_update_properties(action);
+        }
+        PayloadAction::CreateProposal(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::CREATE_PROPOSAL);
+            payload.set_create_proposal(action);
+        }
+        PayloadAction::AnswerProposal(action) => {
+            let response = match action.get_response() {
+                payload::AnswerProposalAction_Response::ACCEPT => {
+                    payload_v2::AnswerProposalV2Action_Response::ACCEPT
+                }
+                payload::AnswerProposalAction_Response::REJECT => {
+                    payload_v2::AnswerProposalV2Action_Response::REJECT
+                }
+                payload::AnswerProposalAction_Response::CANCEL => {
+                    payload_v2::AnswerProposalV2Action_Response::CANCEL
+                }
+                payload::AnswerProposalAction_Response::autoOPEN
+                | payload::AnswerProposalAction_Response::autoCLOSE => {
+                    let mut settle = payload_v2::SettleProposalAction::new();
+                    settle.set_proposal_id(action.get_proposal_id().to_string());
+                    settle.set_dg_coin_amount(action.get_dg_coin_amount());
+                    settle.set_close(
+                        action.get_response() == payload::AnswerProposalAction_Response::autoCLOSE,
+                    );
+                    settle.set_exchanges(action.get_exchanges().iter().cloned().collect());
+                    payload.set_action(payload_v2::PayloadV2_Action::SETTLE_PROPOSAL);
+                    payload.set_settle_proposal(settle);
+                    return payload;
+                }
+            };
+            let mut answer = payload_v2::AnswerProposalV2Action::new();
+            answer.set_proposal_id(action.get_proposal_id().to_string());
+            answer.set_response(response);
+            payload.set_action(payload_v2::PayloadV2_Action::ANSWER_PROPOSAL);
+            payload.set_answer_proposal(answer);
+        }
+        PayloadAction::CounterProposal(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::ANSWER_PROPOSAL);
+            payload.set_answer_proposal(action);
+        }
+        PayloadAction::RevokeReporter(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::REVOKE_REPORTER);
+            payload.set_revoke_reporter(action);
+        }
+        PayloadAction::SettleProposal(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::SETTLE_PROPOSAL);
+            payload.set_settle_proposal(action);
+        }
+        PayloadAction::Migrate(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::MIGRATE);
+            payload.set_migrate(action);
+        }
+        PayloadAction::UpdateParticipant(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::UPDATE_PARTICIPANT);
+            payload.set_update_participant(action);
+        }
+        PayloadAction::RotateKey(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::ROTATE_KEY);
+            payload.set_rotate_key(action);
+        }
+        PayloadAction::SetParticipantStatus(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::SET_PARTICIPANT_STATUS);
+            payload.set_set_participant_status(action);
+        }
+        PayloadAction::SetRoles(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::SET_ROLES);
+            payload.set_set_roles(action);
+        }
+        PayloadAction::CreateOrganization(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::CREATE_ORGANIZATION);
+            payload.set_create_organization(action);
+        }
+        PayloadAction::SetOrganizationMember(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::SET_ORGANIZATION_MEMBER);
+            payload.set_set_organization_member(action);
+        }
+        PayloadAction::CreateMultisigPolicy(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::CREATE_MULTISIG_POLICY);
+            payload.set_create_multisig_policy(action);
+        }
+        PayloadAction::ExpireProposals(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::EXPIRE_PROPOSALS);
+            payload.set_expire_proposals(action);
+        }
+        PayloadAction::SetCreditLimit(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::SET_CREDIT_LIMIT);
+            payload.set_set_credit_limit(action);
+        }
+        PayloadAction::RepayCredit(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::REPAY_CREDIT);
+            payload.set_repay_credit(action);
+        }
+        PayloadAction::WithdrawApproval(action) => {
+            payload.set_action(payload_v2::PayloadV2_Action::WITHDRAW_APPROVAL);
+            payload.set_withdraw_approval(action);
+        }
+    }
+    payload
+}
+
+/// The actions of every family version. Payloads of each version decode to
+/// these, and the handler applies them the same way whatever the version.
+#[derive(Debug, Clone)]
+pub enum PayloadAction {
+    CreateParticipant(payload::CreateParticipantAction),
+    CreateRecord(payload::CreateRecordAction),
+    FinalizeRecord(payload::FinalizeRecordAction),
+    CreateTable(payload::CreateTableAction),
+    UpdateProperties(payload::UpdatePropertiesAction),
+    CreateProposal(payload::CreateProposalAction),
+    AnswerProposal(payload::AnswerProposalAction),
+    RevokeReporter(payload::RevokeReporterAction),
+    SettleProposal(payload_v2::SettleProposalAction),
+    Migrate(payload_v2::MigrateAction),
+    UpdateParticipant(payload_v2::UpdateParticipantAction),
+    RotateKey(payload_v2::RotateKeyAction),
+    SetParticipantStatus(payload_v2::SetParticipantStatusAction),
+    SetRoles(payload_v2::SetRolesAction),
+    CreateOrganization(payload_v2::CreateOrganizationAction),
+    SetOrganizationMember(payload_v2::SetOrganizationMemberAction),
+    CreateMultisigPolicy(payload_v2::CreateMultisigPolicyAction),
+    ExpireProposals(payload_v2::ExpireProposalsAction),
+    SetCreditLimit(payload_v2::SetCreditLimitAction),
+    RepayCredit(payload_v2::RepayCreditAction),
+    WithdrawApproval(payload_v2::WithdrawApprovalAction),
+    /// A COUNTER answer, which unlike the other answers has no version 1.1
+    /// form.
+    CounterProposal(payload_v2::AnswerProposalV2Action),
+}
+
+impl PayloadAction {
+    /// The action of a version 1.1 payload.
+    pub fn from_v1(payload: &payload::PayloadDGC) -> PayloadAction {
+        match payload.get_action() {
+            payload::PayloadDGC_Action::CREATE_PARTICIPANT => {
+                PayloadAction::CreateParticipant(payload.get_create_participant().clone())
+            }
+            payload::PayloadDGC_Action::CREATE_RECORD => {
+                PayloadAction::CreateRecord(payload.get_create_record().clone())
+            }
+            payload::PayloadDGC_Action::FINALIZE_RECORD => {
+                PayloadAction::FinalizeRecord(payload.get_finalize_record().clone())
+            }
+            payload::PayloadDGC_Action::CREATE_TABLE => {
+                PayloadAction::CreateTable(payload.get_create_table().clone())
+            }
+            payload::PayloadDGC_Action::UPDATE_PROPERTIES => {
+                PayloadAction::UpdateProperties(payload.get_update_properties().clone())
+            }
+            payload::PayloadDGC_Action::CREATE_PROPOSAL => {
+                PayloadAction::CreateProposal(payload.get_create_proposal().clone())
+            }
+            payload::PayloadDGC_Action::ANSWER_PROPOSAL => {
+                PayloadAction::AnswerProposal(payload.get_answer_proposal().clone())
+            }
+            payload::PayloadDGC_Action::REVOKE_REPORTER => {
+                PayloadAction::RevokeReporter(payload.get_revoke_reporter().clone())
+            }
+        }
+    }
+
+    /// The action of a version 2 payload.
+    pub fn from_v2(payload: &payload_v2::PayloadV2) -> PayloadAction {
+        match payload.get_action() {
+            payload_v2::PayloadV2_Action::CREATE_PARTICIPANT => {
+                PayloadAction::CreateParticipant(payload.get_create_participant().clone())
+            }
+            payload_v2::PayloadV2_Action::CREATE_RECORD => {
+                PayloadAction::CreateRecord(payload.get_create_record().clone())
+            }
+            payload_v2::PayloadV2_Action::FINALIZE_RECORD => {
+                PayloadAction::FinalizeRecord(payload.get_finalize_record().clone())
+            }
+            payload_v2::PayloadV2_Action::CREATE_TABLE => {
+                PayloadAction::CreateTable(payload.get_create_table().clone())
+            }
+            payload_v2::PayloadV2_Action::UPDATE_PROPERTIES => {
+                PayloadAction::UpdateProperties(payload.get_update_properties().clone())
+            }
+            payload_v2::PayloadV2_Action::CREATE_PROPOSAL => {
+                PayloadAction::CreateProposal(payload.get_create_proposal().clone())
+            }
+            payload_v2::PayloadV2_Action::ANSWER_PROPOSAL => {
+                let answer = payload.get_answer_proposal();
+                let response = match answer.get_response() {
+                    payload_v2::AnswerProposalV2Action_Response::ACCEPT => {
+                        payload::AnswerProposalAction_Response::ACCEPT
+                    }
+                    payload_v2::AnswerProposalV2Action_Response::REJECT => {
+                        payload::AnswerProposalAction_Response::REJECT
+                    }
+                    payload_v2::AnswerProposalV2Action_Response::CANCEL => {
+                        payload::AnswerProposalAction_Response::CANCEL
+                    }
+                    payload_v2::AnswerProposalV2Action_Response::COUNTER => {
+                        return PayloadAction::CounterProposal(answer.clone())
+                    }
+                };
+                let mut action = payload::AnswerProposalAction::new();
+                action.set_proposal_id(answer.get_proposal_id().to_string());
+                action.set_response(response);
+                PayloadAction::AnswerProposal(action)
+            }
+            payload_v2::PayloadV2_Action::REVOKE_REPORTER => {
+                PayloadAction::RevokeReporter(payload.get_revoke_reporter().clone())
+            }
+            payload_v2::PayloadV2_Action::SETTLE_PROPOSAL => {
+                PayloadAction::SettleProposal(payload.get_settle_proposal().clone())
+            }
+            payload_v2::PayloadV2_Action::MIGRATE => {
+                PayloadAction::Migrate(payload.get_migrate().clone())
+            }
+            payload_v2::PayloadV2_Action::UPDATE_PARTICIPANT => {
+                PayloadAction::UpdateParticipant(payload.get_update_participant().clone())
+            }
+            payload_v2::PayloadV2_Action::ROTATE_KEY => {
+                PayloadAction::RotateKey(payload.get_rotate_key().clone())
+            }
+            payload_v2::PayloadV2_Action::SET_PARTICIPANT_STATUS => {
+                PayloadAction::SetParticipantStatus(payload.get_set_participant_status().clone())
+            }
+            payload_v2::PayloadV2_Action::SET_ROLES => {
+                PayloadAction::SetRoles(payload.get_set_roles().clone())
+            }
+            payload_v2::PayloadV2_Action::CREATE_ORGANIZATION => {
+                PayloadAction::CreateOrganization(payload.get_create_organization().clone())
+            }
+            payload_v2::PayloadV2_Action::SET_ORGANIZATION_MEMBER => {
+                PayloadAction::SetOrganizationMember(payload.get_set_organization_member().clone())
+            }
+            payload_v2::PayloadV2_Action::CREATE_MULTISIG_POLICY => {
+                PayloadAction::CreateMultisigPolicy(payload.get_create_multisig_policy().clone())
+            }
+            payload_v2::PayloadV2_Action::EXPIRE_PROPOSALS => {
+                PayloadAction::ExpireProposals(payload.get_expire_proposals().clone())
+            }
+            payload_v2::PayloadV2_Action::SET_CREDIT_LIMIT => {
+                PayloadAction::SetCreditLimit(payload.get_set_credit_limit().clone())
+            }
+            payload_v2::PayloadV2_Action::REPAY_CREDIT => {
+                PayloadAction::RepayCredit(payload.get_repay_credit().clone())
+            }
+            payload_v2::PayloadV2_Action::WITHDRAW_APPROVAL => {
+                PayloadAction::WithdrawApproval(payload.get_withdraw_approval().clone())
+            }
+        }
+    }
+
+    /// The action name, as in the version 2 `Action` enum.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PayloadAction::CreateParticipant(_) => "CREATE_PARTICIPANT",
+            PayloadAction::CreateRecord(_) => "CREATE_RECORD",
+            PayloadAction::FinalizeRecord(_) => "FINALIZE_RECORD",
+            PayloadAction::CreateTable(_) => "CREATE_TABLE",
+            PayloadAction::UpdateProperties(_) => "UPDATE_PROPERTIES",
+            PayloadAction::CreateProposal(_) => "CREATE_PROPOSAL",
+            PayloadAction::AnswerProposal(_) => "ANSWER_PROPOSAL",
+            PayloadAction::RevokeReporter(_) => "REVOKE_REPORTER",
+            PayloadAction::SettleProposal(_) => "SETTLE_PROPOSAL",
+            PayloadAction::Migrate(_) => "MIGRATE",
+            PayloadAction::UpdateParticipant(_) => "UPDATE_PARTICIPANT",
+            PayloadAction::RotateKey(_) => "ROTATE_KEY",
+            PayloadAction::SetParticipantStatus(_) => "SET_PARTICIPANT_STATUS",
+            PayloadAction::SetRoles(_) => "SET_ROLES",
+            PayloadAction::CreateOrganization(_) => "CREATE_ORGANIZATION",
+            PayloadAction::SetOrganizationMember(_) => "SET_ORGANIZATION_MEMBER",
+            PayloadAction::CreateMultisigPolicy(_) => "CREATE_MULTISIG_POLICY",
+            PayloadAction::ExpireProposals(_) => "EXPIRE_PROPOSALS",
+            PayloadAction::SetCreditLimit(_) => "SET_CREDIT_LIMIT",
+            PayloadAction::RepayCredit(_) => "REPAY_CREDIT",
+            PayloadAction::WithdrawApproval(_) => "WITHDRAW_APPROVAL",
+            PayloadAction::CounterProposal(_) => "ANSWER_PROPOSAL",
+        }
+    }
+
+    pub fn record_id(&self) -> Option<String> {
+        let record_id = match *self {
+            PayloadAction::CreateRecord(ref action) => action.get_record_id(),
+            PayloadAction::FinalizeRecord(ref action) => action.get_record_id(),
+            PayloadAction::UpdateProperties(ref action) => action.get_record_id(),
+            PayloadAction::CreateProposal(ref action) => action.get_record_id(),
+            PayloadAction::AnswerProposal(ref action) => action.get_record_id(),
+            PayloadAction::RevokeReporter(ref action) => action.get_record_id(),
+            _ => "",
+        };
+        if record_id == "" {
+            None
+        } else {
+            Some(record_id.to_string())
+        }
+    }
+
+    pub fn proposal_id(&self) -> Option<String> {
+        match *self {
+            PayloadAction::CreateProposal(ref action) => Some(action.get_proposal_id().to_string()),
+            PayloadAction::AnswerProposal(ref action) => Some(action.get_proposal_id().to_string()),
+            PayloadAction::SettleProposal(ref action) => Some(action.get_proposal_id().to_string()),
+            PayloadAction::CounterProposal(ref action) => Some(action.get_proposal_id().to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// The version 1.1 answer a settlement stands for: autoCLOSE when it closes
+/// the proposal, autoOPEN otherwise.
+pub fn settlement_answer(settle: &payload_v2::SettleProposalAction) -> payload::AnswerProposalAction {
+    let mut answer = payload::AnswerProposalAction::new();
+    answer.set_proposal_id(settle.get_proposal_id().to_string());
+    answer.set_response(if settle.get_close() {
+        payload::AnswerProposalAction_Response::autoCLOSE
+    } else {
+        payload::AnswerProposalAction_Response::autoOPEN
+    });
+    answer.set_dg_coin_amount(settle.get_dg_coin_amount());
+    answer.set_exchanges(settle.get_exchanges().iter().cloned().collect());
+    answer
+}
+
+/// Decodes a payload of the given family version. Returns None for a
+/// version the handler does not accept.
+pub fn parse_payload(
+    family_version: &str,
+    bytes: &[u8],
+) -> Option<Result<(PayloadAction, u64), protobuf::ProtobufError>> {
+    match family_version {
+        FAMILY_VERSION => Some(
+            protobuf::parse_from_bytes::<payload::PayloadDGC>(bytes)
+                .map(|payload| (PayloadAction::from_v1(&payload), payload.get_timestamp())),
+        ),
+        FAMILY_VERSION_2 => Some(
+            protobuf::parse_from_bytes::<payload_v2::PayloadV2>(bytes)
+                .map(|payload| (PayloadAction::from_v2(&payload), payload.get_timestamp())),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns the state addresses the handler reads and writes for the action,
+/// taken by the participant `signer`, which is the public key the
+/// participant registered with even if another key signs for it. Property
+/// addresses are given as the record's property range, since the property
+/// names of a record are only known from its table in state, and every
+/// organization, multisig policy and pending approval is given for actions
+/// on a record, since whether its owner or custodian is an organization or a
+/// policy is only known from the record.
+pub fn make_addresses(family: &Family, action: &PayloadAction, signer: &str) -> Vec<String> {
+    let ns = family.namespace();
+    let mut addresses = Vec::new();
+    match *action {
+        PayloadAction::CreateParticipant(ref action) => {
+            addresses.push(make_participant_address(ns, signer));
+            addresses.push(make_organization_address(ns, signer));
+            addresses.push(make_multisig_policy_address(ns, signer));
+            if action.get_email() != "" {
+                addresses.push(make_email_index_address(ns, action.get_email()));
+            }
+        }
+        PayloadAction::CreateRecord(ref action) => {
+            addresses.push(make_participant_address(ns, signer));
+            addresses.push(make_record_address(ns, action.get_record_id()));
+            addresses.push(make_table_address(ns, action.get_table()));
+            addresses.push(make_property_address_range(ns, action.get_record_id()));
+        }
+        PayloadAction::FinalizeRecord(ref action) => {
+            addresses.push(make_record_address(ns, action.get_record_id()));
+            addresses.extend(shared_party_prefixes(ns));
+        }
+        PayloadAction::CreateTable(ref action) => {
+            addresses.push(make_table_address(ns, action.get_name()));
+        }
+        PayloadAction::UpdateProperties(ref action) => {
+            addresses.push(make_record_address(ns, action.get_record_id()));
+            addresses.push(make_property_address_range(ns, action.get_record_id()));
+        }
+        PayloadAction::CreateProposal(ref action) => {
+            addresses.push(make_participant_address(ns, signer));
+            addresses.push(make_proposal_address(ns, action.get_proposal_id()));
+            if action.get_receiving_participant() != "" {
+                addresses.push(make_participant_address(ns, action.get_receiving_participant()));
+            }
+            if action.get_record_id() != "" {
+                addresses.push(make_record_address(ns, action.get_record_id()));
+                addresses.extend(shared_party_prefixes(ns));
+            }
+        }
+        PayloadAction::AnswerProposal(_)
+        | PayloadAction::SettleProposal(_)
+        | PayloadAction::CounterProposal(_) => {
+            // Accepting a transfer of ownership reads the record's table and
+            // exchanges are keyed by proposal ids carried in the payload, so
+            // the whole namespace is declared, as the server does.
+            addresses.push(ns.to_string());
+        }
+        PayloadAction::RevokeReporter(ref action) => {
+            addresses.push(make_record_address(ns, action.get_record_id()));
+            addresses.extend(shared_party_prefixes(ns));
+            for property_name in action.get_properties() {
+                addresses.push(make_property_address(ns, action.get_record_id(), property_name, 0));
+            }
+        }
+        PayloadAction::Migrate(ref action) => {
+            let cursor = action.get_cursor() as usize;
+            if cursor < action.get_addresses().len() {
+                addresses.extend(action.get_addresses()[cursor..].iter().cloned());
+            }
+        }
+        PayloadAction::UpdateParticipant(_) => {
+            // The email being replaced is only known from state, so every
+            // email index entry is declared.
+            addresses.push(make_participant_address(ns, signer));
+            addresses.push(ns.to_string() + EMAIL_INDEX);
+        }
+        PayloadAction::RotateKey(ref action) => {
+            addresses.push(make_participant_address(ns, action.get_participant_id()));
+            addresses.push(make_participant_address(ns, action.get_new_public_key()));
+            addresses.push(make_key_alias_address(ns, action.get_new_public_key()));
+        }
+        PayloadAction::SetParticipantStatus(ref action) => {
+            addresses.push(make_participant_address(ns, action.get_participant_id()));
+        }
+        PayloadAction::SetRoles(ref action) => {
+            addresses.push(make_role_address(ns, action.get_public_key()));
+        }
+        PayloadAction::CreateOrganization(ref action) => {
+            addresses.push(make_organization_address(ns, action.get_organization_id()));
+            addresses.push(make_participant_address(ns, action.get_organization_id()));
+            addresses.push(make_multisig_policy_address(ns, action.get_organization_id()));
+            addresses.push(make_participant_address(ns, signer));
+        }
+        PayloadAction::SetOrganizationMember(ref action) => {
+            addresses.push(make_organization_address(ns, action.get_organization_id()));
+            addresses.push(make_participant_address(ns, action.get_participant_id()));
+        }
+        PayloadAction::CreateMultisigPolicy(ref action) => {
+            addresses.push(make_multisig_policy_address(ns, action.get_policy_id()));
+            addresses.push(make_participant_address(ns, action.get_policy_id()));
+            addresses.push(make_organization_address(ns, action.get_policy_id()));
+            for signer in action.get_signers() {
+                addresses.push(make_participant_address(ns, signer));
+            }
+        }
+        PayloadAction::ExpireProposals(ref action) => {
+            for proposal_id in action.get_proposal_ids() {
+                addresses.push(make_proposal_address(ns, proposal_id));
+            }
+        }
+        PayloadAction::SetCreditLimit(ref action) => {
+            addresses.push(make_participant_address(ns, action.get_participant_id()));
+        }
+        PayloadAction::RepayCredit(_) => {
+            addresses.push(make_participant_address(ns, signer));
+        }
+        PayloadAction::WithdrawApproval(ref action) => {
+            addresses.push(make_pending_approval_address(ns, action.get_approval_id()));
+        }
+    }
+    addresses
+}
+
+/// The prefixes of the entities which can stand for participants as owners
+/// and custodians of records, of the approvals multisig policies collect and
+/// of the participants whose status is checked when they are counted.
+fn shared_party_prefixes(namespace: &str) -> Vec<String> {
+    vec![
+        namespace.to_string() + ORGANIZATION,
+        namespace.to_string() + MULTISIG_POLICY,
+        namespace.to_string() + PENDING_APPROVAL,
+        namespace.to_string() + PARTICIPANT,
+    ]
+}
+
+/// The addresses the handler reads for an action signed by `signer` for
+/// `participant`: those it writes, plus the participant, its roles and the
+/// signer's key alias it checks the signer against, the settings it takes
+/// its limits, administrators, role enforcement and approval lifetime from
+/// and the BlockInfo state it checks timestamps with.
+pub fn make_inputs(
+    family: &Family,
+    action: &PayloadAction,
+    signer: &str,
+    participant: &str,
+) -> Vec<String> {
+    let mut inputs = make_addresses(family, action, participant);
+    inputs.push(make_participant_address(family.namespace(), participant));
+    inputs.push(make_key_alias_address(family.namespace(), signer));
+    inputs.push(make_role_address(family.namespace(), participant));
+    inputs.extend(Limits::setting_addresses());
+    inputs.push(make_setting_address(ADMINS_KEY));
+    inputs.push(make_setting_address(ENFORCE_KEY));
+    inputs.push(make_setting_address(APPROVAL_LIFETIME_KEY));
+    inputs.extend(block_time_addresses());
+    inputs
+}
+
+/// Builds a transaction of `family` carrying a version 1.1 payload. The
+/// `participant` the signer acts for defaults to the signer's own key, and
+/// only needs giving for a key which was rotated in.
+pub fn create_transaction(
+    family: &Family,
+    payload: &payload::PayloadDGC,
+    signer: &signing::Signer,
+    participant: Option<&str>,
+    batcher_public_key: Option<&str>,
+) -> Result<Transaction, TransactionError> {
+    build_transaction(
+        family,
+        FAMILY_VERSION,
+        &PayloadAction::from_v1(payload),
+        payload.write_to_bytes()?,
+        signer,
+        participant,
+        batcher_public_key,
+    )
+}
+
+/// Builds a transaction of `family` carrying a version 2 payload, like
+/// `create_transaction`.
+pub fn create_transaction_v2(
+    family: &Family,
+    payload: &payload_v2::PayloadV2,
+    signer: &signing::Signer,
+    participant: Option<&str>,
+    batcher_public_key: Option<&str>,
+) -> Result<Transaction, TransactionError> {
+    build_transaction(
+        family,
+        FAMILY_VERSION_2,
+        &PayloadAction::from_v2(payload),
+        payload.write_to_bytes()?,
+        signer,
+        participant,
+        batcher_public_key,
+    )
+}
+
+fn build_transaction(
+    family: &Family,
+    family_version: &str,
+    action: &PayloadAction,
+    payload_bytes: Vec<u8>,
+    signer: &signing::Signer,
+    participant: Option<&str>,
+    batcher_public_key: Option<&str>,
+) -> Result<Transaction, TransactionError> {
+    let public_key = signer.get_public_key()?.as_hex();
+    let participant = participant.unwrap_or(&public_key);
+    let inputs = make_inputs(family, action, &public_key, participant);
+    let outputs = make_addresses(family, action, participant);
+
+    let mut sha = Sha512::new();
+    sha.input(&payload_bytes);
+
+    let mut header = TransactionHeader::new();
+    header.set_family_name(family.name().to_string());
+    header.set_family_version(family_version.to_string());
+    header.set_nonce(make_nonce());
+    header.set_signer_public_key(public_key.clone());
+    header.set_batcher_public_key(match batcher_public_key {
+        Some(key) => key.to_string(),
+        None => public_key.clone(),
+    });
+    header.set_inputs(RepeatedField::from_vec(inputs));
+    header.set_outputs(RepeatedField::from_vec(outputs));
+    header.set_payload_sha512(sha.result_str());
+
+    let header_bytes = header.write_to_bytes()?;
+    let header_signature = signer.sign(&header_bytes)?;
+
+    let mut transaction = Transaction::new();
+    transaction.set_header(header_bytes);
+    transaction.set_header_signature(header_signature);
+    transaction.set_payload(payload_bytes);
+    Ok(transaction)
+}
+
+pub fn create_batch(
+    transactions: Vec<Transaction>,
+    signer: &signing::Signer,
+) -> Result<Batch, TransactionError> {
+    let public_key = signer.get_public_key()?.as_hex();
+    let transaction_ids = transactions
+        .iter()
+        .map(|t| t.get_header_signature().to_string())
+        .collect::<Vec<String>>();
+
+    let mut header = BatchHeader::new();
+    header.set_signer_public_key(public_key);
+    header.set_transaction_ids(RepeatedField::from_vec(transaction_ids));
+
+    let header_bytes = header.write_to_bytes()?;
+    let header_signature = signer.sign(&header_bytes)?;
+
+    let mut batch = Batch::new();
+    batch.set_header(header_bytes);
+    batch.set_header_signature(header_signature);
+    batch.set_transactions(RepeatedField::from_vec(transactions));
+    Ok(batch)
+}
+
+pub fn create_batch_list(batches: Vec<Batch>) -> BatchList {
+    let mut batch_list = BatchList::new();
+    batch_list.set_batches(RepeatedField::from_vec(batches));
+    batch_list
+}
+
+fn make_nonce() -> String {
+    let mut rng = rand::thread_rng();
+    format!("{:016x}", rng.gen::<u64>())
+}
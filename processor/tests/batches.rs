@@ -0,0 +1,272 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the transactions and batches built offline by the `dgc` CLI:
+//! their headers and signatures, and that the inputs and outputs they
+//! declare cover everything the handler reads and writes.
+
+extern crate crypto;
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use std::collections::HashMap;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha512;
+use protobuf::RepeatedField;
+
+use sawtooth_sdk::messages::batch::BatchHeader;
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::transaction::{Transaction, TransactionHeader};
+use sawtooth_sdk::processor::handler::{ContextError, TransactionContext, TransactionHandler};
+use sawtooth_sdk::signing;
+use sawtooth_sdk::signing::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
+
+use dgc_rest_api_tp::addressing::*;
+use dgc_rest_api_tp::context::MemoryContext;
+use dgc_rest_api_tp::handler::{StateDGC, TransactionHandlerDGC};
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::*;
+
+use common::{set_block_time, TIMESTAMP};
+
+/// A context which, like the validator, refuses reads and writes outside
+/// the transaction's declared inputs and outputs.
+struct ScopedContext<'a> {
+    state: &'a mut MemoryContext,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+fn covers(prefixes: &[String], address: &str) -> bool {
+    prefixes.iter().any(|prefix| address.starts_with(prefix.as_str()))
+}
+
+impl<'a> TransactionContext for ScopedContext<'a> {
+    fn get_state(&mut self, addresses: Vec<String>) -> Result<Option<Vec<u8>>, ContextError> {
+        for address in &addresses {
+            if !covers(&self.inputs, address) {
+                return Err(ContextError::AuthorizationError(format!("read {}", address)));
+            }
+        }
+        self.state.get_state(addresses)
+    }
+
+    fn set_state(&mut self, entries: HashMap<String, Vec<u8>>) -> Result<(), ContextError> {
+        for address in entries.keys() {
+            if !covers(&self.outputs, address) {
+                return Err(ContextError::AuthorizationError(format!("write {}", address)));
+            }
+        }
+        self.state.set_state(entries)
+    }
+
+    fn delete_state(
+        &mut self,
+        addresses: Vec<String>,
+    ) -> Result<Option<Vec<String>>, ContextError> {
+        for address in &addresses {
+            if !covers(&self.outputs, address) {
+                return Err(ContextError::AuthorizationError(format!("delete {}", address)));
+            }
+        }
+        self.state.delete_state(addresses)
+    }
+
+    fn add_receipt_data(&mut self, data: &[u8]) -> Result<(), ContextError> {
+        self.state.add_receipt_data(data)
+    }
+
+    fn add_event(
+        &mut self,
+        event_type: String,
+        attributes: Vec<(String, String)>,
+        data: &[u8],
+    ) -> Result<(), ContextError> {
+        self.state.add_event(event_type, attributes, data)
+    }
+}
+
+fn private_key(index: u64) -> Secp256k1PrivateKey {
+    Secp256k1PrivateKey::from_hex(&format!("{:064x}", index + 1)).expect("key parses")
+}
+
+fn header(transaction: &Transaction) -> TransactionHeader {
+    protobuf::parse_from_bytes(transaction.get_header()).expect("header decodes")
+}
+
+/// Applies a transaction the way a validator would hand it to the handler.
+fn apply(state: &mut MemoryContext, transaction: &Transaction) -> Result<(), String> {
+    let header = header(transaction);
+    let mut request = TpProcessRequest::new();
+    request.set_header(header.clone());
+    request.set_payload(transaction.get_payload().to_vec());
+    request.set_signature(transaction.get_header_signature().to_string());
+
+    let snapshot = state.clone();
+    let result = {
+        let mut context = ScopedContext {
+            state,
+            inputs: header.get_inputs().to_vec(),
+            outputs: header.get_outputs().to_vec(),
+        };
+        TransactionHandlerDGC::new().apply(&request, &mut context)
+    };
+    if result.is_err() {
+        *state = snapshot;
+    }
+    result.map_err(|err| format!("{:?}", err))
+}
+
+#[test]
+fn transactions_are_signed_and_describe_their_payload() {
+    let context = signing::create_context("secp256k1").unwrap();
+    let key = private_key(0);
+    let factory = signing::CryptoFactory::new(&*context);
+    let signer = factory.new_signer(&key);
+    let public_key = signer.get_public_key().unwrap().as_hex();
+
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name("alice".to_string());
+    let action = PayloadAction::CreateParticipant(action);
+    let family = Family::default();
+
+    let v1_payload = make_payload(action.clone(), 7).unwrap();
+    let v1 = create_transaction(&family, &v1_payload, &signer, None, None).unwrap();
+    let v2_payload = make_payload_v2(action, 7);
+    let v2 = create_transaction_v2(&family, &v2_payload, &signer, None, None).unwrap();
+
+    for &(ref transaction, version) in [(v1, FAMILY_VERSION), (v2, FAMILY_VERSION_2)].iter() {
+        let header = header(transaction);
+        assert_eq!(header.get_family_name(), FAMILY_NAME);
+        assert_eq!(header.get_family_version(), version);
+        assert_eq!(header.get_signer_public_key(), public_key);
+        assert_eq!(header.get_batcher_public_key(), public_key);
+
+        let mut sha = Sha512::new();
+        sha.input(transaction.get_payload());
+        assert_eq!(header.get_payload_sha512(), sha.result_str());
+
+        let verifier = Secp256k1PublicKey::from_hex(&public_key).unwrap();
+        assert!(context
+            .verify(transaction.get_header_signature(), transaction.get_header(), &verifier)
+            .unwrap());
+
+        match parse_payload(version, transaction.get_payload()) {
+            Some(Ok((PayloadAction::CreateParticipant(decoded), timestamp))) => {
+                assert_eq!(decoded.get_name(), "alice");
+                assert_eq!(timestamp, 7);
+            }
+            other => panic!("payload did not decode: {:?}", other.is_some()),
+        }
+
+        let participant = make_participant_address(family.namespace(), &public_key);
+        assert!(header.get_outputs().contains(&participant));
+        for output in header.get_outputs() {
+            assert!(header.get_inputs().contains(output));
+        }
+    }
+}
+
+#[test]
+fn batches_list_and_sign_their_transactions() {
+    let context = signing::create_context("secp256k1").unwrap();
+    let key = private_key(0);
+    let factory = signing::CryptoFactory::new(&*context);
+    let signer = factory.new_signer(&key);
+
+    let transactions: Vec<Transaction> = (0..3)
+        .map(|index| {
+            let mut action = payload::CreateTableAction::new();
+            action.set_name(format!("table-{}", index));
+            let payload = make_payload_v2(PayloadAction::CreateTable(action), index);
+            create_transaction_v2(&Family::default(), &payload, &signer, None, None).unwrap()
+        })
+        .collect();
+    let batch = create_batch(transactions.clone(), &signer).unwrap();
+
+    let header: BatchHeader = protobuf::parse_from_bytes(batch.get_header()).unwrap();
+    let ids: Vec<&str> = transactions.iter().map(|t| t.get_header_signature()).collect();
+    assert_eq!(header.get_transaction_ids(), &ids[..]);
+    assert_eq!(batch.get_transactions(), &transactions[..]);
+
+    let public_key = signer.get_public_key().unwrap().as_hex();
+    let verifier = Secp256k1PublicKey::from_hex(&public_key).unwrap();
+    assert!(context
+        .verify(batch.get_header_signature(), batch.get_header(), &verifier)
+        .unwrap());
+
+    let batch_list = create_batch_list(vec![batch.clone(), batch]);
+    assert_eq!(batch_list.get_batches().len(), 2);
+}
+
+#[test]
+fn declared_addresses_cover_what_the_handler_touches() {
+    let context = signing::create_context("secp256k1").unwrap();
+    let (owner_key, buyer_key) = (private_key(0), private_key(1));
+    let factory = signing::CryptoFactory::new(&*context);
+    let owner = factory.new_signer(&owner_key);
+    let buyer = factory.new_signer(&buyer_key);
+    let buyer_id = buyer.get_public_key().unwrap().as_hex();
+    let family = Family::default();
+    let mut state = MemoryContext::new();
+    set_block_time(&mut state, 1, TIMESTAMP);
+
+    let mut weight = property::PropertySchema::new();
+    weight.set_name("weight".to_string());
+    weight.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![weight]));
+
+    let mut value = property::PropertyValue::new();
+    value.set_name("weight".to_string());
+    value.set_data_type(property::PropertySchema_DataType::NUMBER);
+    value.set_number_value(12);
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    record.set_properties(RepeatedField::from_vec(vec![value]));
+
+    let mut proposal = payload::CreateProposalAction::new();
+    proposal.set_proposal_id("sale".to_string());
+    proposal.set_role(proposal::Proposal_Role::transferOwnership);
+    proposal.set_record_id("crate-1".to_string());
+    proposal.set_receiving_participant(buyer_id.clone());
+
+    let mut accept = payload::AnswerProposalAction::new();
+    accept.set_proposal_id("sale".to_string());
+    accept.set_response(payload::AnswerProposalAction_Response::ACCEPT);
+
+    let mut owner_participant = payload::CreateParticipantAction::new();
+    owner_participant.set_name("owner".to_string());
+    owner_participant.set_email("owner@example.com".to_string());
+    let mut buyer_participant = payload::CreateParticipantAction::new();
+    buyer_participant.set_name("buyer".to_string());
+
+    let steps = vec![
+        (&owner, PayloadAction::CreateParticipant(owner_participant)),
+        (&buyer, PayloadAction::CreateParticipant(buyer_participant)),
+        (&owner, PayloadAction::CreateTable(table)),
+        (&owner, PayloadAction::CreateRecord(record)),
+        (&owner, PayloadAction::CreateProposal(proposal)),
+        (&buyer, PayloadAction::AnswerProposal(accept)),
+    ];
+    for (index, (signer, action)) in steps.into_iter().enumerate() {
+        let name = action.name();
+        let payload = make_payload_v2(action, TIMESTAMP + index as u64);
+        let transaction = create_transaction_v2(&family, &payload, signer, None, None).unwrap();
+        if let Err(err) = apply(&mut state, &transaction) {
+            panic!("{} was refused: {}", name, err);
+        }
+    }
+
+    let record = StateDGC::new(&mut state, family.namespace())
+        .get_record("crate-1")
+        .unwrap()
+        .expect("record exists");
+    assert_eq!(record.get_owners().last().unwrap().get_participant_id(), buyer_id);
+}
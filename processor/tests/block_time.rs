@@ -0,0 +1,132 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of payload timestamps against the BlockInfo block time and the
+//! `dgc.timestamp.max_skew_seconds` window, and of the refusal of
+//! transactions on networks without BlockInfo.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::block_time::*;
+use dgc_rest_api_tp::context::MemoryContext;
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, request, set_block_time, set_setting, Chain, TIMESTAMP};
+
+const BLOCK_TIME: u64 = 1_600_000_000;
+
+#[test]
+fn timestamps_are_refused_without_block_info() {
+    let mut context = MemoryContext::new();
+    match check_timestamp(&mut context, BLOCK_TIME) {
+        Err(ApplyError::InvalidTransaction(msg)) => {
+            assert_eq!(msg, "BlockInfo is not available to check the timestamp against");
+        }
+        other => panic!("timestamp was accepted without BlockInfo: {:?}", other),
+    }
+    set_setting(&mut context, WITHOUT_BLOCK_INFO_KEY, "yes");
+    assert!(check_timestamp(&mut context, BLOCK_TIME).is_err());
+
+    // Unless the network is set to run without it
+    set_setting(&mut context, WITHOUT_BLOCK_INFO_KEY, "true");
+    assert_eq!(check_timestamp(&mut context, 0).unwrap(), 0);
+    assert_eq!(check_timestamp(&mut context, BLOCK_TIME).unwrap(), 0);
+    assert_eq!(current_time(TIMESTAMP, 0), TIMESTAMP);
+}
+
+#[test]
+fn the_handler_rejects_transactions_without_block_info() {
+    let mut context = MemoryContext::new();
+    let handler = TransactionHandlerDGC::new();
+    let mut create = payload::CreateParticipantAction::new();
+    create.set_name("owner".to_string());
+    let request = request(&public_key(0), PayloadAction::CreateParticipant(create), TIMESTAMP);
+
+    assert!(context.apply(&handler, &request).is_err());
+    assert!(context.state().is_empty());
+    set_setting(&mut context, WITHOUT_BLOCK_INFO_KEY, "true");
+    context.apply(&handler, &request).unwrap();
+}
+
+#[test]
+fn the_default_window_is_inclusive() {
+    let mut context = MemoryContext::new();
+    set_block_time(&mut context, 7, BLOCK_TIME);
+
+    for timestamp in &[BLOCK_TIME, BLOCK_TIME - DEFAULT_MAX_SKEW, BLOCK_TIME + DEFAULT_MAX_SKEW] {
+        assert_eq!(check_timestamp(&mut context, *timestamp).unwrap(), BLOCK_TIME);
+    }
+    for timestamp in &[BLOCK_TIME - DEFAULT_MAX_SKEW - 1, BLOCK_TIME + DEFAULT_MAX_SKEW + 1] {
+        assert!(check_timestamp(&mut context, *timestamp).is_err());
+    }
+    assert_eq!(current_time(TIMESTAMP, BLOCK_TIME), BLOCK_TIME);
+}
+
+#[test]
+fn the_window_follows_the_setting() {
+    let mut context = MemoryContext::new();
+    set_block_time(&mut context, 7, BLOCK_TIME);
+    set_setting(&mut context, MAX_SKEW_KEY, "10");
+
+    assert!(check_timestamp(&mut context, BLOCK_TIME + 10).is_ok());
+    assert!(check_timestamp(&mut context, BLOCK_TIME - 10).is_ok());
+    assert!(check_timestamp(&mut context, BLOCK_TIME + 11).is_err());
+    assert!(check_timestamp(&mut context, BLOCK_TIME - 11).is_err());
+
+    set_setting(&mut context, MAX_SKEW_KEY, "0");
+    assert!(check_timestamp(&mut context, BLOCK_TIME).is_ok());
+    assert!(check_timestamp(&mut context, BLOCK_TIME + 1).is_err());
+}
+
+#[test]
+fn an_invalid_setting_falls_back_to_the_default() {
+    for invalid in &["soon", "-5", ""] {
+        let mut context = MemoryContext::new();
+        set_block_time(&mut context, 7, BLOCK_TIME);
+        set_setting(&mut context, MAX_SKEW_KEY, invalid);
+
+        assert!(check_timestamp(&mut context, BLOCK_TIME + DEFAULT_MAX_SKEW).is_ok());
+        assert!(check_timestamp(&mut context, BLOCK_TIME + DEFAULT_MAX_SKEW + 1).is_err());
+    }
+}
+
+#[test]
+fn the_handler_rejects_skewed_transactions_and_records_block_time() {
+    let mut chain = Chain::new();
+    let owner = public_key(0);
+    chain.block_time = Some(TIMESTAMP + 4);
+    set_setting(&mut chain.context, MAX_SKEW_KEY, "2");
+
+    // The chain's timestamps start at TIMESTAMP + 1, three seconds early
+    let mut create = payload::CreateParticipantAction::new();
+    create.set_name("owner".to_string());
+    assert!(chain.apply(&owner, PayloadAction::CreateParticipant(create.clone())).is_err());
+    assert!(chain.apply(&owner, PayloadAction::CreateParticipant(create)).is_ok());
+
+    let mut weight = property::PropertySchema::new();
+    weight.set_name("weight".to_string());
+    weight.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![weight]));
+    chain.apply(&owner, PayloadAction::CreateTable(table)).unwrap();
+
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(&owner, PayloadAction::CreateRecord(record)).unwrap();
+
+    let created = chain.record("crate-1").expect("record exists");
+    let first_owner = &created.get_owners()[0];
+    assert_eq!(first_owner.get_timestamp(), chain.timestamp);
+    assert_eq!(first_owner.get_block_timestamp(), TIMESTAMP + 4);
+}
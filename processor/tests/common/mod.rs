@@ -0,0 +1,193 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers shared by the integration tests for building requests and
+//! seeding in-memory state.
+
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, HashMap};
+
+use protobuf::{Message, RepeatedField};
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::messages::setting::{Setting, Setting_Entry};
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext};
+
+use dgc_rest_api_tp::addressing::{
+    make_block_info_address, make_block_info_config_address, make_setting_address, Family,
+};
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::handler::{StateDGC, TransactionHandlerDGC};
+use dgc_rest_api_tp::messages::block_info::{BlockInfo, BlockInfoConfig};
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::{
+    make_payload, make_payload_v2, PayloadAction, FAMILY_VERSION, FAMILY_VERSION_2,
+};
+
+pub const TIMESTAMP: u64 = 1_500_000_000;
+
+pub fn public_key(index: usize) -> String {
+    format!("02{:064x}", index + 1)
+}
+
+/// Writes an on-chain setting the way the settings family stores it.
+pub fn set_setting(context: &mut MemoryContext, key: &str, value: &str) {
+    let mut entry = Setting_Entry::new();
+    entry.set_key(key.to_string());
+    entry.set_value(value.to_string());
+    let mut setting = Setting::new();
+    setting.set_entries(RepeatedField::from_vec(vec![entry]));
+
+    let mut entries = HashMap::new();
+    entries.insert(
+        make_setting_address(key),
+        setting.write_to_bytes().expect("setting serializes"),
+    );
+    context.set_state(entries).expect("setting is written");
+}
+
+/// Records block `block_num`, committed at `timestamp`, as the latest block
+/// the way the BlockInfo family does.
+pub fn set_block_time(context: &mut MemoryContext, block_num: u64, timestamp: u64) {
+    let mut config = BlockInfoConfig::new();
+    config.set_latest_block(block_num);
+    let mut block = BlockInfo::new();
+    block.set_block_num(block_num);
+    block.set_timestamp(timestamp);
+
+    let mut entries = HashMap::new();
+    entries.insert(
+        make_block_info_config_address(),
+        config.write_to_bytes().expect("config serializes"),
+    );
+    entries.insert(
+        make_block_info_address(block_num),
+        block.write_to_bytes().expect("block info serializes"),
+    );
+    context.set_state(entries).expect("block info is written");
+}
+
+/// An empty in-memory state whose latest block was committed at
+/// `TIMESTAMP`.
+pub fn new_context() -> MemoryContext {
+    let mut context = MemoryContext::new();
+    set_block_time(&mut context, 1, TIMESTAMP);
+    context
+}
+
+/// The entries of `context` under `namespace`, leaving out the settings and
+/// blocks the family reads.
+pub fn family_state(context: &MemoryContext, namespace: &str) -> BTreeMap<String, Vec<u8>> {
+    context
+        .state()
+        .iter()
+        .filter(|&(address, _)| address.starts_with(namespace))
+        .map(|(address, data)| (address.clone(), data.clone()))
+        .collect()
+}
+
+pub fn request(signer: &str, action: PayloadAction, timestamp: u64) -> TpProcessRequest {
+    let payload = make_payload_v2(action, timestamp)
+        .write_to_bytes()
+        .expect("payload serializes");
+    make_process_request(
+        &Family::default(),
+        FAMILY_VERSION_2,
+        signer,
+        &payload,
+        &format!("{:0128x}", timestamp),
+    )
+}
+
+pub fn request_v1(signer: &str, action: PayloadAction, timestamp: u64) -> TpProcessRequest {
+    let payload = make_payload(action, timestamp)
+        .expect("action has a version 1.1 payload")
+        .write_to_bytes()
+        .expect("payload serializes");
+    make_process_request(
+        &Family::default(),
+        FAMILY_VERSION,
+        signer,
+        &payload,
+        &format!("{:0128x}", timestamp),
+    )
+}
+
+/// An in-memory ledger which applies version 2 actions in order, each with
+/// the next timestamp. Each is applied in a block of its own, committed at
+/// `block_time`, or at the transaction's timestamp when that is None.
+pub struct Chain {
+    pub context: MemoryContext,
+    pub handler: TransactionHandlerDGC,
+    pub timestamp: u64,
+    pub block_num: u64,
+    pub block_time: Option<u64>,
+}
+
+impl Chain {
+    pub fn new() -> Chain {
+        Chain::with_handler(TransactionHandlerDGC::new())
+    }
+
+    pub fn with_handler(handler: TransactionHandlerDGC) -> Chain {
+        Chain {
+            context: MemoryContext::new(),
+            handler,
+            timestamp: TIMESTAMP,
+            block_num: 0,
+            block_time: None,
+        }
+    }
+
+    pub fn apply(&mut self, signer: &str, action: PayloadAction) -> Result<(), ApplyError> {
+        self.next_block();
+        let request = request(signer, action, self.timestamp);
+        self.context.apply(&self.handler, &request)
+    }
+
+    pub fn apply_v1(&mut self, signer: &str, action: PayloadAction) -> Result<(), ApplyError> {
+        self.next_block();
+        let request = request_v1(signer, action, self.timestamp);
+        self.context.apply(&self.handler, &request)
+    }
+
+    fn next_block(&mut self) {
+        self.timestamp += 1;
+        self.block_num += 1;
+        let block_time = self.block_time.unwrap_or(self.timestamp);
+        set_block_time(&mut self.context, self.block_num, block_time);
+    }
+
+    pub fn state(&mut self) -> StateDGC {
+        StateDGC::new(&mut self.context, Family::default().namespace())
+    }
+
+    pub fn participant(&mut self, id: &str) -> Option<participant::Participant> {
+        self.state().get_participant(id).expect("participant decodes")
+    }
+
+    pub fn proposal(&mut self, id: &str) -> Option<proposal::Proposal> {
+        self.state().get_proposal(id).expect("proposal decodes")
+    }
+
+    pub fn record(&mut self, id: &str) -> Option<record::Record> {
+        self.state().get_record(id).expect("record decodes")
+    }
+
+    /// Creates a participant signing with `key`.
+    pub fn create_participant(&mut self, key: &str, name: &str) {
+        let mut action = payload::CreateParticipantAction::new();
+        action.set_name(name.to_string());
+        self.apply(key, PayloadAction::CreateParticipant(action))
+            .expect("participant is created");
+    }
+}
+
+/// The latest balance of a participant.
+pub fn balance(participant: &participant::Participant) -> f64 {
+    participant
+        .get_dg_coin_balances()
+        .last()
+        .map(|balance| balance.get_dg_coin_amount())
+        .unwrap_or(0.0)
+}
@@ -0,0 +1,185 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the processor configuration: the TOML file, the `DGC_TP_*`
+//! environment overrides, validation and `--print-config` output.
+
+extern crate dgc_rest_api_tp;
+extern crate log;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+
+use log::LogLevelFilter;
+
+use dgc_rest_api_tp::addressing::{get_dgc_rest_api_prefix, FAMILY_NAME};
+use dgc_rest_api_tp::config::*;
+
+const FULL_CONFIG: &str = r#"
+connect = "tcp://validator:4004"
+metrics_address = "0.0.0.0:9100"
+shutdown_timeout_seconds = 5
+
+[log]
+level = "debug"
+format = "json"
+file = "/var/log/dgc/tp.log"
+max_file_size = 1024
+max_files = 2
+
+[family]
+name = "dgc_test"
+namespace = "abcdef"
+
+[limits]
+max_struct_depth = 8
+
+[reconnect]
+initial_delay_ms = 100
+max_delay_ms = 1000
+"#;
+
+#[test]
+fn an_empty_file_gives_the_defaults() {
+    let config = ProcessorConfig::parse("").unwrap();
+    assert_eq!(config, ProcessorConfig::default());
+    assert_eq!(config.connect, "tcp://localhost:4004");
+    assert!(config.validate().is_ok());
+
+    let family = config.family().unwrap();
+    assert_eq!(family.name(), FAMILY_NAME);
+    assert_eq!(family.namespace(), get_dgc_rest_api_prefix(FAMILY_NAME));
+}
+
+#[test]
+fn files_set_every_section() {
+    let config = ProcessorConfig::parse(FULL_CONFIG).unwrap();
+    assert_eq!(config.connect, "tcp://validator:4004");
+    assert_eq!(config.metrics_address, Some(String::from("0.0.0.0:9100")));
+    assert_eq!(config.health_address, None);
+    assert_eq!(config.shutdown_timeout_seconds, 5);
+    assert_eq!(config.log.format, LogFormat::Json);
+    assert_eq!(config.log.level_filter().unwrap(), LogLevelFilter::Debug);
+    assert_eq!(config.log.max_files, 2);
+    assert_eq!(config.limits.max_struct_depth, 8);
+    assert_eq!(config.limits.max_properties, 64);
+    assert_eq!(config.reconnect.max_delay_ms, 1000);
+    assert!(config.validate().is_ok());
+
+    let family = config.family().unwrap();
+    assert_eq!(family.name(), "dgc_test");
+    assert_eq!(family.namespace(), "abcdef");
+}
+
+#[test]
+fn printed_configs_read_back_the_same() {
+    for config in &[
+        ProcessorConfig::default(),
+        ProcessorConfig::parse(FULL_CONFIG).unwrap(),
+    ] {
+        let printed = config.to_toml().unwrap();
+        assert_eq!(&ProcessorConfig::parse(&printed).unwrap(), config);
+    }
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    for contents in &[
+        "connect = 4004",
+        "unknown = true",
+        "[log]\nlevel = \"info\"\ncolour = true",
+        "[log]\nformat = \"xml\"",
+        "[limits]\nmax_struct_depth = -1",
+        "connect = ",
+    ] {
+        match ProcessorConfig::parse(contents) {
+            Err(ConfigError::ParseError(_)) => (),
+            other => panic!("{:?} was not rejected: {:?}", contents, other),
+        }
+    }
+}
+
+#[test]
+fn invalid_values_fail_validation() {
+    let invalid: Vec<fn(&mut ProcessorConfig)> = vec![
+        |config| config.log.level = String::from("loud"),
+        |config| {
+            config.log.file = Some(String::from("tp.log"));
+            config.log.max_files = 0;
+        },
+        |config| {
+            config.log.file = Some(String::from("tp.log"));
+            config.log.max_file_size = 0;
+        },
+        |config| config.reconnect.initial_delay_ms = 0,
+        |config| config.reconnect.max_delay_ms = config.reconnect.initial_delay_ms - 1,
+        |config| config.family.name = String::new(),
+        |config| config.family.namespace = Some(String::from("ABCDEF")),
+        |config| config.family.namespace = Some(String::from("abc")),
+        |config| config.family.namespace = Some(String::from("000000")),
+    ];
+    for (index, change) in invalid.iter().enumerate() {
+        let mut config = ProcessorConfig::default();
+        change(&mut config);
+        match config.validate() {
+            Err(ConfigError::InvalidValue(_)) => (),
+            other => panic!("change {} passed validation: {:?}", index, other),
+        }
+    }
+
+    // Rotation settings only matter when logging to a file
+    let mut config = ProcessorConfig::default();
+    config.log.max_files = 0;
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn log_formats_and_levels_are_parsed() {
+    assert_eq!(LogFormat::parse("text").unwrap(), LogFormat::Text);
+    assert_eq!(LogFormat::parse("json").unwrap(), LogFormat::Json);
+    assert!(LogFormat::parse("JSON").is_err());
+    assert!(LogFormat::parse("").is_err());
+
+    let mut log = LogConfig::default();
+    assert_eq!(log.level_filter().unwrap(), LogLevelFilter::Warn);
+    for &(level, filter) in &[("off", LogLevelFilter::Off), ("trace", LogLevelFilter::Trace)] {
+        log.level = level.to_string();
+        assert_eq!(log.level_filter().unwrap(), filter);
+    }
+}
+
+#[test]
+fn files_and_the_environment_override_the_defaults() {
+    let path = env::temp_dir().join(format!("dgc-tp-config-{}.toml", std::process::id()));
+    File::create(&path)
+        .and_then(|mut file| file.write_all(FULL_CONFIG.as_bytes()))
+        .unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    let mut config = ProcessorConfig::load(Some(&path)).unwrap();
+    assert_eq!(config, ProcessorConfig::parse(FULL_CONFIG).unwrap());
+    match ProcessorConfig::from_file(&format!("{}.missing", path)) {
+        Err(ConfigError::IoError(_)) => (),
+        other => panic!("missing file was read: {:?}", other),
+    }
+
+    env::set_var(CONNECT_ENV, "tcp://other:4004");
+    env::set_var(LOG_LEVEL_ENV, "trace");
+    env::set_var(NAMESPACE_ENV, "123456");
+    let applied = config.apply_env();
+    env::set_var(LOG_FORMAT_ENV, "yaml");
+    let invalid = config.apply_env();
+    for key in &[CONNECT_ENV, LOG_LEVEL_ENV, NAMESPACE_ENV, LOG_FORMAT_ENV] {
+        env::remove_var(key);
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(applied.is_ok());
+    assert!(invalid.is_err());
+    assert_eq!(config.connect, "tcp://other:4004");
+    assert_eq!(config.log.level, "trace");
+    assert_eq!(config.log.format, LogFormat::Json);
+    assert_eq!(config.metrics_address, Some(String::from("0.0.0.0:9100")));
+    assert_eq!(config.family().unwrap().namespace(), "123456");
+}
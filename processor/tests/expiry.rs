@@ -0,0 +1,129 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of proposal expiry: expired proposals can no longer be accepted,
+//! and `EXPIRE_PROPOSALS` closes the open ones which have expired.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, Chain};
+
+/// A chain with the participants alice, bob and carol, where alice owns
+/// "crate-1".
+fn setup() -> (Chain, [String; 3]) {
+    let mut chain = Chain::new();
+    let keys = [public_key(0), public_key(1), public_key(2)];
+    for (key, name) in keys.iter().zip(&["alice", "bob", "carol"]) {
+        chain.create_participant(key, name);
+    }
+
+    let mut property = property::PropertySchema::new();
+    property.set_name("weight".to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![property]));
+    chain.apply(&keys[0], PayloadAction::CreateTable(table)).unwrap();
+
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(&keys[0], PayloadAction::CreateRecord(record)).unwrap();
+    (chain, keys)
+}
+
+/// Offers "crate-1" to `receiver` until `expires_at`.
+fn offer(id: &str, receiver: &str, expires_at: u64) -> PayloadAction {
+    let mut action = payload::CreateProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_role(proposal::Proposal_Role::transferOwnership);
+    action.set_record_id("crate-1".to_string());
+    action.set_receiving_participant(receiver.to_string());
+    action.set_expires_at(expires_at);
+    PayloadAction::CreateProposal(action)
+}
+
+fn answer(id: &str, response: payload::AnswerProposalAction_Response) -> PayloadAction {
+    let mut action = payload::AnswerProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_response(response);
+    PayloadAction::AnswerProposal(action)
+}
+
+fn expire(ids: &[&str]) -> PayloadAction {
+    let mut action = payload_v2::ExpireProposalsAction::new();
+    action.set_proposal_ids(ids.iter().map(|id| id.to_string()).collect());
+    PayloadAction::ExpireProposals(action)
+}
+
+fn status(chain: &mut Chain, id: &str) -> proposal::Proposal_Status {
+    chain.proposal(id).unwrap().get_status()
+}
+
+fn assert_refused(result: Result<(), ApplyError>, expected: &str) {
+    match result {
+        Err(ApplyError::InvalidTransaction(msg)) => assert_eq!(msg, expected),
+        other => panic!("action was not refused: {:?}", other),
+    }
+}
+
+#[test]
+fn expired_proposals_cannot_be_accepted() {
+    let (mut chain, [alice, bob, _]) = setup();
+    let accept = payload::AnswerProposalAction_Response::ACCEPT;
+    let expires_at = chain.timestamp + 10;
+    assert!(chain.apply(&alice, offer("offer-1", &bob, chain.timestamp)).is_err());
+    chain.apply(&alice, offer("offer-1", &bob, expires_at)).unwrap();
+
+    chain.timestamp = expires_at;
+    assert_refused(
+        chain.apply(&bob, answer("offer-1", accept)),
+        "Proposal has expired: offer-1",
+    );
+    assert_eq!(status(&mut chain, "offer-1"), proposal::Proposal_Status::OPEN);
+
+    // It can still be turned down
+    let reject = payload::AnswerProposalAction_Response::REJECT;
+    chain.apply(&bob, answer("offer-1", reject)).unwrap();
+    assert_eq!(status(&mut chain, "offer-1"), proposal::Proposal_Status::REJECTED);
+}
+
+#[test]
+fn expiring_proposals_closes_the_open_ones_which_have_expired() {
+    let (mut chain, [alice, bob, carol]) = setup();
+    let start = chain.timestamp;
+    chain.apply(&alice, offer("offer-1", &bob, start + 10)).unwrap();
+    chain.apply(&alice, offer("offer-2", &bob, start + 100)).unwrap();
+    chain.apply(&alice, offer("offer-3", &carol, start + 10)).unwrap();
+    let reject = payload::AnswerProposalAction_Response::REJECT;
+    chain.apply(&carol, answer("offer-3", reject)).unwrap();
+
+    chain.timestamp = start + 10;
+    // A proposal which has not expired fails the whole transaction
+    assert_refused(
+        chain.apply(&carol, expire(&["offer-1", "offer-2"])),
+        "Proposal has not expired: offer-2",
+    );
+    assert_eq!(status(&mut chain, "offer-1"), proposal::Proposal_Status::OPEN);
+
+    // Proposals answered before they expired are skipped
+    chain.apply(&carol, expire(&["offer-1", "offer-3"])).unwrap();
+    assert_eq!(status(&mut chain, "offer-1"), proposal::Proposal_Status::EXPIRED);
+    assert_eq!(status(&mut chain, "offer-3"), proposal::Proposal_Status::REJECTED);
+
+    // Proposals which have not expired stay open and can be accepted
+    assert_eq!(status(&mut chain, "offer-2"), proposal::Proposal_Status::OPEN);
+    let accept = payload::AnswerProposalAction_Response::ACCEPT;
+    chain.apply(&bob, answer("offer-2", accept)).unwrap();
+    assert_eq!(status(&mut chain, "offer-2"), proposal::Proposal_Status::ACCEPTED);
+}
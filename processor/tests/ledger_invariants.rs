@@ -0,0 +1,588 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Property-based checks of the DGC ledger. Random sequences of participant,
+//! proposal, answer and credit line actions are applied to in-memory state, and after
+//! every transaction the balances, total supply and proposal statuses are
+//! checked against the rules of the ledger. Both family versions follow the
+//! same rules, except in the blocks kept on the looser rules of version 1.1.
+
+extern crate dgc_rest_api_tp;
+extern crate proptest;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use proptest::prelude::*;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::block_time::LEGACY_RULES_UNTIL_KEY;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::settings::ADMINS_KEY;
+use dgc_rest_api_tp::transaction::{make_payload, PayloadAction, FAMILY_VERSION, FAMILY_VERSION_2};
+
+use common::{balance, family_state, public_key, set_setting, Chain};
+
+const PARTICIPANTS: usize = 4;
+const PROPOSALS: usize = 6;
+/// The participant listed in `dgc.admins`, which alone can settle exchanges
+/// and issue credit.
+const ADMIN: usize = 0;
+
+#[derive(Debug, Clone)]
+enum Step {
+    CreateParticipant {
+        participant: usize,
+    },
+    CreateProposal {
+        issuer: usize,
+        receiver: usize,
+        proposal: usize,
+        role: proposal::Proposal_Role,
+        amount: f64,
+    },
+    AnswerProposal {
+        signer: usize,
+        proposal: usize,
+        response: payload::AnswerProposalAction_Response,
+        amount: f64,
+    },
+    SetCreditLimit {
+        signer: usize,
+        participant: usize,
+        amount: f64,
+    },
+    RepayCredit {
+        signer: usize,
+        amount: f64,
+    },
+}
+
+fn proposal_id(proposal: usize) -> String {
+    format!("proposal-{}", proposal)
+}
+
+fn amount() -> BoxedStrategy<f64> {
+    prop_oneof![
+        8 => (1i64..100).prop_map(|amount| amount as f64),
+        1 => Just(0.0),
+        1 => (-100i64..0).prop_map(|amount| amount as f64),
+    ].boxed()
+}
+
+fn role() -> BoxedStrategy<proposal::Proposal_Role> {
+    prop_oneof![
+        Just(proposal::Proposal_Role::buyDGC),
+        Just(proposal::Proposal_Role::sellDGC),
+        Just(proposal::Proposal_Role::creditDGC),
+        Just(proposal::Proposal_Role::transferDGC),
+    ].boxed()
+}
+
+fn response() -> BoxedStrategy<payload::AnswerProposalAction_Response> {
+    prop_oneof![
+        Just(payload::AnswerProposalAction_Response::ACCEPT),
+        Just(payload::AnswerProposalAction_Response::REJECT),
+        Just(payload::AnswerProposalAction_Response::CANCEL),
+        Just(payload::AnswerProposalAction_Response::autoOPEN),
+        Just(payload::AnswerProposalAction_Response::autoCLOSE),
+    ].boxed()
+}
+
+fn step() -> BoxedStrategy<Step> {
+    prop_oneof![
+        1 => (0..PARTICIPANTS).prop_map(|participant| Step::CreateParticipant {
+            participant: participant,
+        }),
+        2 => (0..PARTICIPANTS, 0..PARTICIPANTS, 0..PROPOSALS, role(), amount()).prop_map(
+            |(issuer, receiver, proposal, role, amount)| Step::CreateProposal {
+                issuer: issuer,
+                receiver: receiver,
+                proposal: proposal,
+                role: role,
+                amount: amount,
+            }
+        ),
+        3 => (0..PARTICIPANTS, 0..PROPOSALS, response(), amount()).prop_map(
+            |(signer, proposal, response, amount)| Step::AnswerProposal {
+                signer: signer,
+                proposal: proposal,
+                response: response,
+                amount: amount,
+            }
+        ),
+        1 => (0..PARTICIPANTS, 0..PARTICIPANTS, amount()).prop_map(
+            |(signer, participant, amount)| Step::SetCreditLimit {
+                signer: signer,
+                participant: participant,
+                amount: amount,
+            }
+        ),
+        1 => (0..PARTICIPANTS, amount()).prop_map(|(signer, amount)| Step::RepayCredit {
+            signer: signer,
+            amount: amount,
+        }),
+    ].boxed()
+}
+
+/// Every participant starts with a balance, so transfers and sales have
+/// something to move.
+fn starting_steps() -> Vec<Step> {
+    let mut steps = Vec::new();
+    for participant in 0..PARTICIPANTS {
+        steps.push(Step::CreateParticipant {
+            participant: participant,
+        });
+        steps.push(Step::CreateProposal {
+            issuer: participant,
+            receiver: participant,
+            proposal: PROPOSALS + participant,
+            role: proposal::Proposal_Role::buyDGC,
+            amount: 50.0,
+        });
+        steps.push(Step::AnswerProposal {
+            signer: ADMIN,
+            proposal: PROPOSALS + participant,
+            response: payload::AnswerProposalAction_Response::autoCLOSE,
+            amount: 50.0,
+        });
+    }
+    steps
+}
+
+fn signer(step: &Step) -> usize {
+    match *step {
+        Step::CreateParticipant { participant } => participant,
+        Step::CreateProposal { issuer, .. } => issuer,
+        Step::AnswerProposal { signer, .. } => signer,
+        Step::SetCreditLimit { signer, .. } => signer,
+        Step::RepayCredit { signer, .. } => signer,
+    }
+}
+
+fn action(step: &Step) -> PayloadAction {
+    match *step {
+        Step::CreateParticipant { participant } => {
+            let mut action = payload::CreateParticipantAction::new();
+            action.set_name(format!("participant-{}", participant));
+            PayloadAction::CreateParticipant(action)
+        }
+        Step::CreateProposal {
+            receiver,
+            proposal,
+            role,
+            amount,
+            ..
+        } => {
+            let mut action = payload::CreateProposalAction::new();
+            action.set_proposal_id(proposal_id(proposal));
+            action.set_role(role);
+            action.set_receiving_participant(public_key(receiver));
+            action.set_dg_coin_amount(amount);
+            PayloadAction::CreateProposal(action)
+        }
+        Step::AnswerProposal {
+            proposal,
+            response,
+            amount,
+            ..
+        } => {
+            let mut action = payload::AnswerProposalAction::new();
+            action.set_proposal_id(proposal_id(proposal));
+            action.set_response(response);
+            action.set_dg_coin_amount(amount);
+            PayloadAction::AnswerProposal(action)
+        }
+        Step::SetCreditLimit {
+            participant,
+            amount,
+            ..
+        } => {
+            let mut action = payload_v2::SetCreditLimitAction::new();
+            action.set_participant_id(public_key(participant));
+            action.set_dg_coin_amount(amount);
+            PayloadAction::SetCreditLimit(action)
+        }
+        Step::RepayCredit { amount, .. } => {
+            let mut action = payload_v2::RepayCreditAction::new();
+            action.set_dg_coin_amount(amount);
+            PayloadAction::RepayCredit(action)
+        }
+    }
+}
+
+/// The balance, credit limit and drawn credit of every participant, and
+/// every proposal.
+struct Ledger {
+    accounts: Vec<Option<(f64, f64, f64)>>,
+    proposals: Vec<Option<proposal::Proposal>>,
+}
+
+impl Ledger {
+    fn read(chain: &mut Chain) -> Ledger {
+        let accounts = (0..PARTICIPANTS)
+            .map(|participant| {
+                chain
+                    .participant(&public_key(participant))
+                    .map(|participant| {
+                        let credit = participant
+                            .get_dg_coin_credits()
+                            .last()
+                            .map(|credit| credit.get_dg_coin_amount())
+                            .unwrap_or(0.0);
+                        let drawn = participant
+                            .get_dg_coin_drawn_credits()
+                            .last()
+                            .map(|drawn| drawn.get_dg_coin_amount())
+                            .unwrap_or(0.0);
+                        (balance(&participant), credit, drawn)
+                    })
+            })
+            .collect();
+        let proposals = (0..PROPOSALS + PARTICIPANTS)
+            .map(|proposal| chain.proposal(&proposal_id(proposal)))
+            .collect();
+        Ledger {
+            accounts: accounts,
+            proposals: proposals,
+        }
+    }
+
+    /// Coins held less the credit drawn to pay for them.
+    fn supply(&self) -> f64 {
+        self.accounts
+            .iter()
+            .filter_map(|account| account.map(|(balance, _, drawn)| balance - drawn))
+            .sum()
+    }
+}
+
+/// The change in total supply the step is allowed to make: only the
+/// administrator settling a buy or sell proposal mints or burns coins.
+fn allowed_supply_change(step: &Step, before: &Ledger) -> f64 {
+    if let Step::AnswerProposal {
+        signer,
+        proposal,
+        response,
+        amount,
+    } = *step
+    {
+        let settles = response == payload::AnswerProposalAction_Response::autoOPEN
+            || response == payload::AnswerProposalAction_Response::autoCLOSE;
+        if let Some(ref current) = before.proposals[proposal] {
+            if settles && signer == ADMIN {
+                match current.get_role() {
+                    proposal::Proposal_Role::buyDGC => return amount,
+                    proposal::Proposal_Role::sellDGC => return -amount,
+                    _ => (),
+                }
+            }
+        }
+    }
+    0.0
+}
+
+fn check_step(step: &Step, accepted: bool, before: &Ledger, after: &Ledger) {
+    for (participant, account) in after.accounts.iter().enumerate() {
+        if let Some((balance, credit, drawn)) = *account {
+            assert!(
+                balance >= 0.0,
+                "participant {} has negative balance {}",
+                participant,
+                balance
+            );
+            // A limit can be lowered below the credit already drawn, so
+            // only drawing more is held to it
+            let drawn_before = before.accounts[participant]
+                .map(|(_, _, drawn)| drawn)
+                .unwrap_or(0.0);
+            assert!(
+                drawn <= drawn_before || drawn <= credit,
+                "participant {} drew credit {} over its limit {}",
+                participant,
+                drawn,
+                credit
+            );
+            assert!(drawn >= 0.0, "participant {} repaid more than it drew", participant);
+        }
+    }
+
+    if let Step::SetCreditLimit { signer, .. } = *step {
+        assert!(
+            !accepted || signer == ADMIN,
+            "participant {} set a credit limit without the IssueCredit permission",
+            signer
+        );
+    }
+
+    let change = after.supply() - before.supply();
+    if accepted {
+        assert_eq!(
+            change,
+            allowed_supply_change(step, before),
+            "supply changed by {} after {:?}",
+            change,
+            step
+        );
+    } else {
+        assert_eq!(change, 0.0, "rejected {:?} changed supply", step);
+    }
+
+    for (index, (old, new)) in before.proposals.iter().zip(&after.proposals).enumerate() {
+        match (old, new) {
+            (&Some(ref old), &Some(ref new)) => {
+                if old.get_status() != proposal::Proposal_Status::OPEN {
+                    assert_eq!(old, new, "proposal {} changed after it was closed", index);
+                }
+            }
+            (&None, &Some(ref new)) => {
+                assert_eq!(new.get_status(), proposal::Proposal_Status::OPEN);
+            }
+            (&Some(_), &None) => panic!("proposal {} was deleted", index),
+            (&None, &None) => (),
+        }
+    }
+}
+
+/// Applies the step in `family_version`, or in version 2 for the actions
+/// version 1.1 does not have.
+fn apply(chain: &mut Chain, step: &Step, family_version: &str) -> Result<(), ApplyError> {
+    let (signer, action) = (public_key(signer(step)), action(step));
+    if family_version == FAMILY_VERSION && make_payload(action.clone(), chain.timestamp).is_ok() {
+        chain.apply_v1(&signer, action)
+    } else {
+        chain.apply(&signer, action)
+    }
+}
+
+fn run(steps: Vec<Step>, family_version: &str) {
+    let mut chain = Chain::new();
+    set_setting(&mut chain.context, ADMINS_KEY, &public_key(ADMIN));
+
+    let starting = starting_steps();
+    for (index, step) in starting.iter().chain(steps.iter()).enumerate() {
+        let before = Ledger::read(&mut chain);
+        let accepted = apply(&mut chain, step, family_version).is_ok();
+        assert!(accepted || index >= starting.len(), "starting {:?} was refused", step);
+        let after = Ledger::read(&mut chain);
+        check_step(step, accepted, &before, &after);
+    }
+}
+
+proptest! {
+    #[test]
+    fn ledger_invariants_hold(steps in prop::collection::vec(step(), 1..40)) {
+        run(steps, FAMILY_VERSION_2);
+    }
+
+    #[test]
+    fn ledger_invariants_hold_in_version_1_1(steps in prop::collection::vec(step(), 1..40)) {
+        run(steps, FAMILY_VERSION);
+    }
+}
+
+/// Version 1.1 answers are settled on the terms stored with the proposal,
+/// and closed proposals and reused ids are refused, as in version 2.
+#[test]
+fn version_1_1_follows_the_rules_of_version_2() {
+    let mut chain = Chain::new();
+    let (admin, alice) = (public_key(ADMIN), public_key(1));
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+    for index in 0..3 {
+        let create = action(&Step::CreateParticipant { participant: index });
+        chain.apply_v1(&public_key(index), create).unwrap();
+    }
+    let buy = action(&Step::CreateProposal {
+        issuer: 1,
+        receiver: 1,
+        proposal: 0,
+        role: proposal::Proposal_Role::buyDGC,
+        amount: 10.0,
+    });
+    chain.apply_v1(&alice, buy.clone()).unwrap();
+
+    // The role and amount the answer repeats are not those of the proposal
+    let mut settle = payload::AnswerProposalAction::new();
+    settle.set_proposal_id(proposal_id(0));
+    settle.set_response(payload::AnswerProposalAction_Response::autoCLOSE);
+    settle.set_role(proposal::Proposal_Role::sellDGC);
+    settle.set_dg_coin_amount(25.0);
+    let answer = PayloadAction::AnswerProposal(settle.clone());
+    assert!(chain.apply_v1(&admin, answer).is_err());
+
+    settle.set_dg_coin_amount(10.0);
+    let answer = PayloadAction::AnswerProposal(settle);
+    chain.apply_v1(&admin, answer.clone()).unwrap();
+    assert_eq!(balance(&chain.participant(&alice).unwrap()), 10.0);
+    assert_eq!(
+        chain.proposal(&proposal_id(0)).unwrap().get_status(),
+        proposal::Proposal_Status::CLOSED
+    );
+    assert!(chain.apply_v1(&admin, answer).is_err());
+    assert!(chain.apply_v1(&alice, buy).is_err());
+
+    // A transfer is paid by its issuer, whoever the answer names
+    let transfer = action(&Step::CreateProposal {
+        issuer: 1,
+        receiver: 2,
+        proposal: 1,
+        role: proposal::Proposal_Role::transferDGC,
+        amount: 4.0,
+    });
+    chain.apply_v1(&alice, transfer).unwrap();
+    let mut accept = payload::AnswerProposalAction::new();
+    accept.set_proposal_id(proposal_id(1));
+    accept.set_response(payload::AnswerProposalAction_Response::ACCEPT);
+    accept.set_role(proposal::Proposal_Role::transferDGC);
+    accept.set_receiving_participant(public_key(2));
+    accept.set_dg_coin_amount(40.0);
+    chain.apply_v1(&public_key(2), PayloadAction::AnswerProposal(accept)).unwrap();
+    assert_eq!(balance(&chain.participant(&alice).unwrap()), 6.0);
+    assert_eq!(balance(&chain.participant(&public_key(2)).unwrap()), 4.0);
+}
+
+/// In the blocks `dgc.proposals.legacy_rules_until_block` covers, version
+/// 1.1 answers still settle on the terms they carry and proposal ids can
+/// still be reused, so chains written under those rules replay unchanged.
+#[test]
+fn version_1_1_keeps_its_rules_in_legacy_blocks() {
+    let mut chain = Chain::new();
+    let admin = public_key(ADMIN);
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+    set_setting(&mut chain.context, LEGACY_RULES_UNTIL_KEY, "4");
+
+    let create = action(&Step::CreateProposal {
+        issuer: ADMIN,
+        receiver: ADMIN,
+        proposal: 0,
+        role: proposal::Proposal_Role::buyDGC,
+        amount: 10.0,
+    });
+    // A 1.1 answer repeats the role, and settles the amount it carries
+    let mut settle = payload::AnswerProposalAction::new();
+    settle.set_proposal_id(proposal_id(0));
+    settle.set_response(payload::AnswerProposalAction_Response::autoCLOSE);
+    settle.set_role(proposal::Proposal_Role::buyDGC);
+    settle.set_dg_coin_amount(25.0);
+
+    chain.apply_v1(&admin, action(&Step::CreateParticipant { participant: ADMIN })).unwrap();
+    chain.apply_v1(&admin, create.clone()).unwrap();
+    chain.apply_v1(&admin, PayloadAction::AnswerProposal(settle)).unwrap();
+    chain.apply_v1(&admin, create.clone()).unwrap();
+
+    assert_eq!(balance(&chain.participant(&admin).unwrap()), 25.0);
+    assert_eq!(
+        chain.proposal(&proposal_id(0)).unwrap().get_status(),
+        proposal::Proposal_Status::OPEN
+    );
+
+    // After the last legacy block the same proposal id is refused
+    assert_eq!(chain.block_num, 4);
+    assert!(chain.apply_v1(&admin, create).is_err());
+}
+
+/// A ledger where every participant has its starting balance and participant
+/// 1 owns "crate-1" and has offered it to participant 2 for `price`.
+fn offered_for_sale(price: f64) -> Chain {
+    let mut chain = Chain::new();
+    set_setting(&mut chain.context, ADMINS_KEY, &public_key(ADMIN));
+    for step in starting_steps() {
+        apply(&mut chain, &step, FAMILY_VERSION_2).expect("setup step is accepted");
+    }
+
+    let seller = public_key(1);
+    let mut property = property::PropertySchema::new();
+    property.set_name("weight".to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(vec![property].into());
+    chain.apply(&seller, PayloadAction::CreateTable(table)).expect("table is created");
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(&seller, PayloadAction::CreateRecord(record)).expect("record is created");
+    chain.apply(&seller, transfer(0, 2, price)).expect("record is offered");
+    chain
+}
+
+/// Offers "crate-1" to `receiver` for `price`.
+fn transfer(proposal: usize, receiver: usize, price: f64) -> PayloadAction {
+    let mut action = payload::CreateProposalAction::new();
+    action.set_proposal_id(proposal_id(proposal));
+    action.set_role(proposal::Proposal_Role::transferOwnership);
+    action.set_record_id("crate-1".to_string());
+    action.set_receiving_participant(public_key(receiver));
+    action.set_dg_coin_amount(price);
+    PayloadAction::CreateProposal(action)
+}
+
+fn accept(proposal: usize) -> PayloadAction {
+    let mut answer = payload::AnswerProposalAction::new();
+    answer.set_proposal_id(proposal_id(proposal));
+    answer.set_response(payload::AnswerProposalAction_Response::ACCEPT);
+    PayloadAction::AnswerProposal(answer)
+}
+
+fn record_owner(chain: &mut Chain) -> String {
+    let record = chain.record("crate-1").expect("record exists");
+    record.get_owners().last().expect("record has an owner").get_participant_id().to_string()
+}
+
+/// The ledger entries of the family, leaving out the blocks each
+/// transaction adds.
+fn ledger_state(chain: &Chain) -> BTreeMap<String, Vec<u8>> {
+    family_state(&chain.context, Family::default().namespace())
+}
+
+/// Accepting a priced transfer pays the price and moves the record in one
+/// transaction, and leaves the supply as it was.
+#[test]
+fn priced_transfers_pay_and_move_the_record_together() {
+    let mut chain = offered_for_sale(30.0);
+
+    let before = Ledger::read(&mut chain);
+    chain.apply(&public_key(2), accept(0)).expect("sale is accepted");
+    let after = Ledger::read(&mut chain);
+
+    assert_eq!(after.accounts[1], Some((80.0, 0.0, 0.0)));
+    assert_eq!(after.accounts[2], Some((20.0, 0.0, 0.0)));
+    assert_eq!(after.supply(), before.supply());
+    assert_eq!(record_owner(&mut chain), public_key(2));
+    assert_eq!(
+        after.proposals[0].as_ref().map(|proposal| proposal.get_status()),
+        Some(proposal::Proposal_Status::ACCEPTED)
+    );
+}
+
+/// A priced transfer the receiver cannot pay for changes nothing.
+#[test]
+fn priced_transfers_over_the_balance_change_nothing() {
+    let mut chain = offered_for_sale(80.0);
+
+    let before = ledger_state(&chain);
+    assert!(chain.apply(&public_key(2), accept(0)).is_err());
+    assert_eq!(ledger_state(&chain), before);
+    assert_eq!(record_owner(&mut chain), public_key(1));
+}
+
+/// A priced transfer of a record which has since changed owner changes
+/// nothing, so the new owner is not made to give it up.
+#[test]
+fn priced_transfers_of_records_sold_since_change_nothing() {
+    let mut chain = offered_for_sale(30.0);
+
+    chain.apply(&public_key(1), transfer(1, 3, 0.0)).expect("gift is offered");
+    chain.apply(&public_key(3), accept(1)).expect("gift is accepted");
+    assert_eq!(record_owner(&mut chain), public_key(3));
+
+    let before = ledger_state(&chain);
+    assert!(chain.apply(&public_key(2), accept(0)).is_err());
+    assert_eq!(ledger_state(&chain), before);
+    let ledger = Ledger::read(&mut chain);
+    assert_eq!(ledger.accounts[2], Some((50.0, 0.0, 0.0)));
+}
@@ -0,0 +1,173 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the processor lifecycle: counting transactions in flight,
+//! draining them on shutdown, readiness and reconnecting with backoff.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::lifecycle::*;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, Chain};
+
+/// An address nothing is listening on.
+fn unused_address() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn get(address: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn transactions_are_counted_until_they_finish() {
+    let lifecycle = Lifecycle::new(None);
+    {
+        let _first = lifecycle.begin().expect("not stopping");
+        let _second = lifecycle.begin().expect("not stopping");
+        assert_eq!(lifecycle.in_flight(), 2);
+    }
+    assert_eq!(lifecycle.in_flight(), 0);
+
+    let running = lifecycle.begin().expect("not stopping");
+    lifecycle.request_stop();
+    assert!(lifecycle.is_stopping());
+    assert!(lifecycle.begin().is_none());
+    assert_eq!(lifecycle.in_flight(), 1);
+    drop(running);
+    assert_eq!(lifecycle.in_flight(), 0);
+}
+
+#[test]
+fn draining_waits_for_transactions_in_flight() {
+    let lifecycle = Arc::new(Lifecycle::new(None));
+    assert!(lifecycle.drain(Duration::from_millis(0)));
+
+    let worker = {
+        let lifecycle = lifecycle.clone();
+        let (started, wait) = std::sync::mpsc::channel();
+        let worker = thread::spawn(move || {
+            let _in_flight = lifecycle.begin().expect("not stopping");
+            started.send(()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+        });
+        wait.recv().unwrap();
+        worker
+    };
+    lifecycle.request_stop();
+    assert!(!lifecycle.drain(Duration::from_millis(10)));
+    assert!(lifecycle.drain(Duration::from_secs(10)));
+    worker.join().unwrap();
+}
+
+#[test]
+fn readiness_follows_the_connection_and_shutdown() {
+    let path = env::temp_dir().join(format!("dgc-tp-ready-{}", std::process::id()));
+    let ready_file = path.to_str().unwrap().to_string();
+    let lifecycle = Lifecycle::new(Some(ready_file.clone()));
+    assert!(!lifecycle.is_ready());
+
+    lifecycle.set_connected(true);
+    assert!(lifecycle.is_ready());
+    assert!(Path::new(&ready_file).exists());
+
+    lifecycle.set_connected(false);
+    assert!(!lifecycle.is_ready());
+    assert!(!Path::new(&ready_file).exists());
+
+    lifecycle.set_connected(true);
+    lifecycle.request_stop();
+    assert!(!lifecycle.is_ready());
+    assert!(!Path::new(&ready_file).exists());
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn health_probes_report_readiness() {
+    let lifecycle = Arc::new(Lifecycle::new(None));
+    let address = unused_address();
+    serve_health(&address, lifecycle.clone()).unwrap();
+
+    assert!(get(&address, "/healthz").starts_with("HTTP/1.1 200 OK"));
+    assert!(get(&address, "/readyz").starts_with("HTTP/1.1 503"));
+    lifecycle.set_connected(true);
+    assert!(get(&address, "/readyz").starts_with("HTTP/1.1 200 OK"));
+    assert!(get(&address, "/metrics").starts_with("HTTP/1.1 404"));
+    lifecycle.request_stop();
+    assert!(get(&address, "/readyz").starts_with("HTTP/1.1 503"));
+    assert!(get(&address, "/healthz").starts_with("HTTP/1.1 200 OK"));
+}
+
+#[test]
+fn backoff_doubles_up_to_the_maximum() {
+    let millis = Duration::from_millis;
+    let mut backoff = Backoff::new(millis(100), millis(500));
+    let delays: Vec<Duration> = (0..5).map(|_| backoff.next_delay()).collect();
+    assert_eq!(delays, vec![millis(100), millis(200), millis(400), millis(500), millis(500)]);
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), millis(100));
+}
+
+#[test]
+fn waiting_for_the_validator_stops_with_the_processor() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("tcp://{}", listener.local_addr().unwrap());
+    let lifecycle = Lifecycle::new(None);
+    let mut backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(20));
+    backoff.next_delay();
+    assert!(wait_for_validator(&endpoint, &mut backoff, &lifecycle));
+    assert_eq!(backoff.next_delay(), Duration::from_millis(10));
+
+    lifecycle.request_stop();
+    let unreachable = format!("tcp://{}", unused_address());
+    assert!(!wait_for_validator(&unreachable, &mut backoff, &lifecycle));
+
+    let started = Instant::now();
+    sleep_unless_stopping(Duration::from_secs(60), &lifecycle);
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn the_handler_refuses_transactions_once_stopping() {
+    let lifecycle = Arc::new(Lifecycle::new(None));
+    let mut handler = TransactionHandlerDGC::new();
+    handler.set_lifecycle(lifecycle.clone());
+    let mut chain = Chain::with_handler(handler);
+    let signer = public_key(0);
+
+    chain.create_participant(&signer, "alice");
+    assert_eq!(lifecycle.in_flight(), 0);
+
+    lifecycle.request_stop();
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name("bob".to_string());
+    match chain.apply(&public_key(1), PayloadAction::CreateParticipant(action)) {
+        Err(ApplyError::InternalError(_)) => (),
+        other => panic!("transaction was not refused: {:?}", other),
+    }
+    assert!(chain.participant(&public_key(1)).is_none());
+    assert_eq!(lifecycle.in_flight(), 0);
+}
@@ -0,0 +1,180 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the transaction limits: how they are read from on-chain
+//! settings, and where each one starts rejecting actions.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+
+use dgc_rest_api_tp::context::MemoryContext;
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::limits::*;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, set_setting, Chain};
+
+fn limits() -> Limits {
+    Limits {
+        max_payload_bytes: 512,
+        max_properties: 3,
+        max_struct_depth: 2,
+        max_struct_width: 2,
+        max_string_length: 8,
+        max_bytes_length: 4,
+        max_enum_options: 2,
+        max_migrate_entries: 1,
+    }
+}
+
+fn schema(name: &str, fields: Vec<property::PropertySchema>) -> property::PropertySchema {
+    let mut schema = property::PropertySchema::new();
+    schema.set_name(name.to_string());
+    if fields.is_empty() {
+        schema.set_data_type(property::PropertySchema_DataType::NUMBER);
+    } else {
+        schema.set_data_type(property::PropertySchema_DataType::STRUCT);
+        schema.set_struct_properties(RepeatedField::from_vec(fields));
+    }
+    schema
+}
+
+fn value(name: &str, fields: Vec<property::PropertyValue>) -> property::PropertyValue {
+    let mut value = property::PropertyValue::new();
+    value.set_name(name.to_string());
+    if fields.is_empty() {
+        value.set_data_type(property::PropertySchema_DataType::NUMBER);
+    } else {
+        value.set_data_type(property::PropertySchema_DataType::STRUCT);
+        value.set_struct_values(RepeatedField::from_vec(fields));
+    }
+    value
+}
+
+fn create_table(properties: Vec<property::PropertySchema>) -> PayloadAction {
+    let mut action = payload::CreateTableAction::new();
+    action.set_name("table".to_string());
+    action.set_properties(RepeatedField::from_vec(properties));
+    PayloadAction::CreateTable(action)
+}
+
+fn create_record(properties: Vec<property::PropertyValue>) -> PayloadAction {
+    let mut action = payload::CreateRecordAction::new();
+    action.set_record_id("record".to_string());
+    action.set_table("table".to_string());
+    action.set_properties(RepeatedField::from_vec(properties));
+    PayloadAction::CreateRecord(action)
+}
+
+#[test]
+fn unset_settings_use_the_defaults() {
+    let mut context = MemoryContext::new();
+    let loaded = Limits::load(&mut context, &limits()).unwrap();
+    assert_eq!(loaded, limits());
+}
+
+#[test]
+fn settings_override_the_defaults() {
+    let mut context = MemoryContext::new();
+    set_setting(&mut context, MAX_STRUCT_DEPTH_KEY, "7");
+    set_setting(&mut context, MAX_PAYLOAD_BYTES_KEY, "2048");
+
+    let loaded = Limits::load(&mut context, &limits()).unwrap();
+    assert_eq!(loaded.max_struct_depth, 7);
+    assert_eq!(loaded.max_payload_bytes, 2048);
+    assert_eq!(loaded.max_struct_width, limits().max_struct_width);
+}
+
+#[test]
+fn invalid_settings_fall_back_to_the_defaults() {
+    for invalid in &["0", "-3", "many", "", "1.5"] {
+        let mut context = MemoryContext::new();
+        for key in Limits::setting_keys() {
+            set_setting(&mut context, key, invalid);
+        }
+        let loaded = Limits::load(&mut context, &limits()).unwrap();
+        assert_eq!(loaded, limits(), "setting {:?} was not ignored", invalid);
+    }
+}
+
+#[test]
+fn struct_depth_is_limited() {
+    let limits = limits();
+
+    let nested = schema("outer", vec![schema("inner", vec![schema("leaf", vec![])])]);
+    assert!(limits.check_action(&create_table(vec![nested])).is_ok());
+    let too_deep = schema(
+        "outer",
+        vec![schema("middle", vec![schema("inner", vec![schema("leaf", vec![])])])],
+    );
+    assert!(limits.check_action(&create_table(vec![too_deep])).is_err());
+
+    let nested = value("outer", vec![value("inner", vec![value("leaf", vec![])])]);
+    assert!(limits.check_action(&create_record(vec![nested])).is_ok());
+    let too_deep = value(
+        "outer",
+        vec![value("middle", vec![value("inner", vec![value("leaf", vec![])])])],
+    );
+    assert!(limits.check_action(&create_record(vec![too_deep])).is_err());
+}
+
+#[test]
+fn struct_width_is_limited() {
+    let limits = limits();
+    let fields = |count: usize| (0..count).map(|i| schema(&format!("f{}", i), vec![])).collect();
+    let values = |count: usize| (0..count).map(|i| value(&format!("f{}", i), vec![])).collect();
+
+    assert!(limits.check_action(&create_table(vec![schema("s", fields(2))])).is_ok());
+    assert!(limits.check_action(&create_table(vec![schema("s", fields(3))])).is_err());
+    assert!(limits.check_action(&create_record(vec![value("s", values(2))])).is_ok());
+    assert!(limits.check_action(&create_record(vec![value("s", values(3))])).is_err());
+}
+
+#[test]
+fn sizes_are_limited_at_the_boundary() {
+    let limits = limits();
+
+    assert!(limits.check_payload_size(512).is_ok());
+    assert!(limits.check_payload_size(513).is_err());
+
+    let properties = |count: usize| (0..count).map(|i| schema(&format!("p{}", i), vec![])).collect();
+    assert!(limits.check_action(&create_table(properties(3))).is_ok());
+    assert!(limits.check_action(&create_table(properties(4))).is_err());
+
+    assert!(limits.check_action(&create_table(vec![schema("12345678", vec![])])).is_ok());
+    assert!(limits.check_action(&create_table(vec![schema("123456789", vec![])])).is_err());
+
+    let mut options = schema("kind", vec![]);
+    options.set_data_type(property::PropertySchema_DataType::ENUM);
+    options.set_enum_options(RepeatedField::from_vec(vec!["a".to_string(), "b".to_string()]));
+    assert!(limits.check_action(&create_table(vec![options.clone()])).is_ok());
+    options.mut_enum_options().push("c".to_string());
+    assert!(limits.check_action(&create_table(vec![options])).is_err());
+
+    let mut bytes = value("blob", vec![]);
+    bytes.set_data_type(property::PropertySchema_DataType::BYTES);
+    bytes.set_bytes_value(vec![0; 4]);
+    assert!(limits.check_action(&create_record(vec![bytes.clone()])).is_ok());
+    bytes.set_bytes_value(vec![0; 5]);
+    assert!(limits.check_action(&create_record(vec![bytes])).is_err());
+}
+
+#[test]
+fn the_handler_applies_on_chain_limits() {
+    let mut chain = Chain::with_handler(TransactionHandlerDGC::with_limits(limits()));
+    let owner = public_key(0);
+    chain.create_participant(&owner, "owner");
+
+    let nested = schema("outer", vec![schema("inner", vec![schema("leaf", vec![])])]);
+    set_setting(&mut chain.context, MAX_STRUCT_DEPTH_KEY, "1");
+    assert!(chain.apply(&owner, create_table(vec![nested.clone()])).is_err());
+
+    set_setting(&mut chain.context, MAX_STRUCT_DEPTH_KEY, "nested");
+    assert!(chain.apply(&owner, create_table(vec![nested])).is_ok());
+}
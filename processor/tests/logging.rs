@@ -0,0 +1,174 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the structured transaction log: the fields kept for the
+//! transaction being applied and how the text and JSON encoders write them.
+
+extern crate dgc_rest_api_tp;
+#[macro_use]
+extern crate log;
+extern crate log4rs;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+extern crate serde_json;
+
+mod common;
+
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::sync::Once;
+
+use log::{LogLevelFilter, LogMetadata, LogRecord};
+use serde_json::Value;
+
+use dgc_rest_api_tp::config::LogFormat;
+use dgc_rest_api_tp::logging::*;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, request, Chain, TIMESTAMP};
+
+thread_local! {
+    static FORMAT: Cell<LogFormat> = Cell::new(LogFormat::Json);
+    static LINES: RefCell<Vec<u8>> = RefCell::new(Vec::new());
+}
+
+/// Encodes every line logged on a thread with that thread's format, and
+/// keeps it for the thread to read back.
+struct CaptureLogger;
+
+struct Buffer(Vec<u8>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl log4rs::encode::Write for Buffer {}
+
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _metadata: &LogMetadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &LogRecord) {
+        let mut buffer = Buffer(Vec::new());
+        encoder(FORMAT.with(|format| format.get()))
+            .encode(&mut buffer, record)
+            .expect("line is encoded");
+        LINES.with(|lines| lines.borrow_mut().extend(buffer.0));
+    }
+}
+
+static INIT: Once = Once::new();
+
+/// The lines logged in `format` while `f` runs on this thread.
+fn capture<F: FnOnce()>(format: LogFormat, f: F) -> Vec<String> {
+    INIT.call_once(|| {
+        log::set_logger(|max| {
+            max.set(LogLevelFilter::Info);
+            Box::new(CaptureLogger)
+        }).expect("logger is installed");
+    });
+    FORMAT.with(|current| current.set(format));
+    LINES.with(|lines| lines.borrow_mut().clear());
+    f();
+    LINES.with(|lines| {
+        String::from_utf8(lines.borrow().clone())
+            .expect("lines are utf-8")
+            .lines()
+            .map(String::from)
+            .collect()
+    })
+}
+
+fn create_participant(name: &str) -> PayloadAction {
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name(name.to_string());
+    PayloadAction::CreateParticipant(action)
+}
+
+#[test]
+fn fields_last_as_long_as_the_scope() {
+    let request = request(&public_key(0), create_participant("alice"), TIMESTAMP);
+
+    // Without a scope there is nothing to record the action on
+    set_action("CREATE_RECORD", None, None);
+    {
+        let scope = TransactionScope::enter(&request);
+        assert_eq!(scope.action(), None);
+        set_action("CREATE_RECORD", Some(String::from("crate-1")), None);
+        assert_eq!(scope.action(), Some(String::from("CREATE_RECORD")));
+    }
+    let scope = TransactionScope::enter(&request);
+    assert_eq!(scope.action(), None);
+}
+
+#[test]
+fn json_lines_carry_the_transaction_fields() {
+    let signer = public_key(0);
+    let mut chain = Chain::new();
+    let lines = capture(LogFormat::Json, || {
+        chain.create_participant(&signer, "alice");
+        assert!(chain.apply(&signer, create_participant("alice")).is_err());
+    });
+
+    let lines: Vec<Value> = lines
+        .iter()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+        .collect();
+    let results: Vec<&Value> = lines
+        .iter()
+        .filter(|line| line["transaction"]["result"].is_string())
+        .collect();
+    assert_eq!(results.len(), 2);
+
+    let applied = &results[0];
+    assert_eq!(applied["level"], "INFO");
+    assert_eq!(applied["message"], "Transaction applied");
+    assert_eq!(applied["transaction"]["signer"], Value::String(signer.clone()));
+    assert_eq!(applied["transaction"]["signature"], format!("{:0128x}", TIMESTAMP + 1));
+    assert_eq!(applied["transaction"]["action"], "CREATE_PARTICIPANT");
+    assert_eq!(applied["transaction"]["result"], "applied");
+    assert!(applied["transaction"]["duration_ms"].is_number());
+    assert!(applied["transaction"].get("error").is_none());
+    assert!(applied["transaction"].get("record_id").is_none());
+
+    let rejected = &results[1];
+    assert_eq!(rejected["transaction"]["result"], "invalid");
+    let error = rejected["transaction"]["error"].as_str().unwrap();
+    assert!(rejected["message"].as_str().unwrap().ends_with(error));
+
+    // Lines logged outside a transaction have no transaction fields
+    let outside = capture(LogFormat::Json, || info!("between transactions"));
+    assert_eq!(outside.len(), 1);
+    let outside: Value = serde_json::from_str(&outside[0]).unwrap();
+    assert_eq!(outside["message"], "between transactions");
+    assert!(outside.get("transaction").is_none());
+}
+
+#[test]
+fn text_lines_end_with_the_transaction_fields() {
+    let signer = public_key(0);
+    let mut chain = Chain::new();
+    let lines = capture(LogFormat::Text, || {
+        chain.create_participant(&signer, "alice");
+    });
+
+    let applied = lines
+        .iter()
+        .find(|line| line.contains(" result=applied"))
+        .expect("the result is logged");
+    assert!(applied.contains(&format!(" signer={}", signer)));
+    assert!(applied.contains(" action=CREATE_PARTICIPANT"));
+    assert!(applied.contains(" duration_ms="));
+    assert!(!applied.contains(" error="));
+
+    let outside = capture(LogFormat::Text, || info!("between transactions"));
+    assert!(!outside[0].contains("signer="));
+}
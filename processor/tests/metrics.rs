@@ -0,0 +1,162 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the Prometheus metrics: what each transaction and state call
+//! counts, and how rejections are grouped by error code.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext};
+
+use dgc_rest_api_tp::addressing::{make_participant_address, Family};
+use dgc_rest_api_tp::context::MemoryContext;
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::metrics::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, Chain};
+
+fn invalid(msg: &str) -> ApplyError {
+    ApplyError::InvalidTransaction(msg.to_string())
+}
+
+/// The value of the sample rendered as `name`, labels included.
+fn sample(rendered: &str, name: &str) -> Option<String> {
+    rendered
+        .lines()
+        .find(|line| line.starts_with(name) && line[name.len()..].starts_with(' '))
+        .map(|line| line[name.len() + 1..].to_string())
+}
+
+#[test]
+fn transactions_are_counted_by_action_and_result() {
+    let metrics = Metrics::new();
+    let millis = Duration::from_millis;
+    metrics.record_transaction(Some("CREATE_RECORD"), &Ok(()), millis(3));
+    metrics.record_transaction(Some("CREATE_RECORD"), &Ok(()), millis(30));
+    metrics.record_transaction(
+        Some("CREATE_RECORD"),
+        &Err(invalid("Record already exists: crate-1")),
+        millis(1),
+    );
+    metrics.record_transaction(None, &Err(invalid("Cannot deserialize payload")), millis(1));
+    metrics.record_transaction(
+        Some("MIGRATE"),
+        &Err(ApplyError::InternalError(String::from("state unavailable"))),
+        Duration::from_secs(5),
+    );
+
+    let rendered = metrics.render();
+    let expected = [
+        ("dgc_transactions_total{action=\"CREATE_RECORD\",result=\"applied\"}", "2"),
+        ("dgc_transactions_total{action=\"CREATE_RECORD\",result=\"invalid\"}", "1"),
+        ("dgc_transactions_total{action=\"UNKNOWN\",result=\"invalid\"}", "1"),
+        ("dgc_transactions_total{action=\"MIGRATE\",result=\"internal_error\"}", "1"),
+        ("dgc_rejections_total{code=\"already_exists\"}", "1"),
+        ("dgc_rejections_total{code=\"malformed_payload\"}", "1"),
+        ("dgc_rejections_total{code=\"internal_error\"}", "1"),
+        ("dgc_apply_duration_seconds_bucket{action=\"CREATE_RECORD\",le=\"0.0025\"}", "1"),
+        ("dgc_apply_duration_seconds_bucket{action=\"CREATE_RECORD\",le=\"0.005\"}", "2"),
+        ("dgc_apply_duration_seconds_bucket{action=\"CREATE_RECORD\",le=\"0.05\"}", "3"),
+        ("dgc_apply_duration_seconds_bucket{action=\"CREATE_RECORD\",le=\"+Inf\"}", "3"),
+        ("dgc_apply_duration_seconds_count{action=\"CREATE_RECORD\"}", "3"),
+        ("dgc_apply_duration_seconds_sum{action=\"CREATE_RECORD\"}", "0.034"),
+        ("dgc_apply_duration_seconds_bucket{action=\"MIGRATE\",le=\"2.5\"}", "0"),
+        ("dgc_apply_duration_seconds_bucket{action=\"MIGRATE\",le=\"+Inf\"}", "1"),
+    ];
+    for &(name, value) in expected.iter() {
+        assert_eq!(sample(&rendered, name), Some(value.to_string()), "{}", name);
+    }
+    assert!(rendered.contains("# TYPE dgc_transactions_total counter"));
+    assert!(rendered.contains("# TYPE dgc_apply_duration_seconds histogram"));
+}
+
+#[test]
+fn rejections_are_grouped_by_wording() {
+    let codes = [
+        ("Cannot deserialize payload", "malformed_payload"),
+        ("Payload is 70000 bytes, more than the limit of 65536", "limit_exceeded"),
+        ("Timestamp is not set", "timestamp"),
+        ("Only the owner can finalize a record", "unauthorized"),
+        ("Record does not exist: crate-1", "not_found"),
+        ("Participant already exists: 02ab", "already_exists"),
+        ("Record is final: crate-1", "invalid_state"),
+        ("Insufficient balance", "invalid_amount"),
+        ("Value must be of type NUMBER", "invalid_value"),
+        ("Something else went wrong", "invalid"),
+    ];
+    for &(msg, code) in codes.iter() {
+        assert_eq!(error_code(&invalid(msg)), code, "{}", msg);
+    }
+    assert_eq!(
+        error_code(&ApplyError::InternalError(String::from("Record does not exist"))),
+        "internal_error"
+    );
+}
+
+#[test]
+fn state_calls_are_counted_by_address_type() {
+    let metrics = Metrics::new();
+    let namespace = Family::default().namespace().to_string();
+    let participant = make_participant_address(&namespace, &public_key(0));
+    let mut inner = MemoryContext::new();
+    {
+        let mut context = MeteredContext::new(&mut inner, &metrics, &namespace);
+        let mut entries = HashMap::new();
+        entries.insert(participant.clone(), vec![0; 300]);
+        entries.insert(format!("{}{}", "1cf126", "0".repeat(64)), vec![0; 10]);
+        context.set_state(entries).unwrap();
+        assert_eq!(context.get_state(vec![participant.clone()]).unwrap(), Some(vec![0; 300]));
+        assert_eq!(context.get_state(vec![format!("{}ff", namespace)]).unwrap(), None);
+        context.delete_state(vec![participant.clone()]).unwrap();
+    }
+    assert!(inner.state().get(&participant).is_none());
+
+    let rendered = metrics.render();
+    let expected = [
+        ("dgc_state_get_total", "2"),
+        ("dgc_state_get_bytes_total", "300"),
+        ("dgc_state_set_total", "2"),
+        ("dgc_state_set_bytes_total", "310"),
+        ("dgc_state_delete_total", "1"),
+        ("dgc_container_bytes_bucket{address_type=\"participants\",le=\"256\"}", "0"),
+        ("dgc_container_bytes_bucket{address_type=\"participants\",le=\"1024\"}", "1"),
+        ("dgc_container_bytes_count{address_type=\"other\"}", "1"),
+    ];
+    for &(name, value) in expected.iter() {
+        assert_eq!(sample(&rendered, name), Some(value.to_string()), "{}", name);
+    }
+}
+
+#[test]
+fn the_handler_records_what_it_applies() {
+    let metrics = Arc::new(Metrics::new());
+    let mut handler = TransactionHandlerDGC::new();
+    handler.set_metrics(metrics.clone());
+    let mut chain = Chain::with_handler(handler);
+    let signer = public_key(0);
+
+    chain.create_participant(&signer, "alice");
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name("alice".to_string());
+    assert!(chain.apply(&signer, PayloadAction::CreateParticipant(action)).is_err());
+
+    let rendered = metrics.render();
+    let applied = "dgc_transactions_total{action=\"CREATE_PARTICIPANT\",result=\"applied\"}";
+    let rejected = "dgc_transactions_total{action=\"CREATE_PARTICIPANT\",result=\"invalid\"}";
+    assert_eq!(sample(&rendered, applied), Some(String::from("1")));
+    assert_eq!(sample(&rendered, rejected), Some(String::from("1")));
+    let already_exists = "dgc_rejections_total{code=\"already_exists\"}";
+    assert_eq!(sample(&rendered, already_exists), Some(String::from("1")));
+    assert_ne!(sample(&rendered, "dgc_state_set_total"), Some(String::from("0")));
+    assert!(rendered.contains("address_type=\"participants\""));
+}
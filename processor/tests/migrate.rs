@@ -0,0 +1,168 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the entity format versions: upgrading entries written by
+//! earlier handlers, and rewriting them in batches with MIGRATE.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use std::collections::HashMap;
+
+use protobuf::{Message, RepeatedField};
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext};
+
+use dgc_rest_api_tp::addressing::*;
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::limits::Limits;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::migrate::*;
+use dgc_rest_api_tp::settings::ADMINS_KEY;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{balance, family_state, public_key, set_setting, Chain};
+
+fn participant(key: &str, format_version: u32, balance: f64) -> participant::Participant {
+    let mut amount = participant::Participant_CreditBalance::new();
+    amount.set_dg_coin_amount(balance);
+    amount.set_proposal_id("proposal-1".to_string());
+    let mut participant = participant::Participant::new();
+    participant.set_public_key(key.to_string());
+    participant.set_name("old".to_string());
+    participant.set_format_version(format_version);
+    participant.set_dg_coin_balances(RepeatedField::from_vec(vec![amount]));
+    participant
+}
+
+fn participant_container(participant: participant::Participant) -> Vec<u8> {
+    let mut container = participant::ParticipantContainer::new();
+    container.set_entries(RepeatedField::from_vec(vec![participant]));
+    container.write_to_bytes().unwrap()
+}
+
+fn migrate(addresses: &[String], cursor: u32) -> PayloadAction {
+    let mut action = payload_v2::MigrateAction::new();
+    action.set_addresses(RepeatedField::from_vec(addresses.to_vec()));
+    action.set_cursor(cursor);
+    PayloadAction::Migrate(action)
+}
+
+/// The participant container stored at `address`, as written.
+fn stored(chain: &Chain, address: &str) -> participant::Participant {
+    let data = &chain.context.state()[address];
+    let container: participant::ParticipantContainer = protobuf::parse_from_bytes(data).unwrap();
+    container.get_entries()[0].clone()
+}
+
+#[test]
+fn overdrawn_participants_are_upgraded_to_drawn_credit() {
+    let mut old = participant(&public_key(0), 0, -15.0);
+    assert!(upgrade_participant(&mut old).unwrap());
+    assert_eq!(old.get_format_version(), PARTICIPANT_FORMAT);
+    assert_eq!(balance(&old), 0.0);
+    let drawn = old.get_dg_coin_drawn_credits().last().unwrap();
+    assert_eq!(drawn.get_dg_coin_amount(), 15.0);
+    assert_eq!(drawn.get_proposal_id(), "proposal-1");
+
+    let upgraded = old.clone();
+    assert!(!upgrade_participant(&mut old).unwrap());
+    assert_eq!(old, upgraded);
+
+    let mut funded = participant(&public_key(0), 1, 20.0);
+    assert!(upgrade_participant(&mut funded).unwrap());
+    assert_eq!(balance(&funded), 20.0);
+    assert!(funded.get_dg_coin_drawn_credits().is_empty());
+}
+
+#[test]
+fn newer_formats_are_refused() {
+    let mut newer = participant(&public_key(0), PARTICIPANT_FORMAT + 1, 0.0);
+    assert!(upgrade_participant(&mut newer).is_err());
+
+    let mut proposal = proposal::Proposal::new();
+    assert!(upgrade_proposal(&mut proposal).unwrap());
+    assert_eq!(proposal.get_format_version(), PROPOSAL_FORMAT);
+    assert!(!upgrade_proposal(&mut proposal).unwrap());
+    proposal.set_format_version(PROPOSAL_FORMAT + 1);
+    assert!(upgrade_proposal(&mut proposal).is_err());
+
+    let mut record = record::Record::new();
+    assert!(upgrade_record(&mut record).unwrap());
+    assert_eq!(record.get_format_version(), RECORD_FORMAT);
+    assert!(!upgrade_record(&mut record).unwrap());
+    record.set_format_version(RECORD_FORMAT + 1);
+    assert!(upgrade_record(&mut record).is_err());
+}
+
+#[test]
+fn only_versioned_entities_can_be_migrated() {
+    let family = Family::default();
+    let ns = family.namespace();
+    assert!(check_migratable(ns, &make_participant_address(ns, &public_key(0))).is_ok());
+    assert!(check_migratable(ns, &make_proposal_address(ns, "proposal-1")).is_ok());
+    assert!(check_migratable(ns, &make_record_address(ns, "crate-1")).is_ok());
+    assert!(check_migratable(ns, &make_table_address(ns, "crate")).is_err());
+    assert!(check_migratable("abcdef", &make_record_address(ns, "crate-1")).is_err());
+    assert!(check_migratable(ns, ns).is_err());
+
+    let address = make_participant_address(ns, &public_key(0));
+    let old = participant_container(participant(&public_key(0), 0, 1.0));
+    let upgraded = migrate_container(ns, &address, &old).unwrap().expect("container changed");
+    assert_eq!(migrate_container(ns, &address, &upgraded).unwrap(), None);
+    match migrate_container(ns, &address, b"\xff\xff") {
+        Err(ApplyError::InternalError(_)) => (),
+        other => panic!("corrupt container was migrated: {:?}", other),
+    }
+}
+
+#[test]
+fn migrate_rewrites_entries_from_the_cursor() {
+    let admin = public_key(9);
+    let limits = Limits {
+        max_migrate_entries: 1,
+        ..Limits::default()
+    };
+    let mut chain = Chain::with_handler(TransactionHandlerDGC::with_limits(limits));
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+
+    let ns = Family::default().namespace().to_string();
+    let keys = [public_key(0), public_key(1)];
+    let addresses: Vec<String> = keys
+        .iter()
+        .map(|key| make_participant_address(&ns, key))
+        .collect();
+    let mut entries = HashMap::new();
+    for (key, address) in keys.iter().zip(&addresses) {
+        entries.insert(address.clone(), participant_container(participant(key, 0, -5.0)));
+    }
+    chain.context.set_state(entries).unwrap();
+
+    // Reads see the upgraded form before anything is rewritten
+    let read = chain.participant(&keys[0]).unwrap();
+    assert_eq!(read.get_format_version(), PARTICIPANT_FORMAT);
+    assert_eq!(stored(&chain, &addresses[0]).get_format_version(), 0);
+
+    // Only administrators may migrate
+    assert!(chain.apply(&keys[0], migrate(&addresses, 0)).is_err());
+    assert_eq!(stored(&chain, &addresses[0]).get_format_version(), 0);
+
+    chain.apply(&admin, migrate(&addresses, 0)).unwrap();
+    assert_eq!(stored(&chain, &addresses[0]), read);
+    assert_eq!(stored(&chain, &addresses[1]).get_format_version(), 0);
+
+    chain.apply(&admin, migrate(&addresses, 1)).unwrap();
+    assert_eq!(stored(&chain, &addresses[1]).get_format_version(), PARTICIPANT_FORMAT);
+
+    // Migrating current entries, or none at all, changes nothing
+    let before = family_state(&chain.context, &ns);
+    chain.apply(&admin, migrate(&addresses, 0)).unwrap();
+    chain.apply(&admin, migrate(&addresses, 2)).unwrap();
+    assert_eq!(family_state(&chain.context, &ns), before);
+
+    assert!(chain.apply(&admin, migrate(&addresses, 3)).is_err());
+    let table = vec![make_table_address(&ns, "crate")];
+    assert!(chain.apply(&admin, migrate(&table, 0)).is_err());
+}
@@ -0,0 +1,199 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of multisig policies: answers to their proposals need the
+//! threshold, and the approvals collected can be withdrawn, expire and only
+//! count while their signers are active.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+
+use dgc_rest_api_tp::addressing::{Family, PENDING_APPROVAL};
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::settings::{ADMINS_KEY, APPROVAL_LIFETIME_KEY};
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, set_setting, Chain};
+
+const POLICY: &str = "vault";
+
+/// A chain where alice owns "crate-1" and has proposed to transfer it to a
+/// policy of alice, bob and carol with a threshold of 2.
+fn proposed_to_policy() -> (Chain, [String; 3]) {
+    let mut chain = Chain::new();
+    let keys = [public_key(0), public_key(1), public_key(2)];
+    set_setting(&mut chain.context, ADMINS_KEY, &public_key(9));
+    for (key, name) in keys.iter().zip(&["alice", "bob", "carol"]) {
+        chain.create_participant(key, name);
+    }
+
+    let mut policy = payload_v2::CreateMultisigPolicyAction::new();
+    policy.set_policy_id(POLICY.to_string());
+    policy.set_signers(RepeatedField::from_vec(keys.to_vec()));
+    policy.set_threshold(2);
+    chain.apply(&keys[0], PayloadAction::CreateMultisigPolicy(policy)).unwrap();
+
+    let mut property = property::PropertySchema::new();
+    property.set_name("weight".to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![property]));
+    chain.apply(&keys[0], PayloadAction::CreateTable(table)).unwrap();
+
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(&keys[0], PayloadAction::CreateRecord(record)).unwrap();
+
+    let mut proposal = payload::CreateProposalAction::new();
+    proposal.set_proposal_id("proposal-1".to_string());
+    proposal.set_role(proposal::Proposal_Role::transferOwnership);
+    proposal.set_record_id("crate-1".to_string());
+    proposal.set_receiving_participant(POLICY.to_string());
+    chain.apply(&keys[0], PayloadAction::CreateProposal(proposal)).unwrap();
+    (chain, keys)
+}
+
+fn answer(response: payload::AnswerProposalAction_Response) -> PayloadAction {
+    let mut answer = payload::AnswerProposalAction::new();
+    answer.set_proposal_id("proposal-1".to_string());
+    answer.set_response(response);
+    PayloadAction::AnswerProposal(answer)
+}
+
+fn accept() -> PayloadAction {
+    answer(payload::AnswerProposalAction_Response::ACCEPT)
+}
+
+fn withdraw(approval_id: &str) -> PayloadAction {
+    let mut action = payload_v2::WithdrawApprovalAction::new();
+    action.set_approval_id(approval_id.to_string());
+    PayloadAction::WithdrawApproval(action)
+}
+
+/// Every pending approval in state.
+fn pending_approvals(chain: &Chain) -> Vec<multisig::PendingApproval> {
+    let prefix = Family::default().namespace().to_string() + PENDING_APPROVAL;
+    let mut approvals = Vec::new();
+    for (address, data) in chain.context.state() {
+        if address.starts_with(&prefix) {
+            let container: multisig::PendingApprovalContainer =
+                protobuf::parse_from_bytes(data).unwrap();
+            approvals.extend(container.get_entries().iter().cloned());
+        }
+    }
+    approvals
+}
+
+fn owner(chain: &mut Chain) -> String {
+    let record = chain.record("crate-1").unwrap();
+    record.get_owners().last().unwrap().get_participant_id().to_string()
+}
+
+fn status(chain: &mut Chain) -> proposal::Proposal_Status {
+    chain.proposal("proposal-1").unwrap().get_status()
+}
+
+#[test]
+fn answers_for_a_policy_need_its_threshold() {
+    let (mut chain, [alice, bob, carol]) = proposed_to_policy();
+
+    chain.apply(&bob, accept()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::OPEN);
+    assert_eq!(owner(&mut chain), alice);
+    let approvals = pending_approvals(&chain);
+    assert_eq!(approvals.len(), 1);
+    assert_eq!(approvals[0].get_action(), "ANSWER_PROPOSAL");
+    assert_eq!(approvals[0].get_approvers(), [bob.as_str()]);
+    assert!(chain.apply(&bob, accept()).is_err());
+
+    chain.apply(&carol, accept()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::ACCEPTED);
+    assert_eq!(owner(&mut chain), POLICY);
+    assert!(pending_approvals(&chain).is_empty());
+}
+
+#[test]
+fn rejections_for_a_policy_need_its_threshold() {
+    let (mut chain, [_, bob, carol]) = proposed_to_policy();
+    let reject = || answer(payload::AnswerProposalAction_Response::REJECT);
+
+    chain.apply(&bob, reject()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::OPEN);
+    // Approvals of different answers do not add up
+    chain.apply(&carol, accept()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::OPEN);
+    assert_eq!(pending_approvals(&chain).len(), 2);
+
+    chain.apply(&carol, reject()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::REJECTED);
+    assert!(chain.apply(&public_key(5), reject()).is_err());
+}
+
+#[test]
+fn approvals_can_be_withdrawn() {
+    let (mut chain, [alice, bob, carol]) = proposed_to_policy();
+    chain.apply(&bob, accept()).unwrap();
+    let approval_id = pending_approvals(&chain)[0].get_approval_id().to_string();
+
+    assert!(chain.apply(&carol, withdraw(&approval_id)).is_err());
+    assert!(chain.apply(&bob, withdraw("unknown")).is_err());
+    chain.apply(&bob, withdraw(&approval_id)).unwrap();
+    assert!(pending_approvals(&chain).is_empty());
+
+    // The withdrawn approval no longer counts
+    chain.apply(&carol, accept()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::OPEN);
+    chain.apply(&alice, accept()).unwrap();
+    assert_eq!(owner(&mut chain), POLICY);
+    assert!(chain.apply(&bob, withdraw(&approval_id)).is_err());
+}
+
+#[test]
+fn approvals_expire() {
+    let (mut chain, [alice, bob, carol]) = proposed_to_policy();
+    set_setting(&mut chain.context, APPROVAL_LIFETIME_KEY, "100");
+
+    chain.apply(&bob, accept()).unwrap();
+    let approval = pending_approvals(&chain)[0].clone();
+    assert_eq!(approval.get_expires_at(), chain.timestamp + 100);
+
+    // An approval sent after the others expired starts afresh
+    chain.timestamp += 100;
+    chain.apply(&carol, accept()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::OPEN);
+    assert_eq!(pending_approvals(&chain)[0].get_approvers(), [carol.as_str()]);
+
+    // Anyone can remove an expired approval
+    chain.timestamp += 100;
+    chain.apply(&public_key(5), withdraw(approval.get_approval_id())).unwrap();
+    assert!(pending_approvals(&chain).is_empty());
+    chain.apply(&alice, accept()).unwrap();
+    chain.apply(&bob, accept()).unwrap();
+    assert_eq!(owner(&mut chain), POLICY);
+}
+
+#[test]
+fn only_active_signers_count() {
+    let (mut chain, [alice, bob, carol]) = proposed_to_policy();
+    let admin = public_key(9);
+    let mut suspend = payload_v2::SetParticipantStatusAction::new();
+    suspend.set_participant_id(bob.clone());
+    suspend.set_status(participant::Participant_Status::SUSPENDED);
+
+    chain.apply(&bob, accept()).unwrap();
+    chain.apply(&admin, PayloadAction::SetParticipantStatus(suspend)).unwrap();
+    assert!(chain.apply(&bob, accept()).is_err());
+
+    chain.apply(&carol, accept()).unwrap();
+    assert_eq!(status(&mut chain), proposal::Proposal_Status::OPEN);
+    assert_eq!(pending_approvals(&chain)[0].get_approvers(), [carol.as_str()]);
+    chain.apply(&alice, accept()).unwrap();
+    assert_eq!(owner(&mut chain), POLICY);
+}
@@ -0,0 +1,139 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of processors registered under another family name or namespace:
+//! how the namespace is chosen and that state stays under it.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::Message;
+use sawtooth_sdk::processor::handler::TransactionHandler;
+
+use dgc_rest_api_tp::addressing::*;
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::decode::decode_state;
+use dgc_rest_api_tp::handler::{StateDGC, TransactionHandlerDGC};
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::*;
+
+use common::{family_state, new_context, public_key, TIMESTAMP};
+
+fn handler(family: &Family) -> TransactionHandlerDGC {
+    let mut handler = TransactionHandlerDGC::new();
+    handler.set_family(family.clone());
+    handler
+}
+
+fn create_participant(family: &Family, signer: &str, name: &str) -> MemoryContext {
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name(name.to_string());
+    let payload = make_payload_v2(PayloadAction::CreateParticipant(action), TIMESTAMP)
+        .write_to_bytes()
+        .unwrap();
+    let request = make_process_request(family, FAMILY_VERSION_2, signer, &payload, "sig");
+
+    let mut context = new_context();
+    context.apply(&handler(family), &request).unwrap();
+    context
+}
+
+#[test]
+fn namespaces_are_derived_from_the_family_name() {
+    let family = Family::default();
+    assert_eq!(family.name(), FAMILY_NAME);
+    assert_eq!(family.namespace(), get_dgc_rest_api_prefix(FAMILY_NAME));
+    assert_eq!(family.namespace().len(), 6);
+
+    let other = Family::new("dgc_test");
+    assert_eq!(other.namespace(), get_dgc_rest_api_prefix("dgc_test"));
+    assert_ne!(other.namespace(), family.namespace());
+
+    assert_eq!(Family::from_options(None, None).unwrap(), family);
+    assert_eq!(Family::from_options(Some("dgc_test"), None).unwrap(), other);
+    let custom = Family::from_options(None, Some("abc123")).unwrap();
+    assert_eq!(custom.name(), FAMILY_NAME);
+    assert_eq!(custom.namespace(), "abc123");
+}
+
+#[test]
+fn namespaces_must_be_six_lowercase_hex_characters() {
+    assert!(Family::with_namespace("dgc_test", "0a1b2c").is_ok());
+    for namespace in &["", "abc", "abcdef0", "ABCDEF", "abcdeg", "ab cde"] {
+        assert!(
+            Family::with_namespace("dgc_test", namespace).is_err(),
+            "{:?} was accepted",
+            namespace
+        );
+    }
+    assert!(Family::with_namespace("", "abcdef").is_err());
+    assert!(Family::with_namespace("dgc_test", SETTINGS_NAMESPACE).is_err());
+    assert!(Family::with_namespace("dgc_test", BLOCK_INFO_NAMESPACE).is_err());
+}
+
+#[test]
+fn handlers_register_for_their_family() {
+    let family = Family::with_namespace("dgc_test", "abcdef").unwrap();
+    let handler = handler(&family);
+    assert_eq!(handler.family_name(), "dgc_test");
+    assert_eq!(handler.namespaces(), vec![String::from("abcdef")]);
+    assert_eq!(handler.family_versions(), vec![FAMILY_VERSION, FAMILY_VERSION_2]);
+
+    let default = TransactionHandlerDGC::new();
+    assert_eq!(default.family_name(), FAMILY_NAME);
+    assert_eq!(default.namespaces(), vec![Family::default().namespace().to_string()]);
+}
+
+#[test]
+fn state_is_kept_under_the_family_namespace() {
+    let family = Family::with_namespace("dgc_test", "abcdef").unwrap();
+    let signer = public_key(0);
+    let mut context = create_participant(&family, &signer, "alice");
+
+    for address in context.state().keys() {
+        let in_namespace = address.starts_with("abcdef");
+        let shared = address.starts_with(SETTINGS_NAMESPACE)
+            || address.starts_with(BLOCK_INFO_NAMESPACE);
+        assert!(in_namespace || shared, "{} is outside the namespace", address);
+    }
+    assert!(context
+        .state()
+        .contains_key(&make_participant_address("abcdef", &signer)));
+
+    let decoded = decode_state("abcdef", &family_state(&context, "abcdef")).unwrap();
+    assert_eq!(decoded["participants"].as_array().map(|p| p.len()), Some(1));
+    // Entries of another namespace are not mistaken for this family's
+    assert!(decode_state(Family::default().namespace(), context.state()).is_err());
+
+    let default = Family::default();
+    assert!(StateDGC::new(&mut context, "abcdef")
+        .get_participant(&signer)
+        .unwrap()
+        .is_some());
+    assert!(StateDGC::new(&mut context, default.namespace())
+        .get_participant(&signer)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn declared_addresses_follow_the_namespace() {
+    let family = Family::with_namespace("dgc_test", "abcdef").unwrap();
+    let signer = public_key(0);
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name("alice".to_string());
+    let action = PayloadAction::CreateParticipant(action);
+
+    let outputs = make_addresses(&family, &action, &signer);
+    assert!(outputs.contains(&make_participant_address("abcdef", &signer)));
+    for address in &outputs {
+        assert!(address.starts_with("abcdef"), "{} is outside the namespace", address);
+    }
+    let inputs = make_inputs(&family, &action, &signer, &signer);
+    for address in &outputs {
+        assert!(inputs.contains(address));
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of organizations: they own and hold records, and their members act
+//! for them in proposals, answers, finalizing and revoking reporters only
+//! with the permissions they hold, and only while they are members.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::settings::ADMINS_KEY;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use organization::Organization_Member_Permission as Member;
+
+use common::{public_key, set_setting, Chain};
+
+const ORGANIZATION: &str = "acme";
+
+/// A chain where alice founded "acme", which owns and holds "crate-1", and
+/// bob and carol are participants outside it.
+fn owned_by_organization() -> (Chain, [String; 3]) {
+    let mut chain = Chain::new();
+    let keys = [public_key(0), public_key(1), public_key(2)];
+    set_setting(&mut chain.context, ADMINS_KEY, &public_key(9));
+    for (key, name) in keys.iter().zip(&["alice", "bob", "carol"]) {
+        chain.create_participant(key, name);
+    }
+    let alice = &keys[0];
+
+    let mut organization = payload_v2::CreateOrganizationAction::new();
+    organization.set_organization_id(ORGANIZATION.to_string());
+    organization.set_name("Acme Corporation".to_string());
+    chain.apply(alice, PayloadAction::CreateOrganization(organization)).unwrap();
+
+    let mut property = property::PropertySchema::new();
+    property.set_name("weight".to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![property]));
+    chain.apply(alice, PayloadAction::CreateTable(table)).unwrap();
+
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(alice, PayloadAction::CreateRecord(record)).unwrap();
+
+    let ownership = proposal::Proposal_Role::transferOwnership;
+    let custodianship = proposal::Proposal_Role::transferCustodianship;
+    chain.apply(alice, propose("give-1", ownership, ORGANIZATION)).unwrap();
+    chain.apply(alice, accept("give-1")).unwrap();
+    chain.apply(alice, propose("hold-1", custodianship, ORGANIZATION)).unwrap();
+    chain.apply(alice, accept("hold-1")).unwrap();
+    (chain, keys)
+}
+
+fn set_member(participant: &str, permissions: Vec<Member>) -> PayloadAction {
+    let mut action = payload_v2::SetOrganizationMemberAction::new();
+    action.set_organization_id(ORGANIZATION.to_string());
+    action.set_participant_id(participant.to_string());
+    action.set_permissions(permissions);
+    PayloadAction::SetOrganizationMember(action)
+}
+
+fn propose(id: &str, role: proposal::Proposal_Role, receiver: &str) -> PayloadAction {
+    let mut action = payload::CreateProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_role(role);
+    action.set_record_id("crate-1".to_string());
+    action.set_receiving_participant(receiver.to_string());
+    PayloadAction::CreateProposal(action)
+}
+
+fn answer(id: &str, response: payload::AnswerProposalAction_Response) -> PayloadAction {
+    let mut action = payload::AnswerProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_response(response);
+    PayloadAction::AnswerProposal(action)
+}
+
+fn accept(id: &str) -> PayloadAction {
+    answer(id, payload::AnswerProposalAction_Response::ACCEPT)
+}
+
+fn finalize() -> PayloadAction {
+    let mut action = payload::FinalizeRecordAction::new();
+    action.set_record_id("crate-1".to_string());
+    PayloadAction::FinalizeRecord(action)
+}
+
+fn revoke(reporter: &str) -> PayloadAction {
+    let mut action = payload::RevokeReporterAction::new();
+    action.set_record_id("crate-1".to_string());
+    action.set_reporter_id(reporter.to_string());
+    action.set_properties(RepeatedField::from_vec(vec!["weight".to_string()]));
+    PayloadAction::RevokeReporter(action)
+}
+
+fn owner(chain: &mut Chain) -> String {
+    let record = chain.record("crate-1").unwrap();
+    record.get_owners().last().unwrap().get_participant_id().to_string()
+}
+
+fn custodian(chain: &mut Chain) -> String {
+    let record = chain.record("crate-1").unwrap();
+    record.get_custodians().last().unwrap().get_participant_id().to_string()
+}
+
+fn assert_refused(result: Result<(), ApplyError>, expected: &str) {
+    match result {
+        Err(ApplyError::InvalidTransaction(msg)) => assert_eq!(msg, expected),
+        other => panic!("action was not refused: {:?}", other),
+    }
+}
+
+#[test]
+fn organizations_own_and_hold_records() {
+    let (mut chain, [alice, bob, _]) = owned_by_organization();
+    assert_eq!(owner(&mut chain), ORGANIZATION);
+    assert_eq!(custodian(&mut chain), ORGANIZATION);
+
+    // Proposals for its records are issued by the organization
+    let ownership = proposal::Proposal_Role::transferOwnership;
+    chain.apply(&alice, propose("sale-1", ownership, &bob)).unwrap();
+    let sale = chain.proposal("sale-1").unwrap();
+    assert_eq!(sale.get_issuing_participant(), ORGANIZATION);
+
+    chain.apply(&bob, accept("sale-1")).unwrap();
+    assert_eq!(owner(&mut chain), bob);
+    assert_eq!(custodian(&mut chain), ORGANIZATION);
+}
+
+#[test]
+fn the_founder_acts_for_the_organization() {
+    let (mut chain, [alice, bob, _]) = owned_by_organization();
+    let ownership = proposal::Proposal_Role::transferOwnership;
+
+    chain.apply(&alice, propose("sale-1", ownership, &bob)).unwrap();
+    chain.apply(&alice, answer("sale-1", payload::AnswerProposalAction_Response::CANCEL)).unwrap();
+    assert_eq!(
+        chain.proposal("sale-1").unwrap().get_status(),
+        proposal::Proposal_Status::CANCELED
+    );
+
+    // The founder accepted the record, so it reports on it for the
+    // organization
+    chain.apply(&alice, revoke(&alice)).unwrap();
+    chain.apply(&alice, finalize()).unwrap();
+    assert!(chain.record("crate-1").unwrap().get_field_final());
+}
+
+#[test]
+fn members_need_the_permission_for_each_action() {
+    let (mut chain, [alice, bob, carol]) = owned_by_organization();
+    let ownership = proposal::Proposal_Role::transferOwnership;
+    let custodianship = proposal::Proposal_Role::transferCustodianship;
+
+    // Neither a participant outside the organization nor a member without
+    // the permission can act for it
+    chain.apply(&alice, set_member(&bob, vec![Member::MANAGE_MEMBERS])).unwrap();
+    for signer in &[&carol, &bob] {
+        assert_refused(
+            chain.apply(signer, propose("sale-1", ownership, &carol)),
+            "Only the owner can create a proposal to change ownership",
+        );
+        assert_refused(
+            chain.apply(signer, finalize()),
+            "Must be owner and custodian to finalize record",
+        );
+        assert_refused(chain.apply(signer, revoke(&alice)), "Must be owner to revoke reporters");
+    }
+
+    chain.apply(&alice, set_member(&bob, vec![Member::PROPOSE])).unwrap();
+    chain.apply(&bob, propose("sale-1", ownership, &carol)).unwrap();
+    chain.apply(&bob, answer("sale-1", payload::AnswerProposalAction_Response::CANCEL)).unwrap();
+
+    // Answering a proposal made to the organization needs ANSWER
+    chain.apply(&alice, propose("hold-2", custodianship, &carol)).unwrap();
+    chain.apply(&carol, accept("hold-2")).unwrap();
+    chain.apply(&carol, propose("hold-3", custodianship, ORGANIZATION)).unwrap();
+    assert_refused(
+        chain.apply(&bob, accept("hold-3")),
+        "Only the participant a proposal was sent to can accept it",
+    );
+    chain.apply(&alice, set_member(&bob, vec![Member::PROPOSE, Member::ANSWER])).unwrap();
+    chain.apply(&bob, accept("hold-3")).unwrap();
+    assert_eq!(custodian(&mut chain), ORGANIZATION);
+
+    let permissions = vec![Member::FINALIZE, Member::REVOKE_REPORTER];
+    chain.apply(&alice, set_member(&bob, permissions)).unwrap();
+    chain.apply(&bob, revoke(&alice)).unwrap();
+    chain.apply(&bob, finalize()).unwrap();
+    assert!(chain.record("crate-1").unwrap().get_field_final());
+}
+
+#[test]
+fn removed_members_lose_access() {
+    let (mut chain, [alice, bob, carol]) = owned_by_organization();
+    let ownership = proposal::Proposal_Role::transferOwnership;
+    chain.apply(&alice, set_member(&bob, vec![Member::PROPOSE, Member::FINALIZE])).unwrap();
+    chain.apply(&bob, propose("sale-1", ownership, &carol)).unwrap();
+
+    chain.apply(&alice, set_member(&bob, Vec::new())).unwrap();
+    assert_refused(
+        chain.apply(&bob, answer("sale-1", payload::AnswerProposalAction_Response::CANCEL)),
+        "Only the participant who made a proposal can cancel it",
+    );
+    assert_refused(
+        chain.apply(&bob, propose("sale-2", ownership, &carol)),
+        "Only the owner can create a proposal to change ownership",
+    );
+    assert_refused(
+        chain.apply(&bob, finalize()),
+        "Must be owner and custodian to finalize record",
+    );
+    assert_refused(
+        chain.apply(&bob, set_member(&bob, vec![Member::PROPOSE])),
+        &format!(
+            "Only a member allowed to manage members can change the members of {}",
+            ORGANIZATION
+        ),
+    );
+
+    // The organization's last manager cannot be removed
+    assert_refused(
+        chain.apply(&alice, set_member(&alice, Vec::new())),
+        &format!("Organization {} must keep a member who can manage members", ORGANIZATION),
+    );
+}
@@ -0,0 +1,202 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of participant profiles: creating and updating them, keeping
+//! emails unique through the email index, and the profile history.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use sawtooth_sdk::processor::handler::ApplyError;
+use sawtooth_sdk::signing;
+use sawtooth_sdk::signing::secp256k1::Secp256k1PrivateKey;
+
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::settings::ADMINS_KEY;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{public_key, set_setting, Chain};
+
+fn create(name: &str, email: &str) -> PayloadAction {
+    let mut action = payload::CreateParticipantAction::new();
+    action.set_name(name.to_string());
+    action.set_email(email.to_string());
+    PayloadAction::CreateParticipant(action)
+}
+
+fn update(name: &str, email: &str) -> PayloadAction {
+    let mut action = payload_v2::UpdateParticipantAction::new();
+    action.set_name(name.to_string());
+    action.set_email(email.to_string());
+    PayloadAction::UpdateParticipant(action)
+}
+
+fn update_recovery_key(name: &str, recovery_key: &str, remove: bool) -> PayloadAction {
+    let mut action = payload_v2::UpdateParticipantAction::new();
+    action.set_name(name.to_string());
+    action.set_recovery_key(recovery_key.to_string());
+    action.set_remove_recovery_key(remove);
+    PayloadAction::UpdateParticipant(action)
+}
+
+/// A rotation of `participant_id` to the key of private key `index`.
+fn rotate_key(participant_id: &str, index: usize) -> PayloadAction {
+    let context = signing::create_context("secp256k1").unwrap();
+    let new_key = Secp256k1PrivateKey::from_hex(&format!("{:064x}", index + 1)).unwrap();
+    let factory = signing::CryptoFactory::new(&*context);
+    let new_signer = factory.new_signer(&new_key);
+    let mut action = payload_v2::RotateKeyAction::new();
+    action.set_participant_id(participant_id.to_string());
+    action.set_new_public_key(new_signer.get_public_key().unwrap().as_hex());
+    action.set_new_key_signature(new_signer.sign(participant_id.as_bytes()).unwrap());
+    PayloadAction::RotateKey(action)
+}
+
+fn set_status(participant_id: &str, status: participant::Participant_Status) -> PayloadAction {
+    let mut action = payload_v2::SetParticipantStatusAction::new();
+    action.set_participant_id(participant_id.to_string());
+    action.set_status(status);
+    PayloadAction::SetParticipantStatus(action)
+}
+
+fn email_owner(chain: &mut Chain, email: &str) -> Option<String> {
+    chain.state().get_email_owner(email).unwrap()
+}
+
+#[test]
+fn profiles_are_updated_and_their_history_kept() {
+    let mut chain = Chain::new();
+    let alice = public_key(0);
+    chain.apply(&alice, create("alice", "alice@example.com")).unwrap();
+    let created = chain.timestamp;
+
+    chain.apply(&alice, update("Alice", "alice@example.com")).unwrap();
+    let renamed = chain.timestamp;
+    chain.apply(&alice, update("Alice", "alice@example.org")).unwrap();
+
+    let participant = chain.participant(&alice).unwrap();
+    assert_eq!(participant.get_name(), "Alice");
+    assert_eq!(participant.get_email(), "alice@example.org");
+    let history: Vec<(&str, &str, u64)> = participant
+        .get_profile_history()
+        .iter()
+        .map(|change| (change.get_name(), change.get_email(), change.get_timestamp()))
+        .collect();
+    assert_eq!(
+        history,
+        vec![
+            ("alice", "alice@example.com", renamed),
+            ("Alice", "alice@example.com", chain.timestamp),
+        ]
+    );
+    assert!(created < renamed);
+}
+
+#[test]
+fn profiles_are_validated() {
+    let mut chain = Chain::new();
+    let alice = public_key(0);
+    assert!(chain.apply(&alice, update("alice", "")).is_err());
+    chain.apply(&alice, create("alice", "")).unwrap();
+
+    // Unchanged, nameless and malformed profiles
+    assert!(chain.apply(&alice, update("alice", "")).is_err());
+    assert!(chain.apply(&alice, update("", "")).is_err());
+    for email in &["alice", "@example.com", "alice@example", "alice@.com", "a b@example.com"] {
+        assert!(chain.apply(&alice, update("alice", email)).is_err(), "{} was accepted", email);
+    }
+    assert!(chain.apply(&public_key(1), create("bob", "bob@@example.com")).is_err());
+
+    let participant = chain.participant(&alice).unwrap();
+    assert_eq!(participant.get_email(), "");
+    assert!(participant.get_profile_history().is_empty());
+}
+
+#[test]
+fn emails_are_unique_whatever_their_case() {
+    let mut chain = Chain::new();
+    let (alice, bob) = (public_key(0), public_key(1));
+    chain.apply(&alice, create("alice", "alice@example.com")).unwrap();
+    assert_eq!(email_owner(&mut chain, "Alice@Example.com"), Some(alice.clone()));
+
+    assert!(chain.apply(&bob, create("bob", " ALICE@example.com ")).is_err());
+    chain.apply(&bob, create("bob", "bob@example.com")).unwrap();
+    assert!(chain.apply(&bob, update("bob", "alice@EXAMPLE.com")).is_err());
+    assert_eq!(chain.participant(&bob).unwrap().get_email(), "bob@example.com");
+
+    // Changing the case of one's own email keeps the claim on it
+    chain.apply(&alice, update("alice", "Alice@example.com")).unwrap();
+    assert_eq!(email_owner(&mut chain, "alice@example.com"), Some(alice.clone()));
+}
+
+#[test]
+fn emails_given_up_can_be_claimed() {
+    let mut chain = Chain::new();
+    let (alice, bob) = (public_key(0), public_key(1));
+    chain.apply(&alice, create("alice", "shared@example.com")).unwrap();
+    chain.apply(&bob, create("bob", "")).unwrap();
+
+    chain.apply(&alice, update("alice", "alice@example.com")).unwrap();
+    assert_eq!(email_owner(&mut chain, "shared@example.com"), None);
+    assert_eq!(email_owner(&mut chain, "alice@example.com"), Some(alice.clone()));
+    chain.apply(&bob, update("bob", "shared@example.com")).unwrap();
+    assert_eq!(email_owner(&mut chain, "shared@example.com"), Some(bob.clone()));
+
+    // Removing an email frees it as well
+    chain.apply(&alice, update("alice", "")).unwrap();
+    assert_eq!(email_owner(&mut chain, "alice@example.com"), None);
+    assert_eq!(chain.participant(&alice).unwrap().get_email(), "");
+    chain.apply(&bob, update("bob", "alice@example.com")).unwrap();
+    assert_eq!(email_owner(&mut chain, "alice@example.com"), Some(bob));
+    assert_eq!(email_owner(&mut chain, "shared@example.com"), None);
+}
+
+#[test]
+fn recovery_keys_are_only_changed_when_given() {
+    let mut chain = Chain::new();
+    let (alice, recovery) = (public_key(0), public_key(5));
+    chain.apply(&alice, create("alice", "")).unwrap();
+    chain.apply(&alice, update_recovery_key("alice", &recovery, false)).unwrap();
+    assert_eq!(chain.participant(&alice).unwrap().get_recovery_key(), recovery);
+
+    assert!(chain.apply(&alice, update_recovery_key("alice", &recovery, false)).is_err());
+    assert!(chain.apply(&alice, update_recovery_key("alice", &alice, false)).is_err());
+    assert!(chain.apply(&alice, update_recovery_key("alice", &recovery, true)).is_err());
+
+    // A profile update which leaves the recovery key out keeps it
+    chain.apply(&alice, update("Alice", "")).unwrap();
+    assert_eq!(chain.participant(&alice).unwrap().get_recovery_key(), recovery);
+
+    chain.apply(&alice, update_recovery_key("Alice", "", true)).unwrap();
+    assert_eq!(chain.participant(&alice).unwrap().get_recovery_key(), "");
+    assert!(chain.apply(&alice, update_recovery_key("Alice", "", true)).is_err());
+}
+
+#[test]
+fn recovery_keys_cannot_act_for_inactive_participants() {
+    let mut chain = Chain::new();
+    let (alice, recovery, admin) = (public_key(0), public_key(5), public_key(9));
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+    chain.apply(&alice, create("alice", "")).unwrap();
+    chain.apply(&alice, update_recovery_key("alice", &recovery, false)).unwrap();
+
+    let suspended = participant::Participant_Status::SUSPENDED;
+    chain.apply(&admin, set_status(&alice, suspended)).unwrap();
+    match chain.apply(&recovery, rotate_key(&alice, 1)) {
+        Err(ApplyError::InvalidTransaction(msg)) => {
+            assert_eq!(msg, format!("Participant {} is suspended", alice));
+        }
+        other => panic!("suspended participant's key was rotated: {:?}", other),
+    }
+    assert!(chain.participant(&alice).unwrap().get_key_rotations().is_empty());
+
+    let active = participant::Participant_Status::ACTIVE;
+    chain.apply(&admin, set_status(&alice, active)).unwrap();
+    chain.apply(&recovery, rotate_key(&alice, 1)).unwrap();
+    let rotations = chain.participant(&alice).unwrap().get_key_rotations().to_vec();
+    assert_eq!(rotations.len(), 1);
+    assert_eq!(rotations[0].get_rotated_by(), recovery);
+}
@@ -0,0 +1,217 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of proposals: the terms counter-offers change must pass the
+//! checks a new proposal on those terms would, and records only move while
+//! they belong to the issuer.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::settings::ADMINS_KEY;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{balance, public_key, set_setting, Chain};
+
+const POLICY: &str = "vault";
+
+/// A chain with the participants alice, bob and an exchange operator, where
+/// alice owns "crate-1" and bob signs for the policy "vault" alone.
+fn setup() -> (Chain, String, String) {
+    let mut chain = Chain::new();
+    let (alice, bob, operator) = (public_key(0), public_key(1), public_key(2));
+    set_setting(&mut chain.context, ADMINS_KEY, &public_key(9));
+    chain.create_participant(&alice, "alice");
+    chain.create_participant(&bob, "bob");
+    chain.create_participant(&operator, "operator");
+
+    let mut policy = payload_v2::CreateMultisigPolicyAction::new();
+    policy.set_policy_id(POLICY.to_string());
+    policy.set_signers(RepeatedField::from_vec(vec![bob.clone()]));
+    policy.set_threshold(1);
+    chain.apply(&bob, PayloadAction::CreateMultisigPolicy(policy)).unwrap();
+
+    let mut property = property::PropertySchema::new();
+    property.set_name("weight".to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![property]));
+    chain.apply(&alice, PayloadAction::CreateTable(table)).unwrap();
+
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(&alice, PayloadAction::CreateRecord(record)).unwrap();
+    (chain, alice, bob)
+}
+
+fn create_proposal(
+    id: &str,
+    role: proposal::Proposal_Role,
+    receiver: &str,
+    amount: f64,
+) -> PayloadAction {
+    let mut action = payload::CreateProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_role(role);
+    action.set_record_id("crate-1".to_string());
+    action.set_receiving_participant(receiver.to_string());
+    action.set_dg_coin_amount(amount);
+    PayloadAction::CreateProposal(action)
+}
+
+/// A counter-offer at `amount`, or at the proposal's amount when None.
+fn counter(proposal_id: &str, counter_id: &str, amount: Option<f64>) -> PayloadAction {
+    let mut offer = payload_v2::CounterOffer::new();
+    offer.set_proposal_id(counter_id.to_string());
+    offer.set_terms("counter-offer".to_string());
+    if let Some(amount) = amount {
+        offer.set_dg_coin_amount(amount);
+        offer.set_has_dg_coin_amount(true);
+    }
+    let mut action = payload_v2::AnswerProposalV2Action::new();
+    action.set_proposal_id(proposal_id.to_string());
+    action.set_response(payload_v2::AnswerProposalV2Action_Response::COUNTER);
+    action.set_counter(offer);
+    PayloadAction::CounterProposal(action)
+}
+
+fn accept(proposal_id: &str) -> payload::AnswerProposalAction {
+    let mut answer = payload::AnswerProposalAction::new();
+    answer.set_proposal_id(proposal_id.to_string());
+    answer.set_response(payload::AnswerProposalAction_Response::ACCEPT);
+    answer
+}
+
+/// Gives `key` `amount` dg coin through a settled buyDGC proposal.
+fn fund(chain: &mut Chain, key: &str, amount: f64) {
+    let operator = public_key(2);
+    let mut roles = payload_v2::SetRolesAction::new();
+    roles.set_public_key(operator.clone());
+    roles.set_roles(vec![role::RoleAssignment_Role::EXCHANGE_OPERATOR]);
+    chain.apply(&public_key(9), PayloadAction::SetRoles(roles)).unwrap();
+
+    let buy = proposal::Proposal_Role::buyDGC;
+    chain.apply(key, create_proposal("buy-1", buy, key, amount)).unwrap();
+    let mut settle = payload_v2::SettleProposalAction::new();
+    settle.set_proposal_id("buy-1".to_string());
+    settle.set_dg_coin_amount(amount);
+    settle.set_close(true);
+    chain.apply(&operator, PayloadAction::SettleProposal(settle)).unwrap();
+}
+
+fn assert_refused(result: Result<(), ApplyError>, expected: &str) {
+    match result {
+        Err(ApplyError::InvalidTransaction(msg)) => assert_eq!(msg, expected),
+        other => panic!("action was not refused: {:?}", other),
+    }
+}
+
+#[test]
+fn priced_counter_offers_need_participants_on_both_sides() {
+    let (mut chain, alice, bob) = setup();
+    let transfer = proposal::Proposal_Role::transferOwnership;
+    chain.apply(&alice, create_proposal("offer-1", transfer, POLICY, 0.0)).unwrap();
+
+    // A policy can be given the record, but not made to pay for it
+    assert_refused(
+        chain.apply(&bob, counter("offer-1", "offer-2", Some(100.0))),
+        "Only a participant can pay the price of a record",
+    );
+    assert!(chain.proposal("offer-2").is_none());
+    assert_eq!(
+        chain.proposal("offer-1").unwrap().get_status(),
+        proposal::Proposal_Status::OPEN
+    );
+    assert_refused(
+        chain.apply(&bob, counter("offer-1", "offer-2", Some(-1.0))),
+        "The price must be a positive number",
+    );
+
+    chain.apply(&bob, counter("offer-1", "offer-2", Some(0.0))).unwrap();
+    assert_eq!(chain.proposal("offer-2").unwrap().get_dg_coin_amount(), 0.0);
+
+    // Between participants the price can be countered
+    chain.apply(&alice, create_proposal("sale-1", transfer, &bob, 0.0)).unwrap();
+    chain.apply(&bob, counter("sale-1", "sale-2", Some(100.0))).unwrap();
+    assert_eq!(chain.proposal("sale-2").unwrap().get_dg_coin_amount(), 100.0);
+}
+
+#[test]
+fn prices_can_be_countered_down_to_nothing() {
+    let (mut chain, alice, bob) = setup();
+    let transfer = proposal::Proposal_Role::transferOwnership;
+    chain.apply(&alice, create_proposal("sale-1", transfer, &bob, 30.0)).unwrap();
+
+    // Without an amount the counter-offer keeps the price
+    chain.apply(&bob, counter("sale-1", "sale-2", None)).unwrap();
+    assert_eq!(chain.proposal("sale-2").unwrap().get_dg_coin_amount(), 30.0);
+    chain.apply(&alice, counter("sale-2", "sale-3", Some(0.0))).unwrap();
+    assert_eq!(chain.proposal("sale-3").unwrap().get_dg_coin_amount(), 0.0);
+
+    // Bob has no dg coin, so the record can only move as a gift
+    chain.apply(&bob, PayloadAction::AnswerProposal(accept("sale-3"))).unwrap();
+    let record = chain.record("crate-1").unwrap();
+    assert_eq!(record.get_owners().last().unwrap().get_participant_id(), bob);
+    assert_eq!(balance(&chain.participant(&alice).unwrap()), 0.0);
+}
+
+#[test]
+fn counter_offers_need_active_participants() {
+    let (mut chain, alice, bob) = setup();
+    let transfer = proposal::Proposal_Role::transferOwnership;
+    chain.apply(&alice, create_proposal("offer-1", transfer, &bob, 0.0)).unwrap();
+
+    let mut suspend = payload_v2::SetParticipantStatusAction::new();
+    suspend.set_participant_id(alice.clone());
+    suspend.set_status(participant::Participant_Status::SUSPENDED);
+    chain.apply(&public_key(9), PayloadAction::SetParticipantStatus(suspend)).unwrap();
+
+    assert_refused(
+        chain.apply(&bob, counter("offer-1", "offer-2", Some(0.0))),
+        &format!("Issuing participant {} is suspended", alice),
+    );
+    assert!(chain.proposal("offer-2").is_none());
+}
+
+#[test]
+fn countered_transfers_must_be_covered_by_the_balance() {
+    let (mut chain, alice, bob) = setup();
+    fund(&mut chain, &alice, 10.0);
+    let transfer = proposal::Proposal_Role::transferDGC;
+    chain.apply(&alice, create_proposal("pay-1", transfer, &bob, 5.0)).unwrap();
+
+    assert_refused(
+        chain.apply(&bob, counter("pay-1", "pay-2", Some(50.0))),
+        &format!("The dg coin balance of issuing participant is not enough: {}", alice),
+    );
+    chain.apply(&bob, counter("pay-1", "pay-2", Some(10.0))).unwrap();
+    chain.apply(&alice, PayloadAction::AnswerProposal(accept("pay-2"))).unwrap();
+    assert_eq!(balance(&chain.participant(&alice).unwrap()), 0.0);
+    assert_eq!(balance(&chain.participant(&bob).unwrap()), 10.0);
+}
+
+#[test]
+fn transfers_of_records_given_away_since_are_refused() {
+    let (mut chain, alice, bob) = setup();
+    let carol = public_key(2);
+    let transfer = proposal::Proposal_Role::transferOwnership;
+    chain.apply(&alice, create_proposal("offer-1", transfer, &bob, 0.0)).unwrap();
+    chain.apply(&alice, create_proposal("offer-2", transfer, &carol, 0.0)).unwrap();
+    chain.apply(&carol, PayloadAction::AnswerProposal(accept("offer-2"))).unwrap();
+
+    assert_refused(
+        chain.apply(&bob, PayloadAction::AnswerProposal(accept("offer-1"))),
+        "Record has changed owner since the proposal was made: crate-1",
+    );
+    let record = chain.record("crate-1").unwrap();
+    assert_eq!(record.get_owners().last().unwrap().get_participant_id(), carol);
+}
@@ -0,0 +1,140 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks that replaying transactions against in-memory state, as
+//! `dgc-replay` does, is deterministic and applies them the way a validator
+//! would.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use std::collections::HashMap;
+
+use sawtooth_sdk::messages::processor::TpProcessRequest;
+use sawtooth_sdk::processor::handler::{ApplyError, TransactionContext, TransactionHandler};
+
+use dgc_rest_api_tp::addressing::{Family, FAMILY_NAME};
+use dgc_rest_api_tp::block_time::LEGACY_RULES_UNTIL_KEY;
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::decode::decode_state;
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::*;
+
+use common::{family_state, new_context, public_key, request, request_v1, set_setting, TIMESTAMP};
+
+/// A mix of accepted and rejected transactions in both family versions.
+fn transactions() -> Vec<TpProcessRequest> {
+    let (alice, bob) = (public_key(0), public_key(1));
+    let participant = |name: &str| {
+        let mut action = payload::CreateParticipantAction::new();
+        action.set_name(name.to_string());
+        PayloadAction::CreateParticipant(action)
+    };
+    let transfer = |id: &str, amount: f64| {
+        let mut action = payload::CreateProposalAction::new();
+        action.set_proposal_id(id.to_string());
+        action.set_role(proposal::Proposal_Role::transferDGC);
+        action.set_receiving_participant(public_key(1));
+        action.set_dg_coin_amount(amount);
+        PayloadAction::CreateProposal(action)
+    };
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+
+    vec![
+        request_v1(&alice, participant("alice"), TIMESTAMP),
+        request(&bob, participant("bob"), TIMESTAMP + 1),
+        // Already registered
+        request(&bob, participant("bob"), TIMESTAMP + 2),
+        // Not enough balance
+        request(&alice, transfer("transfer", 10.0), TIMESTAMP + 3),
+        // Version 1.1 does not check the amount in legacy blocks
+        request_v1(&alice, transfer("transfer", 0.0), TIMESTAMP + 4),
+        // A table needs properties
+        request_v1(&alice, PayloadAction::CreateTable(table), TIMESTAMP + 5),
+        make_process_request(&Family::default(), FAMILY_VERSION_2, &alice, b"\x08\xff", "sig"),
+    ]
+}
+
+fn replay(requests: &[TpProcessRequest]) -> (MemoryContext, Vec<bool>) {
+    let handler = TransactionHandlerDGC::new();
+    let mut context = new_context();
+    set_setting(&mut context, LEGACY_RULES_UNTIL_KEY, "1");
+    let results = requests
+        .iter()
+        .map(|request| context.apply(&handler, request).is_ok())
+        .collect();
+    (context, results)
+}
+
+#[test]
+fn replays_are_deterministic() {
+    let (first, first_results) = replay(&transactions());
+    let (second, second_results) = replay(&transactions());
+
+    assert_eq!(first_results, vec![true, true, false, false, true, false, false]);
+    assert_eq!(first_results, second_results);
+    assert_eq!(first.state(), second.state());
+
+    let namespace = Family::default().namespace().to_string();
+    let decoded = decode_state(&namespace, &family_state(&first, &namespace)).unwrap();
+    assert_eq!(decoded, decode_state(&namespace, &family_state(&second, &namespace)).unwrap());
+    assert_eq!(decoded["participants"].as_array().map(|p| p.len()), Some(2));
+    assert_eq!(decoded["proposals"].as_array().map(|p| p.len()), Some(1));
+}
+
+/// Writes an entry and then fails, as a transaction can part way through.
+struct FailingHandler;
+
+impl TransactionHandler for FailingHandler {
+    fn family_name(&self) -> String {
+        FAMILY_NAME.to_string()
+    }
+
+    fn family_versions(&self) -> Vec<String> {
+        vec![FAMILY_VERSION_2.to_string()]
+    }
+
+    fn namespaces(&self) -> Vec<String> {
+        vec![Family::default().namespace().to_string()]
+    }
+
+    fn apply(
+        &self,
+        _request: &TpProcessRequest,
+        context: &mut TransactionContext,
+    ) -> Result<(), ApplyError> {
+        let mut entries = HashMap::new();
+        entries.insert(format!("{}{:064}", self.namespaces()[0], 0), vec![1]);
+        context.set_state(entries)?;
+        Err(ApplyError::InvalidTransaction(String::from("failed after writing")))
+    }
+}
+
+#[test]
+fn rejected_transactions_leave_no_writes() {
+    let (mut context, _) = replay(&transactions());
+    let before = context.state().clone();
+
+    assert!(context.apply(&FailingHandler, &transactions()[0]).is_err());
+    assert_eq!(context.state(), &before);
+}
+
+#[test]
+fn undecodable_payloads_are_given_to_the_handler() {
+    let signer = public_key(0);
+    let request = make_process_request(&Family::default(), FAMILY_VERSION, &signer, b"\xff", "sig");
+    let inputs = request.get_header().get_inputs();
+    assert!(inputs.contains(&Family::default().namespace().to_string()));
+
+    let mut context = MemoryContext::new();
+    match context.apply(&TransactionHandlerDGC::new(), &request) {
+        Err(ApplyError::InvalidTransaction(msg)) => assert_eq!(msg, "Cannot deserialize payload"),
+        other => panic!("undecodable payload was not rejected: {:?}", other),
+    }
+    assert!(context.state().is_empty());
+}
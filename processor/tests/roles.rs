@@ -0,0 +1,117 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the permissions roles grant: which are open to everyone until
+//! `dgc.roles.enforce` is set, and which need a role from the start.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::roles::ENFORCE_KEY;
+use dgc_rest_api_tp::settings::ADMINS_KEY;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{balance, public_key, set_setting, Chain};
+
+fn set_roles(key: &str, roles: Vec<role::RoleAssignment_Role>) -> PayloadAction {
+    let mut action = payload_v2::SetRolesAction::new();
+    action.set_public_key(key.to_string());
+    action.set_roles(roles);
+    PayloadAction::SetRoles(action)
+}
+
+fn create_proposal(id: &str, role: proposal::Proposal_Role, receiver: &str) -> PayloadAction {
+    let mut action = payload::CreateProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_role(role);
+    action.set_receiving_participant(receiver.to_string());
+    action.set_dg_coin_amount(10.0);
+    PayloadAction::CreateProposal(action)
+}
+
+fn settle(id: &str) -> PayloadAction {
+    let mut action = payload_v2::SettleProposalAction::new();
+    action.set_proposal_id(id.to_string());
+    action.set_dg_coin_amount(10.0);
+    action.set_close(true);
+    PayloadAction::SettleProposal(action)
+}
+
+fn set_credit_limit(key: &str, limit: f64) -> PayloadAction {
+    let mut action = payload_v2::SetCreditLimitAction::new();
+    action.set_participant_id(key.to_string());
+    action.set_dg_coin_amount(limit);
+    PayloadAction::SetCreditLimit(action)
+}
+
+fn create_table(name: &str) -> PayloadAction {
+    let mut property = property::PropertySchema::new();
+    property.set_name("weight".to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    let mut action = payload::CreateTableAction::new();
+    action.set_name(name.to_string());
+    action.set_properties(vec![property].into());
+    PayloadAction::CreateTable(action)
+}
+
+#[test]
+fn settling_exchanges_needs_a_role_from_the_start() {
+    let mut chain = Chain::new();
+    let (admin, alice, operator) = (public_key(9), public_key(0), public_key(1));
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+    chain.create_participant(&alice, "alice");
+    chain.create_participant(&operator, "operator");
+    let buy = proposal::Proposal_Role::buyDGC;
+    chain.apply(&alice, create_proposal("proposal-1", buy, &alice)).unwrap();
+
+    // The issuer cannot settle its own proposal without the role
+    assert!(chain.apply(&alice, settle("proposal-1")).is_err());
+    assert!(chain.apply(&operator, settle("proposal-1")).is_err());
+    assert_eq!(balance(&chain.participant(&alice).unwrap()), 0.0);
+
+    let exchange_operator = vec![role::RoleAssignment_Role::EXCHANGE_OPERATOR];
+    assert!(chain.apply(&operator, set_roles(&operator, exchange_operator.clone())).is_err());
+    chain.apply(&admin, set_roles(&operator, exchange_operator)).unwrap();
+    chain.apply(&operator, settle("proposal-1")).unwrap();
+    assert_eq!(balance(&chain.participant(&alice).unwrap()), 10.0);
+}
+
+#[test]
+fn issuing_credit_needs_a_role_from_the_start() {
+    let mut chain = Chain::new();
+    let (admin, bank, alice) = (public_key(9), public_key(0), public_key(1));
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+    chain.create_participant(&bank, "bank");
+    chain.create_participant(&alice, "alice");
+    let credit = proposal::Proposal_Role::creditDGC;
+
+    assert!(chain.apply(&bank, create_proposal("proposal-1", credit, &alice)).is_err());
+    assert!(chain.proposal("proposal-1").is_none());
+    assert!(chain.apply(&bank, set_credit_limit(&alice, 500.0)).is_err());
+    assert!(chain.participant(&alice).unwrap().get_dg_coin_credits().is_empty());
+
+    chain.apply(&admin, set_roles(&bank, vec![role::RoleAssignment_Role::CREDIT_ISSUER])).unwrap();
+    chain.apply(&bank, create_proposal("proposal-1", credit, &alice)).unwrap();
+    assert!(chain.proposal("proposal-1").is_some());
+    chain.apply(&bank, set_credit_limit(&alice, 500.0)).unwrap();
+    let credits = chain.participant(&alice).unwrap().get_dg_coin_credits().to_vec();
+    assert_eq!(credits.last().unwrap().get_dg_coin_amount(), 500.0);
+}
+
+#[test]
+fn creating_tables_is_open_until_roles_are_enforced() {
+    let mut chain = Chain::new();
+    let (admin, alice) = (public_key(9), public_key(0));
+    set_setting(&mut chain.context, ADMINS_KEY, &admin);
+    chain.create_participant(&alice, "alice");
+    chain.apply(&alice, create_table("crate")).unwrap();
+
+    set_setting(&mut chain.context, ENFORCE_KEY, "true");
+    assert!(chain.apply(&alice, create_table("shipment")).is_err());
+    chain.apply(&admin, set_roles(&alice, vec![role::RoleAssignment_Role::TABLE_ADMIN])).unwrap();
+    chain.apply(&alice, create_table("shipment")).unwrap();
+}
@@ -0,0 +1,187 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Round trips between table schema files and the protobuf messages. A
+//! table read from JSON or YAML must come back unchanged after conversion
+//! to a `Table` and back, and a `Table` must survive being exported.
+
+extern crate dgc_rest_api_tp;
+extern crate proptest;
+extern crate protobuf;
+
+use proptest::prelude::*;
+
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::schema::{
+    PropertyDefinition, PropertyValueDefinition, SchemaError, SchemaFormat, TableDefinition,
+};
+
+const DATA_TYPES: [&str; 7] = [
+    "BYTES", "BOOLEAN", "NUMBER", "STRING", "ENUM", "STRUCT", "LOCATION",
+];
+
+fn name() -> BoxedStrategy<String> {
+    "[a-z][a-z0-9_]{0,8}".boxed()
+}
+
+fn property() -> BoxedStrategy<PropertyDefinition> {
+    let leaf = (
+        name(),
+        prop::sample::select(&DATA_TYPES[..]),
+        any::<(bool, bool, bool)>(),
+        -6i32..6,
+        prop::collection::vec(name(), 0..3),
+        prop::option::of(name()),
+    )
+        .prop_map(
+            |(name, data_type, (required, fixed, delayed), exponent, options, unit)| {
+                PropertyDefinition {
+                    name,
+                    data_type: data_type.to_string(),
+                    required,
+                    fixed,
+                    delayed,
+                    number_exponent: exponent,
+                    enum_options: options,
+                    struct_properties: Vec::new(),
+                    unit: unit.unwrap_or_default(),
+                }
+            },
+        );
+
+    leaf.prop_recursive(3, 12, 3, |inner| {
+        (name(), prop::collection::vec(inner, 1..3)).prop_map(|(name, struct_properties)| {
+            PropertyDefinition {
+                name,
+                data_type: "STRUCT".to_string(),
+                required: false,
+                fixed: false,
+                delayed: false,
+                number_exponent: 0,
+                enum_options: Vec::new(),
+                struct_properties,
+                unit: String::new(),
+            }
+        })
+    }).boxed()
+}
+
+fn table() -> BoxedStrategy<TableDefinition> {
+    (name(), prop::collection::vec(property(), 1..4))
+        .prop_map(|(name, properties)| TableDefinition { name, properties })
+        .boxed()
+}
+
+fn round_trip(definition: &TableDefinition, format: SchemaFormat) -> TableDefinition {
+    let contents = definition.to_string(format).expect("schema exports");
+    TableDefinition::parse(&contents, format).expect("exported schema parses")
+}
+
+proptest! {
+    #[test]
+    fn schema_files_round_trip(definition in table()) {
+        prop_assert_eq!(&round_trip(&definition, SchemaFormat::Json), &definition);
+        prop_assert_eq!(&round_trip(&definition, SchemaFormat::Yaml), &definition);
+    }
+
+    #[test]
+    fn tables_round_trip(definition in table()) {
+        let table = definition.to_table().expect("definition converts to a table");
+        prop_assert_eq!(&TableDefinition::from_table(&table), &definition);
+
+        for format in &[SchemaFormat::Json, SchemaFormat::Yaml] {
+            let exported = TableDefinition::from_table(&table);
+            let imported = round_trip(&exported, *format).to_table().unwrap();
+            prop_assert_eq!(&imported, &table);
+        }
+    }
+}
+
+#[test]
+fn defaults_are_omitted_and_restored() {
+    let contents = r#"{"name": "crate", "properties": [{"name": "weight", "data_type": "number"}]}"#;
+    let definition = TableDefinition::parse(contents, SchemaFormat::Json).unwrap();
+    let table = definition.to_table().unwrap();
+
+    let weight = &table.get_properties()[0];
+    assert_eq!(weight.get_data_type(), property::PropertySchema_DataType::NUMBER);
+    assert!(!weight.get_required());
+    assert_eq!(weight.get_number_exponent(), 0);
+
+    let exported = TableDefinition::from_table(&table)
+        .to_string(SchemaFormat::Json)
+        .unwrap();
+    assert!(!exported.contains("required"));
+    assert!(!exported.contains("struct_properties"));
+    assert!(exported.contains("\"NUMBER\""));
+}
+
+#[test]
+fn invalid_tables_are_rejected() {
+    let unknown_type = r#"{"name": "crate", "properties": [{"name": "a", "data_type": "DATE"}]}"#;
+    let definition = TableDefinition::parse(unknown_type, SchemaFormat::Json).unwrap();
+    match definition.to_table() {
+        Err(SchemaError::InvalidSchema(msg)) => assert!(msg.contains("DATE")),
+        other => panic!("expected an invalid schema, got {:?}", other),
+    }
+
+    let no_properties = TableDefinition {
+        name: "crate".to_string(),
+        properties: Vec::new(),
+    };
+    assert!(no_properties.to_create_table_action().is_err());
+
+    assert!(TableDefinition::parse("name: [", SchemaFormat::Yaml).is_err());
+}
+
+#[test]
+fn property_values_convert() {
+    let contents = r#"
+- name: serial
+  data_type: BYTES
+  bytes_value: 00ff10
+- name: position
+  data_type: LOCATION
+  location_value:
+    latitude: 45
+    longitude: -122
+- name: dimensions
+  data_type: STRUCT
+  struct_values:
+    - name: height
+      data_type: NUMBER
+      number_value: 12
+"#;
+    let values = PropertyValueDefinition::parse_list(contents, SchemaFormat::Yaml).unwrap();
+    let converted: Vec<property::PropertyValue> = values
+        .iter()
+        .map(|value| value.to_property_value().unwrap())
+        .collect();
+
+    assert_eq!(converted[0].get_bytes_value(), &[0x00, 0xff, 0x10][..]);
+    assert_eq!(converted[1].get_location_value().get_latitude(), 45);
+    assert_eq!(converted[1].get_location_value().get_longitude(), -122);
+    assert_eq!(converted[2].get_struct_values()[0].get_number_value(), 12);
+
+    let missing_location = PropertyValueDefinition {
+        location_value: None,
+        ..values[1].clone()
+    };
+    assert!(missing_location.to_property_value().is_err());
+
+    let bad_hex = PropertyValueDefinition {
+        bytes_value: "zz".to_string(),
+        ..values[0].clone()
+    };
+    assert!(bad_hex.to_property_value().is_err());
+}
+
+#[test]
+fn formats_follow_the_file_extension() {
+    assert_eq!(SchemaFormat::from_path("table.yml"), SchemaFormat::Yaml);
+    assert_eq!(SchemaFormat::from_path("table.yaml"), SchemaFormat::Yaml);
+    assert_eq!(SchemaFormat::from_path("table.json"), SchemaFormat::Json);
+    assert_eq!(SchemaFormat::from_path("table"), SchemaFormat::Json);
+    assert_eq!(SchemaFormat::from_name("YAML").unwrap(), SchemaFormat::Yaml);
+    assert!(SchemaFormat::from_name("xml").is_err());
+}
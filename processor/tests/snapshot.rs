@@ -0,0 +1,199 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the state snapshot export used by `dgc-snapshot`: reading
+//! saved snapshots and the per-Table record and history rows.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate rustc_serialize;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::RepeatedField;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::decode::decode_state;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::snapshot::*;
+use dgc_rest_api_tp::transaction::PayloadAction;
+
+use common::{family_state, public_key, Chain};
+
+fn schema(name: &str, data_type: property::PropertySchema_DataType) -> property::PropertySchema {
+    let mut schema = property::PropertySchema::new();
+    schema.set_name(name.to_string());
+    schema.set_data_type(data_type);
+    schema
+}
+
+fn number(name: &str, value: i64) -> property::PropertyValue {
+    let mut property = property::PropertyValue::new();
+    property.set_name(name.to_string());
+    property.set_data_type(property::PropertySchema_DataType::NUMBER);
+    property.set_number_value(value);
+    property
+}
+
+fn enum_value(name: &str, value: &str) -> property::PropertyValue {
+    let mut property = property::PropertyValue::new();
+    property.set_name(name.to_string());
+    property.set_data_type(property::PropertySchema_DataType::ENUM);
+    property.set_enum_value(value.to_string());
+    property
+}
+
+/// A chain with one table of two properties and two records, one of which
+/// has had its weight updated.
+fn chain() -> Chain {
+    let mut chain = Chain::new();
+    let owner = public_key(0);
+    chain.create_participant(&owner, "owner");
+
+    let mut weight = schema("weight", property::PropertySchema_DataType::NUMBER);
+    weight.set_number_exponent(-2);
+    let mut grade = schema("grade", property::PropertySchema_DataType::ENUM);
+    grade.set_enum_options(RepeatedField::from_vec(vec!["low".to_string(), "high".to_string()]));
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![weight, grade]));
+    chain.apply(&owner, PayloadAction::CreateTable(table)).unwrap();
+
+    for &(id, ref properties) in &[
+        ("crate-1", vec![number("weight", 1250), enum_value("grade", "high")]),
+        ("crate-2", vec![]),
+    ] {
+        let mut record = payload::CreateRecordAction::new();
+        record.set_record_id(id.to_string());
+        record.set_table("crate".to_string());
+        record.set_properties(RepeatedField::from_vec(properties.clone()));
+        chain.apply(&owner, PayloadAction::CreateRecord(record)).unwrap();
+    }
+
+    let mut update = payload::UpdatePropertiesAction::new();
+    update.set_record_id("crate-1".to_string());
+    update.set_properties(RepeatedField::from_vec(vec![number("weight", 1300)]));
+    chain.apply(&owner, PayloadAction::UpdateProperties(update)).unwrap();
+    chain
+}
+
+#[test]
+fn tables_export_current_values_and_history() {
+    let chain = chain();
+    let owner = public_key(0);
+    let namespace = Family::default().namespace().to_string();
+    let exports = export_tables(&namespace, chain.context.state()).unwrap();
+
+    assert_eq!(exports.len(), 1);
+    let export = &exports[0];
+    assert_eq!(export.name, "crate");
+    assert_eq!(
+        export.header,
+        vec!["record_id", "owner", "custodian", "final", "weight", "grade"]
+    );
+    assert_eq!(
+        export.records,
+        vec![
+            vec!["crate-1", &owner, &owner, "false", "13.00", "high"],
+            vec!["crate-2", &owner, &owner, "false", "", ""],
+        ]
+    );
+
+    let weights: Vec<&Vec<String>> = export
+        .history
+        .iter()
+        .filter(|row| row[1] == "weight")
+        .collect();
+    assert_eq!(weights.len(), 2);
+    assert_eq!(weights[0][6], "12.50");
+    assert_eq!(weights[1][6], "13.00");
+    for row in &export.history {
+        assert_eq!(row.len(), HISTORY_HEADER.len());
+        assert_eq!(row[0], "crate-1");
+        assert_eq!(row[5], owner);
+    }
+}
+
+#[test]
+fn snapshots_are_read_in_every_saved_form() {
+    let chain = chain();
+    let namespace = Family::default().namespace().to_string();
+    let state = &family_state(&chain.context, &namespace);
+    let entries: Vec<String> = state
+        .iter()
+        .map(|(address, data)| {
+            format!(r#"{{"address":"{}","data":"{}"}}"#, address, data.to_base64(STANDARD))
+        })
+        .collect();
+    let object: Vec<String> = state
+        .iter()
+        .map(|(address, data)| format!(r#""{}":"{}""#, address, data.to_base64(STANDARD)))
+        .collect();
+
+    let array = format!("[{}]", entries.join(","));
+    let response = format!(r#"{{"data":{},"head":"abc"}}"#, array);
+    let object = format!("{{{}}}", object.join(","));
+    for contents in &[array, response, object] {
+        assert_eq!(&parse_snapshot(contents).unwrap(), state);
+    }
+
+    let state = parse_snapshot(&format!("[{}]", entries.join(","))).unwrap();
+    let decoded = decode_state(&namespace, &state).unwrap();
+    assert_eq!(decoded["records"].as_array().map(|r| r.len()), Some(2));
+    assert_eq!(decoded["tables"].as_array().map(|t| t.len()), Some(1));
+}
+
+#[test]
+fn malformed_snapshots_are_rejected() {
+    for contents in &[
+        "not json",
+        "42",
+        r#"[{"data": "AAAA"}]"#,
+        r#"[{"address": "abc"}]"#,
+        r#"{"abc": "not base64!"}"#,
+    ] {
+        assert!(parse_snapshot(contents).is_err(), "{} was accepted", contents);
+    }
+}
+
+#[test]
+fn numbers_are_scaled_exactly() {
+    assert_eq!(format_number(0, -3), "0");
+    assert_eq!(format_number(15, 2), "1500");
+    assert_eq!(format_number(1250, -2), "12.50");
+    assert_eq!(format_number(5, -3), "0.005");
+    assert_eq!(format_number(-5, -3), "-0.005");
+    assert_eq!(format_number(-1234, -1), "-123.4");
+    assert_eq!(format_number(i64::MIN, -2), "-92233720368547758.08");
+}
+
+#[test]
+fn values_are_formatted_by_data_type() {
+    let mut property = property::Property::new();
+    let mut value = property::PropertyPage_ReportedValue::new();
+
+    property.set_data_type(property::PropertySchema_DataType::ENUM);
+    property.set_enum_options(RepeatedField::from_vec(vec!["low".to_string()]));
+    value.set_enum_value(0);
+    assert_eq!(format_value(&value, &property), "low");
+    value.set_enum_value(3);
+    assert_eq!(format_value(&value, &property), "3");
+
+    property.set_data_type(property::PropertySchema_DataType::BYTES);
+    value.set_bytes_value(vec![0xde, 0xad]);
+    assert_eq!(format_value(&value, &property), "dead");
+
+    property.set_data_type(property::PropertySchema_DataType::BOOLEAN);
+    value.set_boolean_value(true);
+    assert_eq!(format_value(&value, &property), "true");
+
+    property.set_data_type(property::PropertySchema_DataType::LOCATION);
+    value.mut_location_value().set_latitude(45);
+    value.mut_location_value().set_longitude(-122);
+    assert_eq!(format_value(&value, &property), "45,-122");
+
+    property.set_data_type(property::PropertySchema_DataType::TYPE_UNSET);
+    assert_eq!(format_value(&value, &property), "");
+}
@@ -0,0 +1,185 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks, in the spirit of the fuzz targets under `fuzz/`, that untrusted
+//! payloads and struct values are rejected without panicking and that
+//! applying the same input twice gives the same result.
+
+extern crate dgc_rest_api_tp;
+extern crate proptest;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use proptest::prelude::*;
+use protobuf::{Message, RepeatedField};
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::handler::{validate_struct_values, TransactionHandlerDGC};
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::*;
+
+use common::{public_key, Chain, TIMESTAMP};
+
+fn schema(name: &str, fields: Vec<property::PropertySchema>) -> property::PropertySchema {
+    let mut schema = property::PropertySchema::new();
+    schema.set_name(name.to_string());
+    if fields.is_empty() {
+        schema.set_data_type(property::PropertySchema_DataType::NUMBER);
+    } else {
+        schema.set_data_type(property::PropertySchema_DataType::STRUCT);
+        schema.set_struct_properties(RepeatedField::from_vec(fields));
+    }
+    schema
+}
+
+fn value(name: &str, fields: Vec<property::PropertyValue>) -> property::PropertyValue {
+    let mut value = property::PropertyValue::new();
+    value.set_name(name.to_string());
+    if fields.is_empty() {
+        value.set_data_type(property::PropertySchema_DataType::NUMBER);
+    } else {
+        value.set_data_type(property::PropertySchema_DataType::STRUCT);
+        value.set_struct_values(RepeatedField::from_vec(fields));
+    }
+    value
+}
+
+/// A struct holding a number and a nested struct.
+fn shipment_schema() -> property::PropertySchema {
+    schema(
+        "shipment",
+        vec![schema("carrier", vec![]), schema("box", vec![schema("height", vec![])])],
+    )
+}
+
+/// A struct value nested `depth` levels deep.
+fn nested_value(depth: usize) -> property::PropertyValue {
+    (0..depth).fold(value("leaf", vec![]), |inner, _| value("level", vec![inner]))
+}
+
+/// State holding a participant, a table with a struct property and a record.
+fn seeded() -> MemoryContext {
+    let mut chain = Chain::new();
+    let owner = public_key(0);
+    chain.create_participant(&owner, "owner");
+
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    table.set_properties(RepeatedField::from_vec(vec![
+        schema("weight", vec![]),
+        shipment_schema(),
+    ]));
+    chain.apply(&owner, PayloadAction::CreateTable(table)).unwrap();
+
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    chain.apply(&owner, PayloadAction::CreateRecord(record)).unwrap();
+    chain.context
+}
+
+/// Applies `payload` to two copies of `context`, which must agree on the
+/// result and the state written.
+fn apply_twice(context: &MemoryContext, version: &str, payload: &[u8]) -> Result<(), ApplyError> {
+    let handler = TransactionHandlerDGC::new();
+    let request = make_process_request(&Family::default(), version, &public_key(0), payload, "sig");
+
+    let mut first = context.clone();
+    let mut second = context.clone();
+    let first_result = first.apply(&handler, &request);
+    let second_result = second.apply(&handler, &request);
+
+    assert_eq!(format!("{:?}", first_result), format!("{:?}", second_result));
+    assert_eq!(first.state(), second.state());
+    first_result
+}
+
+fn update_payload(version: &str, properties: Vec<property::PropertyValue>) -> Vec<u8> {
+    let mut update = payload::UpdatePropertiesAction::new();
+    update.set_record_id("crate-1".to_string());
+    update.set_properties(RepeatedField::from_vec(properties));
+    let action = PayloadAction::UpdateProperties(update);
+    if version == FAMILY_VERSION {
+        make_payload(action, TIMESTAMP).unwrap().write_to_bytes().unwrap()
+    } else {
+        make_payload_v2(action, TIMESTAMP).write_to_bytes().unwrap()
+    }
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_payloads_are_handled_deterministically(
+        payload in prop::collection::vec(any::<u8>(), 0..256),
+        v2 in any::<bool>(),
+    ) {
+        let version = if v2 { FAMILY_VERSION_2 } else { FAMILY_VERSION };
+        let _ = apply_twice(&MemoryContext::new(), version, &payload);
+        let _ = apply_twice(&seeded(), version, &payload);
+    }
+
+    #[test]
+    fn corrupted_payloads_are_handled_deterministically(
+        index in any::<prop::sample::Index>(),
+        byte in any::<u8>(),
+        v2 in any::<bool>(),
+    ) {
+        let version = if v2 { FAMILY_VERSION_2 } else { FAMILY_VERSION };
+        let shipment = value(
+            "shipment",
+            vec![value("carrier", vec![]), value("box", vec![value("height", vec![])])],
+        );
+        let mut payload = update_payload(version, vec![value("weight", vec![]), shipment]);
+        let position = index.index(payload.len());
+        payload[position] = byte;
+        let _ = apply_twice(&seeded(), version, &payload);
+    }
+}
+
+#[test]
+fn valid_update_is_applied() {
+    let shipment = value(
+        "shipment",
+        vec![value("box", vec![value("height", vec![])]), value("carrier", vec![])],
+    );
+    for version in &[FAMILY_VERSION, FAMILY_VERSION_2] {
+        let payload = update_payload(version, vec![shipment.clone()]);
+        assert!(apply_twice(&seeded(), version, &payload).is_ok());
+    }
+}
+
+#[test]
+fn deeply_nested_values_are_rejected() {
+    for depth in &[3, 50, 1000] {
+        let payload = update_payload(FAMILY_VERSION_2, vec![nested_value(*depth)]);
+        match apply_twice(&seeded(), FAMILY_VERSION_2, &payload) {
+            Err(ApplyError::InvalidTransaction(_)) => (),
+            other => panic!("depth {} was not rejected: {:?}", depth, other),
+        }
+    }
+}
+
+#[test]
+fn struct_values_must_match_the_schema() {
+    let schema = shipment_schema();
+    let check = |fields: Vec<property::PropertyValue>| {
+        validate_struct_values(&RepeatedField::from_vec(fields), &schema.struct_properties)
+    };
+
+    let height = || value("box", vec![value("height", vec![])]);
+    assert!(check(vec![value("carrier", vec![]), height()]).is_ok());
+    assert!(check(vec![height(), value("carrier", vec![])]).is_ok());
+
+    // Missing, renamed, mistyped and mismatched nested fields
+    assert!(check(vec![value("carrier", vec![])]).is_err());
+    assert!(check(vec![value("sender", vec![]), height()]).is_err());
+    let mut carrier = value("carrier", vec![]);
+    carrier.set_data_type(property::PropertySchema_DataType::STRING);
+    assert!(check(vec![carrier, height()]).is_err());
+    let width = value("box", vec![value("width", vec![])]);
+    assert!(check(vec![value("carrier", vec![]), width]).is_err());
+    assert!(check(vec![value("carrier", vec![]), nested_value(1000)]).is_err());
+}
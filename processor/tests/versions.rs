@@ -0,0 +1,225 @@
+// Copyright (c) The dgc.network
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of the family versions: that each version's payloads decode to
+//! the same actions, which actions each version can carry, and that the
+//! handler refuses versions it does not know.
+
+extern crate dgc_rest_api_tp;
+extern crate protobuf;
+extern crate sawtooth_sdk;
+
+mod common;
+
+use protobuf::{Message, RepeatedField};
+use sawtooth_sdk::processor::handler::ApplyError;
+
+use dgc_rest_api_tp::addressing::Family;
+use dgc_rest_api_tp::context::{make_process_request, MemoryContext};
+use dgc_rest_api_tp::handler::TransactionHandlerDGC;
+use dgc_rest_api_tp::messages::*;
+use dgc_rest_api_tp::transaction::*;
+
+use common::{new_context, public_key, request, request_v1, TIMESTAMP};
+
+fn answer(response: payload::AnswerProposalAction_Response) -> payload::AnswerProposalAction {
+    let mut answer = payload::AnswerProposalAction::new();
+    answer.set_proposal_id("proposal-1".to_string());
+    answer.set_response(response);
+    answer
+}
+
+fn settle(close: bool) -> payload_v2::SettleProposalAction {
+    let mut exchange = proposal::Exchange::new();
+    exchange.set_buy_proposal_id("proposal-1".to_string());
+    exchange.set_sell_proposal_id("proposal-2".to_string());
+    let mut settle = payload_v2::SettleProposalAction::new();
+    settle.set_proposal_id("proposal-1".to_string());
+    settle.set_dg_coin_amount(12.5);
+    settle.set_close(close);
+    settle.set_exchanges(RepeatedField::from_vec(vec![exchange]));
+    settle
+}
+
+/// One action of each kind both versions carry.
+fn shared_actions() -> Vec<PayloadAction> {
+    let mut participant = payload::CreateParticipantAction::new();
+    participant.set_name("alice".to_string());
+    participant.set_email("alice@example.com".to_string());
+    let mut record = payload::CreateRecordAction::new();
+    record.set_record_id("crate-1".to_string());
+    record.set_table("crate".to_string());
+    let mut finalize = payload::FinalizeRecordAction::new();
+    finalize.set_record_id("crate-1".to_string());
+    let mut table = payload::CreateTableAction::new();
+    table.set_name("crate".to_string());
+    let mut update = payload::UpdatePropertiesAction::new();
+    update.set_record_id("crate-1".to_string());
+    let mut proposal = payload::CreateProposalAction::new();
+    proposal.set_proposal_id("proposal-1".to_string());
+    proposal.set_role(proposal::Proposal_Role::buyDGC);
+    proposal.set_dg_coin_amount(10.0);
+    let mut revoke = payload::RevokeReporterAction::new();
+    revoke.set_record_id("crate-1".to_string());
+    revoke.set_reporter_id(public_key(1));
+
+    vec![
+        PayloadAction::CreateParticipant(participant),
+        PayloadAction::CreateRecord(record),
+        PayloadAction::FinalizeRecord(finalize),
+        PayloadAction::CreateTable(table),
+        PayloadAction::UpdateProperties(update),
+        PayloadAction::CreateProposal(proposal),
+        PayloadAction::AnswerProposal(answer(payload::AnswerProposalAction_Response::ACCEPT)),
+        PayloadAction::AnswerProposal(answer(payload::AnswerProposalAction_Response::REJECT)),
+        PayloadAction::AnswerProposal(answer(payload::AnswerProposalAction_Response::CANCEL)),
+        PayloadAction::RevokeReporter(revoke),
+    ]
+}
+
+/// One action of each kind only version 2 carries.
+fn version_2_actions() -> Vec<PayloadAction> {
+    let mut counter = payload_v2::AnswerProposalV2Action::new();
+    counter.set_proposal_id("proposal-1".to_string());
+    counter.set_response(payload_v2::AnswerProposalV2Action_Response::COUNTER);
+    let mut status = payload_v2::SetParticipantStatusAction::new();
+    status.set_participant_id(public_key(1));
+    let mut credit = payload_v2::SetCreditLimitAction::new();
+    credit.set_participant_id(public_key(1));
+
+    vec![
+        PayloadAction::CounterProposal(counter),
+        PayloadAction::Migrate(payload_v2::MigrateAction::new()),
+        PayloadAction::UpdateParticipant(payload_v2::UpdateParticipantAction::new()),
+        PayloadAction::RotateKey(payload_v2::RotateKeyAction::new()),
+        PayloadAction::SetParticipantStatus(status),
+        PayloadAction::SetRoles(payload_v2::SetRolesAction::new()),
+        PayloadAction::CreateOrganization(payload_v2::CreateOrganizationAction::new()),
+        PayloadAction::SetOrganizationMember(payload_v2::SetOrganizationMemberAction::new()),
+        PayloadAction::CreateMultisigPolicy(payload_v2::CreateMultisigPolicyAction::new()),
+        PayloadAction::ExpireProposals(payload_v2::ExpireProposalsAction::new()),
+        PayloadAction::SetCreditLimit(credit),
+        PayloadAction::RepayCredit(payload_v2::RepayCreditAction::new()),
+        PayloadAction::WithdrawApproval(payload_v2::WithdrawApprovalAction::new()),
+    ]
+}
+
+fn encode_v2(action: PayloadAction) -> Vec<u8> {
+    make_payload_v2(action, TIMESTAMP).write_to_bytes().unwrap()
+}
+
+fn decode(version: &str, bytes: &[u8]) -> PayloadAction {
+    let (action, timestamp) = parse_payload(version, bytes)
+        .expect("version is supported")
+        .expect("payload decodes");
+    assert_eq!(timestamp, TIMESTAMP);
+    action
+}
+
+#[test]
+fn version_2_payloads_decode_to_the_same_action() {
+    for action in shared_actions().into_iter().chain(version_2_actions()) {
+        let name = action.name();
+        let bytes = encode_v2(action);
+        let decoded = decode(FAMILY_VERSION_2, &bytes);
+        assert_eq!(decoded.name(), name);
+        assert_eq!(encode_v2(decoded), bytes, "{} changed", name);
+    }
+}
+
+#[test]
+fn version_1_1_payloads_decode_to_the_same_action() {
+    for action in shared_actions() {
+        let name = action.name();
+        let bytes = make_payload(action, TIMESTAMP).unwrap().write_to_bytes().unwrap();
+        let decoded = decode(FAMILY_VERSION, &bytes);
+        assert_eq!(decoded.name(), name);
+        let again = make_payload(decoded, TIMESTAMP).unwrap().write_to_bytes().unwrap();
+        assert_eq!(again, bytes, "{} changed", name);
+    }
+}
+
+#[test]
+fn version_2_actions_have_no_version_1_1_payload() {
+    for action in version_2_actions() {
+        let name = action.name();
+        match make_payload(action, TIMESTAMP) {
+            Err(TransactionError::UnsupportedAction(_)) => (),
+            other => panic!("{} was given a version 1.1 payload: {:?}", name, other.is_ok()),
+        }
+    }
+}
+
+#[test]
+fn settlements_stand_for_the_automatic_answers() {
+    for &close in &[false, true] {
+        let v1 = make_payload(PayloadAction::SettleProposal(settle(close)), TIMESTAMP).unwrap();
+        assert_eq!(v1.get_action(), payload::PayloadDGC_Action::ANSWER_PROPOSAL);
+        let answer = v1.get_answer_proposal();
+        assert_eq!(answer, &settlement_answer(&settle(close)));
+        let response = if close {
+            payload::AnswerProposalAction_Response::autoCLOSE
+        } else {
+            payload::AnswerProposalAction_Response::autoOPEN
+        };
+        assert_eq!(answer.get_response(), response);
+        assert_eq!(answer.get_dg_coin_amount(), 12.5);
+        assert_eq!(answer.get_exchanges().len(), 1);
+
+        let v2 = make_payload_v2(PayloadAction::AnswerProposal(answer.clone()), TIMESTAMP);
+        assert_eq!(v2.get_action(), payload_v2::PayloadV2_Action::SETTLE_PROPOSAL);
+        assert_eq!(v2.get_settle_proposal(), &settle(close));
+    }
+}
+
+#[test]
+fn version_2_answers_carry_only_the_proposal_and_response() {
+    let mut accept = answer(payload::AnswerProposalAction_Response::ACCEPT);
+    accept.set_role(proposal::Proposal_Role::transferOwnership);
+    accept.set_record_id("crate-1".to_string());
+    accept.set_dg_coin_amount(3.0);
+
+    let bytes = encode_v2(PayloadAction::AnswerProposal(accept));
+    match decode(FAMILY_VERSION_2, &bytes) {
+        PayloadAction::AnswerProposal(decoded) => {
+            assert_eq!(decoded, answer(payload::AnswerProposalAction_Response::ACCEPT));
+        }
+        other => panic!("decoded as {}", other.name()),
+    }
+}
+
+#[test]
+fn both_versions_apply_the_same_way() {
+    let signer = public_key(0);
+    let mut participant = payload::CreateParticipantAction::new();
+    participant.set_name("alice".to_string());
+    let action = PayloadAction::CreateParticipant(participant);
+    let handler = TransactionHandlerDGC::new();
+
+    let mut v1 = new_context();
+    v1.apply(&handler, &request_v1(&signer, action.clone(), TIMESTAMP)).unwrap();
+    let mut v2 = new_context();
+    v2.apply(&handler, &request(&signer, action, TIMESTAMP)).unwrap();
+    assert_eq!(v1.state(), v2.state());
+}
+
+#[test]
+fn unknown_versions_are_refused() {
+    assert!(parse_payload("3", &[]).is_none());
+    assert!(parse_payload("1.0", &[]).is_none());
+
+    let signer = public_key(0);
+    let mut participant = payload::CreateParticipantAction::new();
+    participant.set_name("alice".to_string());
+    let payload = encode_v2(PayloadAction::CreateParticipant(participant));
+    let request = make_process_request(&Family::default(), "3", &signer, &payload, "sig");
+
+    let mut context = MemoryContext::new();
+    match context.apply(&TransactionHandlerDGC::new(), &request) {
+        Err(ApplyError::InvalidTransaction(msg)) => {
+            assert_eq!(msg, "Unsupported family version: 3");
+        }
+        other => panic!("version 3 was not refused: {:?}", other),
+    }
+    assert!(context.state().is_empty());
+}
@@ -144,7 +144,7 @@ router.use(authHandler)
 router.get('/participants', handle(participants.list))
 router.get('/participants/:publicKey', handle(participants.fetch))
 router.post('/createParticipant', handleBody(participants.create))
-//router.post('/updateParticipants', handleBody(participants.update))
+router.post('/updateParticipants', handleBody(participants.update))
 
 router.post('/authorization', handleBody(auth.authorize))
 router.post('/makePrivateKey', handleBody(auth.makePrivateKey))
@@ -158,6 +158,7 @@ router.post('/retrieveRecords', handleBody(records.retrieveRecords))
 router.post('/deleteRecords', handleBody(records.deleteRecords))
 
 router.post('/applyDGCoinCredit', handleBody(proposals.applyDGCoinCredit))
+router.post('/answerDGCoinCredit', handleBody(proposals.answerDGCoinCreditProposal))
 router.post('/buyDGCoinProposal', handleBody(proposals.buyDGCoinProposal))
 router.post('/sellDGCoinProposal', handleBody(proposals.sellDGCoinProposal))
 router.post('/transferDGCoinProposal', handleBody(proposals.transferDGCoinProposal))
@@ -15,11 +15,11 @@ const fetch = ({ publicKey, authedKey }) => db.fetch(publicKey, publicKey === au
 
 const create = params => transaction.create(params)
 
-//const update = params => transaction.update(params)
+const update = params => transaction.update(params)
 
 module.exports = {
   list,
   fetch,
-//  update,
+  update,
   create
 }
@@ -25,6 +25,10 @@ const applyDGCoinCredit = params => {
   return transaction.applyDGCoinCredit(params)
 }
 
+const answerDGCoinCreditProposal = params => {
+  return transaction.answerDGCoinCreditProposal(params)
+}
+
 const buyDGCoinProposal = params => {
   return transaction.buyDGCoinProposal(params)
 }
@@ -51,6 +55,7 @@ module.exports = {
   list,
   retrieveProposals,
   applyDGCoinCredit,
+  answerDGCoinCreditProposal,
   buyDGCoinProposal,
   sellDGCoinProposal,
   transferDGCoinProposal,
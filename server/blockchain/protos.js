@@ -56,6 +56,10 @@ const compile = () => {
       'CreateProposalAction',
       'AnswerProposalAction',
       'RevokeReporterAction'
+    ]),
+    loadProtos('payload_v2.proto', [
+      'PayloadV2',
+      'UpdateParticipantAction'
     ])
   ])
 }
@@ -45,6 +45,20 @@ r.connect({host: HOST, port: PORT})
       .then(() => {
         return r.db(NAME).table('participants').indexCreate('publicKey').run(conn)
       })
+      .then(() => {
+        console.log('Creating "organizations" table...')
+        return r.db(NAME).tableCreate('organizations').run(conn)
+      })
+      .then(() => {
+        return r.db(NAME).table('organizations').indexCreate('organizationId').run(conn)
+      })
+      .then(() => {
+        console.log('Creating "multisigPolicies" table...')
+        return r.db(NAME).tableCreate('multisigPolicies').run(conn)
+      })
+      .then(() => {
+        return r.db(NAME).table('multisigPolicies').indexCreate('policyId').run(conn)
+      })
       .then(() => {
         console.log('Creating "records" table...')
         return r.db(NAME).tableCreate('records').run(conn)
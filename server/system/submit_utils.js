@@ -14,9 +14,18 @@ const {
 } = require('sawtooth-sdk/protobuf')
 const protos = require('../blockchain/protos')
 
-const FAMILY_NAME = 'dgc_REST_api'
+// Each deployment sharing a validator network uses its own family name and
+// namespace, matching the processor's family.name and family.namespace
+const FAMILY_NAME = process.env.FAMILY_NAME || 'dgc_REST_api'
 const FAMILY_VERSION = '1.1'
-const NAMESPACE = '3bfc81'
+const FAMILY_VERSION_2 = '2'
+const NAMESPACE = process.env.NAMESPACE ||
+  createHash('sha512').update(FAMILY_NAME).digest('hex').slice(0, 6)
+
+// The processor reads the dgc.* settings and the BlockInfo namespace
+const settingsPart = part => createHash('sha256').update(part).digest('hex').slice(0, 16)
+const DGC_SETTINGS = '000000' + settingsPart('dgc')
+const BLOCK_INFO_NAMESPACE = '00b10c'
 
 const SERVER = process.env.SERVER || 'http://localhost:3000'
 const RETRY_WAIT = process.env.RETRY_WAIT || 5000
@@ -35,20 +44,26 @@ const awaitServerPubkey = () => {
   return awaitServerInfo().then(info => JSON.parse(info).pubkey)
 }
 
-const encodeHeader = (signerPublicKey, batcherPublicKey, payload) => {
+const encodeHeader = (signerPublicKey, batcherPublicKey, payload, familyVersion) => {
   return TransactionHeader.encode({
     signerPublicKey,
     batcherPublicKey,
     familyName: FAMILY_NAME,
-    familyVersion: FAMILY_VERSION,
-    inputs: [NAMESPACE],
+    familyVersion,
+    inputs: [NAMESPACE, DGC_SETTINGS, BLOCK_INFO_NAMESPACE],
     outputs: [NAMESPACE],
     nonce: (Math.random() * 10 ** 18).toString(36),
     payloadSha512: createHash('sha512').update(payload).digest('hex')
   }).finish()
 }
 
-const getTxnCreator = (privateKeyHex = null, batcherPublicKeyHex = null) => {
+// Payloads of family version 2 are made with encodeTimestampedPayloadV2 and
+// signed by a creator made with familyVersion FAMILY_VERSION_2
+const getTxnCreator = (
+  privateKeyHex = null,
+  batcherPublicKeyHex = null,
+  familyVersion = FAMILY_VERSION
+) => {
   const context = new secp256k1.Secp256k1Context()
   const privateKey = privateKeyHex === null
     ? context.newRandomPrivateKey()
@@ -60,7 +75,8 @@ const getTxnCreator = (privateKeyHex = null, batcherPublicKeyHex = null) => {
     : batcherPublicKeyHex
 
   return payload => {
-    const header = encodeHeader(signerPublicKey, batcherPublicKey, payload)
+    const header =
+      encodeHeader(signerPublicKey, batcherPublicKey, payload, familyVersion)
     const headerSignature = context.sign(header, privateKey)
     return Transaction.create({ header, headerSignature, payload })
   }
@@ -85,9 +101,17 @@ const encodeTimestampedPayload = message => {
   }, message)).finish()
 }
 
+const encodeTimestampedPayloadV2 = message => {
+  return protos.PayloadV2.encode(_.assign({
+    timestamp: Math.floor(Date.now() / 1000)
+  }, message)).finish()
+}
+
 module.exports = {
+  FAMILY_VERSION_2,
   awaitServerPubkey,
   getTxnCreator,
   submitTxns,
-  encodeTimestampedPayload
+  encodeTimestampedPayload,
+  encodeTimestampedPayloadV2
 }
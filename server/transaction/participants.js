@@ -7,10 +7,12 @@ const _ = require('lodash')
 const request = require('request-promise-native')
 const protos = require('../blockchain/protos')
 const {
+  FAMILY_VERSION_2,
   awaitServerPubkey,
   getTxnCreator,
   submitTxns,
-  encodeTimestampedPayload
+  encodeTimestampedPayload,
+  encodeTimestampedPayloadV2
 } = require('../system/submit_utils')
 
 const SERVER = process.env.SERVER || 'http://localhost:3000'
@@ -31,12 +33,14 @@ const create =  params  => {
     }
   })
 
-  // Create Participant
+  // Create Participant. A recovery key can only be given afterwards, with
+  // UPDATE_PARTICIPANT
   .then(() => {
     const participantPayload = encodeTimestampedPayload({
       action: protos.PayloadDGC.Action.CREATE_PARTICIPANT,
       createParticipant: protos.CreateParticipantAction.create({ 
-        name: params.data.name 
+        name: params.data.name,
+        email: params.data.email
       })
     })
     const participantTxns = [ createTxn(params.privateKey, participantPayload) ]
@@ -61,6 +65,33 @@ const create =  params  => {
   })
 }
 
+// Replaces the participant's name and email with UPDATE_PARTICIPANT, which
+// is only available in family version 2. The recovery key is only replaced
+// when one is given, and removed when removeRecoveryKey is set
+const update = params => {
+  return protos.compile()
+  .then(awaitServerPubkey)
+  .then(batcherPublicKey => {
+    const createTxn = getTxnCreator(
+      params.privateKey, batcherPublicKey, FAMILY_VERSION_2)
+    const participantPayload = encodeTimestampedPayloadV2({
+      action: protos.PayloadV2.Action.UPDATE_PARTICIPANT,
+      updateParticipant: protos.UpdateParticipantAction.create({
+        name: params.data.name,
+        email: params.data.email,
+        recoveryKey: params.data.recoveryKey,
+        removeRecoveryKey: params.data.removeRecoveryKey
+      })
+    })
+    return submitTxns([ createTxn(participantPayload) ])
+  })
+  .catch(err => {
+    console.error(err.toString())
+    throw err
+  })
+}
+
 module.exports = {
-  create
+  create,
+  update
 }
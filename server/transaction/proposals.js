@@ -115,20 +115,22 @@ const applyDGCoinCredit = params => {
     })
     return creditDGCoin(proposal)
   })
+}
+
+/**
+ * The receiving participant accepts a DGC credit proposal
+ */
+const answerDGCoinCreditProposal = params => {
+  return Promise.resolve()
   .then(() => {
-    // Here is the Auto response for the answer
-    // Assume the receiving_participant = issuing_participant for the testing
-    // Eventually, should remove the below code one the system in production
-    let retrieveQuery = {}
-    let query = {}
-    query.proposalId = proposalId
-    retrieveQuery.query = query
-    proposals.retrieve(retrieveQuery).map(proposal => {
-      proposal.privateKey = params['privateKey']
-      proposal.receivingParticipant = proposal.issuingParticipant
-      proposal.dgCoinAmount = proposal.dgCoinAmount
-      return answerDGCoinCredit(proposal)
+    let proposal = {}
+    proposal.privateKey = params['privateKey']
+    Object.keys(params['data']).map(key => {
+      if (key == 'proposalId') {
+        proposal.proposalId = params['data']['proposalId']
+      }
     })
+    return answerDGCoinCredit(proposal)
   })
 }
 
@@ -668,6 +670,7 @@ const transferCreate = (records) => {
         proposalId: record.proposalId,
         receivingParticipant: record.receivingKey,
         recordId: record.recordId,
+        terms: record.terms,
       })
     })
     return submitTxns(custodianTxns)
@@ -751,7 +754,8 @@ const authorizeCreate = (records) => {
         proposalId: record.proposalId,
         receivingParticipant: record.receivingKey,
         recordId: record.recordId,
-        properties: record.reportableProperties
+        properties: record.reportableProperties,
+        terms: record.terms,
       })
     })
     return submitTxns(authorizeTxns)
@@ -848,6 +852,7 @@ const answerReporterAuthorize = params => {
 
 module.exports = {
   applyDGCoinCredit,
+  answerDGCoinCreditProposal,
   buyDGCoinProposal,
   sellDGCoinProposal,
   transferDGCoinProposal,